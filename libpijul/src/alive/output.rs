@@ -5,6 +5,7 @@ use crate::output::FileError;
 use crate::pristine::*;
 use crate::vector2::Vector2;
 use crate::vertex_buffer::VertexBuffer;
+use crate::HashSet;
 
 #[derive(Debug)]
 struct ConflictStackElt {
@@ -12,6 +13,9 @@ struct ConflictStackElt {
     side: usize,
     idx: usize,
     id: usize,
+    /// Lines deleted by the changes of this conflict, see
+    /// `VertexBuffer::conflict_base`.
+    base: Vec<u8>,
 }
 
 fn output_conflict<T: ChannelTxnT, B: VertexBuffer, P: ChangeStore>(
@@ -28,12 +32,21 @@ fn output_conflict<T: ChannelTxnT, B: VertexBuffer, P: ChangeStore>(
         side: 0,
         idx: 0,
         id: 0,
+        base: Vec::new(),
     }];
     let mut is_zombie = None;
     let mut id = 0;
+    // Last vertex output, used to find the base of order conflicts.
+    let mut last = None;
     while let Some(mut elt) = stack.pop() {
         let n_sides = elt.conflict.len();
         if n_sides > 1 && elt.side == 0 && elt.idx == 0 {
+            if line_buf.wants_conflict_base() {
+                if let Some(last) = last {
+                    elt.base =
+                        conflict_base(changes, txn, channel, graph, sccs, &elt.conflict, last)?
+                }
+            }
             let txn = txn.read();
             let channel = channel.read();
             elt.conflict.sort_by(|a, b| {
@@ -79,6 +92,9 @@ fn output_conflict<T: ChannelTxnT, B: VertexBuffer, P: ChangeStore>(
                 if let Some(id) = is_zombie.take() {
                     line_buf.end_zombie_conflict::<P>(id)?;
                 }
+                if elt.side == 1 && line_buf.wants_conflict_base() {
+                    line_buf.conflict_base(elt.id, &elt.base)?;
+                }
                 match elt.conflict[elt.side].path[elt.idx] {
                     PathElement::Scc { scc } => {
                         let vid = sccs[scc][0];
@@ -104,6 +120,9 @@ fn output_conflict<T: ChannelTxnT, B: VertexBuffer, P: ChangeStore>(
                             &mut id,
                             line_buf,
                         )?;
+                        if let Some(&v) = sccs[scc].last() {
+                            last = Some(graph[v].vertex)
+                        }
                         elt.idx += 1;
                     }
                     PathElement::Conflict { ref mut sides } => {
@@ -115,6 +134,7 @@ fn output_conflict<T: ChannelTxnT, B: VertexBuffer, P: ChangeStore>(
                             idx: 0,
                             conflict: sides,
                             id,
+                            base: Vec::new(),
                         });
                         break 'outer;
                     }
@@ -145,6 +165,77 @@ fn output_conflict<T: ChannelTxnT, B: VertexBuffer, P: ChangeStore>(
     Ok(())
 }
 
+/// The contents of the vertices deleted by the changes of the sides
+/// of a conflict, reachable from `before` (the last vertex before the
+/// conflict) through deleted edges.
+fn conflict_base<T: ChannelTxnT, P: ChangeStore>(
+    changes: &P,
+    txn: &ArcTxn<T>,
+    channel: &ChannelRef<T>,
+    graph: &Graph,
+    sccs: &Vector2<VertexId>,
+    sides: &[Path],
+    before: Vertex<ChangeId>,
+) -> Result<Vec<u8>, FileError<P::Error, T>> {
+    let mut side_changes = HashSet::default();
+    let mut stack: Vec<&Path> = sides.iter().collect();
+    while let Some(side) = stack.pop() {
+        for elt in side.path.iter() {
+            match elt {
+                PathElement::Scc { scc } => {
+                    side_changes.extend(sccs[*scc].iter().map(|v| graph[*v].vertex.change))
+                }
+                PathElement::Conflict { sides } => stack.extend(sides.iter()),
+            }
+        }
+    }
+    let mut vertices = Vec::new();
+    {
+        let txn = txn.read();
+        let channel = channel.read();
+        let txn_graph = txn.graph(&*channel);
+        let mut visited = HashSet::default();
+        let mut stack = vec![before];
+        while let Some(v) = stack.pop() {
+            if v != before {
+                vertices.push(v)
+            }
+            let len = stack.len();
+            for e in iter_adjacent(
+                &*txn,
+                txn_graph,
+                v,
+                EdgeFlags::DELETED,
+                EdgeFlags::DELETED | EdgeFlags::BLOCK,
+            )? {
+                let e = e?;
+                if !side_changes.contains(&e.introduced_by()) {
+                    continue;
+                }
+                let dest = *txn.find_block(txn_graph, e.dest()).unwrap();
+                if visited.insert(dest) && !is_alive(&*txn, txn_graph, &dest)? {
+                    stack.push(dest)
+                }
+            }
+            // Visit the children in the order of the edges.
+            stack[len..].reverse();
+        }
+    }
+    let mut base = Vec::new();
+    for v in vertices {
+        let len = base.len();
+        base.resize(len + (v.end - v.start), 0);
+        changes
+            .get_contents(
+                |p| txn.read().get_external(&p).unwrap().map(|x| x.into()),
+                v,
+                &mut base[len..],
+            )
+            .map_err(FileError::Changestore)?;
+    }
+    Ok(base)
+}
+
 impl PathElement {
     fn oldest_vertex<T: ChannelTxnT, C: ChangeStore>(
         &self,
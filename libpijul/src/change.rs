@@ -29,6 +29,9 @@ pub use change_file::*;
 
 pub mod noenc;
 
+mod split;
+pub use split::{join_lines, split_lines};
+
 #[derive(Debug, Error)]
 pub enum ChangeError {
     #[error("Version mismatch: got {}", got)]
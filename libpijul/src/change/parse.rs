@@ -574,6 +574,7 @@ fn parse_perms(input: &str) -> IResult<&str, PrintablePerms> {
     alt((
         value(PrintablePerms::IsDir, tag("+dx")),
         value(PrintablePerms::IsExecutable, tag("+x")),
+        value(PrintablePerms::IsSymlink, tag("+l")),
         value(PrintablePerms::IsFile, tag("")),
     ))(input)
 }
@@ -19,6 +19,7 @@ pub enum PrintablePerms {
     IsDir,
     IsExecutable,
     IsFile,
+    IsSymlink,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -211,6 +212,8 @@ impl PrintablePerms {
     pub fn from_metadata(perms: InodeMetadata) -> Self {
         if perms.0 & 0o1000 == 0o1000 {
             PrintablePerms::IsDir
+        } else if perms.is_symlink() {
+            PrintablePerms::IsSymlink
         } else if perms.0 & 0o100 == 0o100 {
             PrintablePerms::IsExecutable
         } else {
@@ -223,6 +226,7 @@ impl PrintablePerms {
             PrintablePerms::IsDir => 0o1100,
             PrintablePerms::IsExecutable => 0o100,
             PrintablePerms::IsFile => 0o0,
+            PrintablePerms::IsSymlink => return InodeMetadata::SYMLINK,
         })
     }
 }
@@ -242,6 +246,7 @@ impl fmt::Display for PrintablePerms {
                 PrintablePerms::IsDir => " +dx",
                 PrintablePerms::IsExecutable => " +x",
                 PrintablePerms::IsFile => "",
+                PrintablePerms::IsSymlink => " +l",
             }
         )
     }
@@ -766,6 +771,7 @@ impl Arbitrary for PrintablePerms {
             PrintablePerms::IsDir,
             PrintablePerms::IsExecutable,
             PrintablePerms::IsFile,
+            PrintablePerms::IsSymlink,
         ])
         .unwrap()
     }
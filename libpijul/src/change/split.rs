@@ -0,0 +1,244 @@
+//! Splitting hunks at line boundaries, in order to record only some
+//! of the lines of an edit.
+use super::*;
+use crate::changestore::ChangeStore;
+
+type GlobalHunk = Hunk<Option<Hash>, Local>;
+type Edges = Vec<NewEdge<Option<Hash>>>;
+
+/// Split an `Edit` or `Replacement` hunk into hunks of one line each:
+/// first the deleted lines, then the inserted ones. Returns `None`
+/// for other hunks, and for hunks of a single line.
+///
+/// Inserted lines share the context of the original hunk, so the
+/// parts selected from a hunk must be joined back with [`join_lines`]
+/// before making a change.
+pub fn split_lines<C: ChangeStore>(
+    changes: &C,
+    hunk: &GlobalHunk,
+    contents: &[u8],
+) -> Result<Option<Vec<GlobalHunk>>, C::Error> {
+    let (deleted, inserted, local, encoding) = match hunk {
+        Hunk::Edit {
+            change: Atom::EdgeMap(e),
+            local,
+            encoding,
+        } => (Some(e), None, local, encoding),
+        Hunk::Edit {
+            change: Atom::NewVertex(n),
+            local,
+            encoding,
+        } => (None, Some(n), local, encoding),
+        Hunk::Replacement {
+            change: Atom::EdgeMap(e),
+            replacement: Atom::NewVertex(n),
+            local,
+            encoding,
+        } => (Some(e), Some(n), local, encoding),
+        _ => return Ok(None),
+    };
+    let mut parts = Vec::new();
+    if let Some(e) = deleted {
+        if !e.edges.iter().all(|e| e.flag.contains(EdgeFlags::DELETED)) {
+            return Ok(None);
+        }
+        for edges in split_deletion(changes, &e.edges)? {
+            parts.push(Hunk::Edit {
+                change: Atom::EdgeMap(EdgeMap {
+                    edges,
+                    inode: e.inode,
+                }),
+                local: local.clone(),
+                encoding: encoding.clone(),
+            })
+        }
+    }
+    if let Some(n) = inserted {
+        let mut start = n.start;
+        for (i, line) in contents[n.start.us()..n.end.us()]
+            .split_inclusive(|c| *c == b'\n')
+            .enumerate()
+        {
+            let end = start + line.len();
+            parts.push(Hunk::Edit {
+                change: Atom::NewVertex(NewVertex {
+                    start,
+                    end,
+                    ..n.clone()
+                }),
+                local: Local {
+                    path: local.path.clone(),
+                    line: local.line + i,
+                },
+                encoding: encoding.clone(),
+            });
+            start = end
+        }
+    }
+    if parts.len() > 1 {
+        Ok(Some(parts))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Group the deleted vertices into lines, splitting the vertices
+/// that span several lines.
+fn split_deletion<C: ChangeStore>(
+    changes: &C,
+    edges: &[NewEdge<Option<Hash>>],
+) -> Result<Vec<Edges>, C::Error> {
+    let mut lines = Vec::new();
+    let mut current = Vec::new();
+    let mut buf = Vec::new();
+    let mut i = 0;
+    while i < edges.len() {
+        let to = edges[i].to;
+        let mut j = i + 1;
+        while j < edges.len() && edges[j].to == to {
+            j += 1
+        }
+        let vertex_edges = &edges[i..j];
+        i = j;
+
+        buf.resize(to.end - to.start, 0);
+        changes.get_contents_ext(to, &mut buf)?;
+        // The parts of a vertex after the first one are only reached
+        // through the block edge, as in `diff::delete`.
+        let block = vertex_edges
+            .iter()
+            .find(|e| e.previous.contains(EdgeFlags::BLOCK));
+        if block.is_none() || buf.is_empty() {
+            current.extend(vertex_edges.iter().cloned());
+            if buf.ends_with(b"\n") {
+                lines.push(std::mem::take(&mut current))
+            }
+            continue;
+        }
+        let mut start = to.start;
+        for line in buf.split_inclusive(|c| *c == b'\n') {
+            let to_ = Vertex {
+                start,
+                end: start + line.len(),
+                ..to
+            };
+            if start == to.start {
+                current.extend(vertex_edges.iter().map(|e| NewEdge {
+                    to: to_,
+                    ..e.clone()
+                }))
+            } else if let Some(block) = block {
+                current.push(NewEdge {
+                    from: to_.start_pos(),
+                    to: to_,
+                    ..block.clone()
+                })
+            }
+            if line.ends_with(b"\n") {
+                lines.push(std::mem::take(&mut current))
+            }
+            start = to_.end
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current)
+    }
+    Ok(lines)
+}
+
+/// Join the parts of a hunk returned by [`split_lines`] into a single
+/// hunk, keeping only the parts for which `selected` is `true`. The
+/// result is a `Replacement` if there are both deleted and inserted
+/// lines. Parts that aren't `Edit` hunks are returned unchanged.
+///
+/// Like in `git add -p`, the inserted lines are placed after the
+/// deleted lines that weren't selected. If they aren't contiguous in
+/// `contents`, they are copied at the end of `contents`.
+pub fn join_lines(
+    parts: Vec<GlobalHunk>,
+    selected: &[bool],
+    contents: &mut Vec<u8>,
+) -> Vec<GlobalHunk> {
+    let mut result = Vec::new();
+    let mut deleted: Option<EdgeMap<Option<Hash>>> = None;
+    let mut kept = None;
+    let mut inserted = Vec::new();
+    let mut local = None;
+    let mut encoding = None;
+    for (part, selected) in parts.into_iter().zip(selected.iter()) {
+        match part {
+            Hunk::Edit {
+                change: Atom::EdgeMap(e),
+                ..
+            } if !selected => kept = e.edges.last().map(|e| e.to),
+            _ if !selected => {}
+            Hunk::Edit {
+                change: Atom::EdgeMap(e),
+                local: l,
+                encoding: enc,
+            } => {
+                local.get_or_insert(l);
+                encoding = enc;
+                if let Some(ref mut deleted) = deleted {
+                    deleted.edges.extend(e.edges)
+                } else {
+                    deleted = Some(e)
+                }
+            }
+            Hunk::Edit {
+                change: Atom::NewVertex(n),
+                local: l,
+                encoding: enc,
+            } => {
+                local.get_or_insert(l);
+                encoding = enc;
+                inserted.push(n)
+            }
+            part => result.push(part),
+        }
+    }
+    let inserted = if let Some(first) = inserted.first() {
+        let mut n = first.clone();
+        if inserted.windows(2).all(|w| w[0].end == w[1].start) {
+            n.end = inserted.last().unwrap().end
+        } else {
+            n.start = ChangePosition(contents.len().into());
+            for v in inserted.iter() {
+                contents.extend_from_within(v.start.us()..v.end.us())
+            }
+            n.end = ChangePosition(contents.len().into());
+            contents.push(0)
+        }
+        if let Some(kept) = kept {
+            n.up_context = vec![kept.end_pos()]
+        }
+        Some(n)
+    } else {
+        None
+    };
+    let local = if let Some(local) = local {
+        local
+    } else {
+        return result;
+    };
+    result.push(match (deleted, inserted) {
+        (Some(deleted), Some(inserted)) => Hunk::Replacement {
+            change: Atom::EdgeMap(deleted),
+            replacement: Atom::NewVertex(inserted),
+            local,
+            encoding,
+        },
+        (Some(deleted), None) => Hunk::Edit {
+            change: Atom::EdgeMap(deleted),
+            local,
+            encoding,
+        },
+        (None, Some(inserted)) => Hunk::Edit {
+            change: Atom::NewVertex(inserted),
+            local,
+            encoding,
+        },
+        (None, None) => unreachable!(),
+    });
+    result
+}
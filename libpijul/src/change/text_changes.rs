@@ -186,6 +186,31 @@ impl LocalChange<Hunk<Option<Hash>, Local>, Author> {
         }
         Ok(())
     }
+
+    /// Write a single hunk, which may not be in this change (for
+    /// example a part of one of its hunks), without the header and
+    /// dependencies. Dependencies are numbered as in [`Self::write`].
+    pub fn write_hunk<W: WriteChangeLine, C: ChangeStore>(
+        &self,
+        changes: &C,
+        hunk: &Hunk<Option<Hash>, Local>,
+        mut w: W,
+    ) -> Result<(), TextSerError<C::Error>> {
+        let mut hashes = HashMap::default();
+        let mut i = 2;
+        for dep in self.dependencies.iter() {
+            hashes.insert(*dep, i);
+            i += 1;
+        }
+        self.write_all_deps(|change| {
+            if let Entry::Vacant(e) = hashes.entry(change) {
+                e.insert(i);
+                i += 1;
+            }
+            Ok(())
+        })?;
+        hunk.write(changes, &hashes, &self.contents, &mut w)
+    }
 }
 
 impl Change {
@@ -634,12 +659,7 @@ impl Hunk<Option<Hash>, Local> {
                 add.start = ChangePosition(contents_.len().into());
                 add.flag = EdgeFlags::FOLDER | EdgeFlags::BLOCK;
                 let meta = FileMetadata {
-                    metadata: InodeMetadata(match perms {
-                        // TODO: deduplicate
-                        PrintablePerms::IsDir => 0o1100,
-                        PrintablePerms::IsExecutable => 0o100,
-                        PrintablePerms::IsFile => 0,
-                    }),
+                    metadata: perms.to_metadata(),
                     basename: &name,
                     encoding: None,
                 };
@@ -688,11 +708,7 @@ impl Hunk<Option<Hash>, Local> {
                 contents,
             } => {
                 let meta = FileMetadata {
-                    metadata: InodeMetadata(match perms {
-                        PrintablePerms::IsDir => 0o1100,
-                        PrintablePerms::IsExecutable => 0o100,
-                        PrintablePerms::IsFile => 0,
-                    }),
+                    metadata: perms.to_metadata(),
                     basename: &name,
                     encoding: encoding.clone(),
                 };
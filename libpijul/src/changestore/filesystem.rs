@@ -3,11 +3,16 @@ use crate::change::{Change, ChangeFile};
 use crate::pristine::{Base32, ChangeId, Hash, Merkle, Vertex};
 use std::cell::RefCell;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+mod index;
+pub use index::{IndexEntry, IndexError, INDEX_FILE};
 
 /// A file system change store.
 pub struct FileSystem {
     change_cache: RefCell<lru_cache::LruCache<ChangeId, ChangeFile>>,
     changes_dir: PathBuf,
+    index: Option<Arc<index::Index>>,
 }
 
 impl Clone for FileSystem {
@@ -16,6 +21,7 @@ impl Clone for FileSystem {
         FileSystem {
             changes_dir: self.changes_dir.clone(),
             change_cache: RefCell::new(lru_cache::LruCache::new(len)),
+            index: self.index.clone(),
         }
     }
 }
@@ -32,6 +38,8 @@ pub enum Error {
     Persist(#[from] tempfile::PersistError),
     #[error(transparent)]
     Tag(#[from] crate::tag::TagError),
+    #[error(transparent)]
+    Index(#[from] IndexError),
 }
 
 pub fn push_filename(changes_dir: &mut PathBuf, hash: &Hash) {
@@ -82,11 +90,119 @@ impl FileSystem {
 
     /// Construct a `FileSystem`, starting from the root of the
     /// repository (i.e. the parent of the `.pijul` directory).
+    ///
+    /// The index of the changes directory is used if it exists, see
+    /// [`FileSystem::build_index`].
     pub fn from_changes(changes_dir: PathBuf, cap: usize) -> Self {
         std::fs::create_dir_all(&changes_dir).unwrap();
+        let index_path = changes_dir.join(INDEX_FILE);
+        let index = if index_path.exists() {
+            match index::Index::open(&index_path) {
+                Ok(index) => Some(Arc::new(index)),
+                Err(e) => {
+                    warn!("Could not open the change index: {:?}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
         FileSystem {
             changes_dir,
             change_cache: RefCell::new(lru_cache::LruCache::new(cap)),
+            index,
+        }
+    }
+
+    /// Whether this change store has an index.
+    pub fn has_index(&self) -> bool {
+        self.index.is_some()
+    }
+
+    /// Create the index of this change store from scratch, replacing
+    /// the existing one if any, and return the number of changes
+    /// indexed. Once created, the index is kept up to date by this
+    /// change store.
+    #[allow(clippy::result_large_err)]
+    pub fn build_index(&mut self) -> Result<usize, Error> {
+        self.remove_index()?;
+        let index = index::Index::open(&self.changes_dir.join(INDEX_FILE))?;
+        let mut n = 0;
+        for dir in std::fs::read_dir(&self.changes_dir)? {
+            let dir = dir?;
+            if !dir.file_type()?.is_dir() {
+                continue;
+            }
+            for f in std::fs::read_dir(dir.path())? {
+                let path = f?.path();
+                if path.extension() != Some("change".as_ref()) {
+                    continue;
+                }
+                let mut h = dir.file_name().to_string_lossy().to_string();
+                h.push_str(&path.file_stem().unwrap().to_string_lossy());
+                let hash = if let Some(hash) = Hash::from_base32(h.as_bytes()) {
+                    hash
+                } else {
+                    continue;
+                };
+                if let Some(entry) = self.read_entry(&hash)? {
+                    index.add(hash, entry)?;
+                    n += 1
+                }
+            }
+        }
+        self.index = Some(Arc::new(index));
+        Ok(n)
+    }
+
+    /// Delete the index of this change store.
+    #[allow(clippy::result_large_err)]
+    pub fn remove_index(&mut self) -> Result<(), Error> {
+        self.index = None;
+        Ok(index::Index::remove(&self.changes_dir.join(INDEX_FILE))?)
+    }
+
+    /// The index entry of a change, read from its file. Changes
+    /// without their contents are not indexed.
+    #[allow(clippy::result_large_err)]
+    fn read_entry(&self, hash: &Hash) -> Result<Option<IndexEntry>, Error> {
+        let path = self.filename(hash);
+        let p = crate::change::ChangeFile::open(*hash, path.to_str().unwrap())?;
+        if !p.has_contents() {
+            return Ok(None);
+        }
+        Ok(Some(IndexEntry {
+            header: p.hashed().header.clone(),
+            dependencies: p.hashed().dependencies.clone(),
+            size: std::fs::metadata(&path)?.len(),
+        }))
+    }
+
+    /// The index entry of a change, indexing it if it isn't already.
+    #[allow(clippy::result_large_err)]
+    pub fn get_index_entry(&self, hash: &Hash) -> Result<Option<IndexEntry>, Error> {
+        let index = if let Some(ref index) = self.index {
+            index
+        } else {
+            return Ok(None);
+        };
+        if let Some(entry) = index.get(hash)? {
+            return Ok(Some(entry));
+        }
+        let entry = self.read_entry(hash)?;
+        if let Some(ref entry) = entry {
+            index.add(*hash, entry.clone())?
+        }
+        Ok(entry)
+    }
+
+    /// The size of the change file of `hash`, in bytes.
+    #[allow(clippy::result_large_err)]
+    pub fn get_size(&self, hash: &Hash) -> Result<u64, Error> {
+        if let Some(entry) = self.get_index_entry(hash)? {
+            Ok(entry.size)
+        } else {
+            Ok(std::fs::metadata(self.filename(hash))?.len())
         }
     }
 
@@ -137,6 +253,12 @@ impl FileSystem {
         if let Some(ref change_id) = change_id {
             self.change_cache.borrow_mut().remove(change_id);
         }
+        // This may replace a change without its contents.
+        if let Some(ref index) = self.index {
+            index
+                .del(hash)
+                .map_err(|e| std::io::Error::other(e.to_string()))?
+        }
         Ok(())
     }
 }
@@ -158,11 +280,23 @@ impl ChangeStore for FileSystem {
     }
 
     fn get_header(&self, h: &Hash) -> Result<ChangeHeader, Self::Error> {
+        if let Some(entry) = self.get_index_entry(h)? {
+            return Ok(entry.header);
+        }
         let path = self.filename(h);
         let p = crate::change::ChangeFile::open(*h, &path.to_str().unwrap())?;
         Ok(p.hashed().header.clone())
     }
 
+    fn get_dependencies(&self, h: &Hash) -> Result<Vec<Hash>, Self::Error> {
+        if let Some(entry) = self.get_index_entry(h)? {
+            return Ok(entry.dependencies);
+        }
+        let path = self.filename(h);
+        let p = crate::change::ChangeFile::open(*h, path.to_str().unwrap())?;
+        Ok(p.hashed().dependencies.clone())
+    }
+
     fn get_tag_header(&self, h: &Merkle) -> Result<ChangeHeader, Self::Error> {
         let path = self.tag_filename(h);
         let mut p = crate::tag::OpenTagFile::open(&path, h)?;
@@ -242,15 +376,26 @@ impl ChangeStore for FileSystem {
             return Err(E::from(Error::from(e)));
         }
         debug!("file_name = {:?}", file_name);
-        if let Err(e) = f.persist(file_name) {
+        if let Err(e) = f.persist(&file_name) {
             return Err(E::from(Error::from(e)));
         }
+        if let Some(ref index) = self.index {
+            let entry = IndexEntry {
+                header: p.hashed.header.clone(),
+                dependencies: p.hashed.dependencies.clone(),
+                size: std::fs::metadata(&file_name).map_err(Error::from)?.len(),
+            };
+            index.put(hash, entry).map_err(Error::from)?
+        }
         Ok(hash)
     }
     fn del_change(&self, hash: &Hash) -> Result<bool, Self::Error> {
         let file_name = self.filename(hash);
         debug!("file_name = {:?}", file_name);
         let result = std::fs::remove_file(&file_name).is_ok();
+        if let Some(ref index) = self.index {
+            index.del(hash)?
+        }
         std::fs::remove_dir(file_name.parent().unwrap()).unwrap_or(()); // fails silently if there are still changes with the same 2-letter prefix.
         Ok(result)
     }
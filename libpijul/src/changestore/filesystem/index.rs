@@ -0,0 +1,201 @@
+//! An index of the change store, in `.pijul/changes/index`, caching
+//! the headers, dependencies and sizes of changes, so that they can
+//! be read without opening and decompressing the change files.
+//!
+//! Since changes are addressed by their hash, entries never become
+//! stale, and the index doesn't need to know about the change files
+//! written by other means than [`FileSystem`](super::FileSystem):
+//! these are simply indexed the first time they are read. Changes
+//! without their contents are not indexed, since their size changes
+//! when their contents are downloaded.
+use crate::change::ChangeHeader;
+use crate::pristine::{Hash, Pair, SerializedHash, L64};
+use ::sanakirja::btree::{self, UDb};
+use ::sanakirja::{Commit, Env, RootDb};
+use std::path::Path;
+use std::sync::Mutex;
+use thiserror::Error;
+
+/// The name of the index file, in the changes directory.
+pub const INDEX_FILE: &str = "index";
+
+const ROOT: usize = 0;
+
+/// Number of roots of the index, which is also the number of lock
+/// files Sanakirja creates next to it.
+const N_ROOTS: usize = 2;
+
+/// Sanakirja values are limited to 510 bytes, so entries are
+/// serialised and split into chunks of at most this size.
+const CHUNK_SIZE: usize = 500;
+
+/// Number of entries read from change files to collect before
+/// writing them to the index.
+const BATCH_SIZE: usize = 256;
+
+#[derive(Debug, Error)]
+pub enum IndexError {
+    #[error(transparent)]
+    Sanakirja(#[from] ::sanakirja::Error),
+    #[error("Corrupt change index: {0}")]
+    Bincode(#[from] bincode::Error),
+}
+
+type Chunks = UDb<Pair<SerializedHash, L64>, [u8]>;
+
+/// What the index knows about a change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub header: ChangeHeader,
+    pub dependencies: Vec<Hash>,
+    /// The size of the change file, in bytes.
+    pub size: u64,
+}
+
+pub(super) struct Index {
+    env: Env,
+    /// Entries read from change files, not yet written.
+    pending: Mutex<Vec<(Hash, IndexEntry)>>,
+}
+
+impl Index {
+    pub(super) fn open(path: &Path) -> Result<Self, IndexError> {
+        Ok(Index {
+            env: Env::new(path, 1 << 20, N_ROOTS)?,
+            pending: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Delete the index at `path`, along with its lock files.
+    pub(super) fn remove(path: &Path) -> Result<(), std::io::Error> {
+        let lock_files = (0..N_ROOTS).map(|n| path.with_extension(format!("lock{}", n)));
+        for p in std::iter::once(path.to_path_buf()).chain(lock_files) {
+            match std::fs::remove_file(&p) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn get(&self, hash: &Hash) -> Result<Option<IndexEntry>, IndexError> {
+        if let Some((_, e)) = self.pending.lock().unwrap().iter().find(|(h, _)| h == hash) {
+            return Ok(Some(e.clone()));
+        }
+        let txn = Env::txn_begin(&self.env)?;
+        let db: Chunks = if let Some(db) = txn.root_db(ROOT) {
+            db
+        } else {
+            return Ok(None);
+        };
+        let key = Pair {
+            a: hash.into(),
+            b: 0u64.into(),
+        };
+        let mut buf = Vec::new();
+        for x in btree::iter(&txn, &db, Some((&key, None)))? {
+            let (k, v) = x?;
+            if k.a != key.a {
+                break;
+            }
+            buf.extend_from_slice(v)
+        }
+        if buf.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(bincode::deserialize(&buf)?))
+        }
+    }
+
+    /// Add an entry read from a change file. The entries are written
+    /// in batches, the last one when the index is dropped.
+    pub(super) fn add(&self, hash: Hash, entry: IndexEntry) -> Result<(), IndexError> {
+        let mut pending = self.pending.lock().unwrap();
+        pending.push((hash, entry));
+        if pending.len() >= BATCH_SIZE {
+            self.write(&mut pending, &[])?
+        }
+        Ok(())
+    }
+
+    /// Write `entry` now (along with the pending entries).
+    pub(super) fn put(&self, hash: Hash, entry: IndexEntry) -> Result<(), IndexError> {
+        let mut pending = self.pending.lock().unwrap();
+        pending.push((hash, entry));
+        self.write(&mut pending, &[])
+    }
+
+    pub(super) fn del(&self, hash: &Hash) -> Result<(), IndexError> {
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|(h, _)| h != hash);
+        self.write(&mut pending, &[*hash])
+    }
+
+    /// Write the pending entries, and delete the entries of `deleted`.
+    fn write(
+        &self,
+        pending: &mut Vec<(Hash, IndexEntry)>,
+        deleted: &[Hash],
+    ) -> Result<(), IndexError> {
+        let mut txn = Env::mut_txn_begin(&self.env)?;
+        let mut db: Chunks = if let Some(db) = txn.root_db(ROOT) {
+            db
+        } else {
+            unsafe { btree::create_db_(&mut txn)? }
+        };
+        for h in deleted.iter().chain(pending.iter().map(|(h, _)| h)) {
+            del_entry(&mut txn, &mut db, h)?
+        }
+        for (h, entry) in pending.drain(..) {
+            let buf = bincode::serialize(&entry)?;
+            for (i, chunk) in buf.chunks(CHUNK_SIZE).enumerate() {
+                let key = Pair {
+                    a: (&h).into(),
+                    b: (i as u64).into(),
+                };
+                btree::put(&mut txn, &mut db, &key, chunk)?;
+            }
+        }
+        txn.set_root(ROOT, db.db.into());
+        txn.commit()?;
+        Ok(())
+    }
+}
+
+fn del_entry(
+    txn: &mut ::sanakirja::MutTxn<&Env, ()>,
+    db: &mut Chunks,
+    hash: &Hash,
+) -> Result<(), IndexError> {
+    let key = Pair {
+        a: hash.into(),
+        b: 0u64.into(),
+    };
+    let mut n = 0;
+    for x in btree::iter(txn, db, Some((&key, None)))? {
+        let (k, _) = x?;
+        if k.a != key.a {
+            break;
+        }
+        n += 1
+    }
+    for i in 0..n {
+        let key = Pair {
+            a: hash.into(),
+            b: (i as u64).into(),
+        };
+        btree::del(txn, db, &key, None)?;
+    }
+    Ok(())
+}
+
+impl Drop for Index {
+    fn drop(&mut self) {
+        let mut pending = self.pending.lock().unwrap();
+        if !pending.is_empty() {
+            if let Err(e) = self.write(&mut pending, &[]) {
+                warn!("Could not write the change index: {:?}", e)
+            }
+        }
+    }
+}
@@ -4,6 +4,7 @@ use super::Line;
 pub enum Algorithm {
     Myers,
     Patience,
+    Histogram,
 }
 
 impl Default for Algorithm {
@@ -43,6 +44,16 @@ pub(super) fn diff(
             lines_b.len(),
         )
         .unwrap_or(()),
+        Algorithm::Histogram => super::histogram::diff(
+            &mut dd,
+            lines_a,
+            0,
+            lines_a.len(),
+            lines_b,
+            0,
+            lines_b.len(),
+        )
+        .unwrap_or(()),
     }
     dd.into_inner()
 }
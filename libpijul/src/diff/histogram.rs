@@ -0,0 +1,191 @@
+//! Histogram diff, as in JGit and Git: recursively split the files
+//! around the longest common region made of the lines that occur the
+//! least often in the old version. Lines such as blank lines or
+//! closing braces are therefore not used as anchors when better ones
+//! exist, which gives more readable hunks on source code.
+use std::collections::HashMap;
+use std::ops::Index;
+
+use super::Line;
+
+/// Above this number of occurrences, lines are not used as anchors,
+/// and we fall back to Myers' algorithm if no other line can be used.
+const MAX_CHAIN: usize = 64;
+
+/// Maximal recursion depth, after which Myers' algorithm is used.
+const MAX_DEPTH: usize = 1024;
+
+pub(super) fn diff<'a, S, D>(
+    d: &mut D,
+    a: &S,
+    a0: usize,
+    a1: usize,
+    b: &S,
+    b0: usize,
+    b1: usize,
+) -> Result<(), D::Error>
+where
+    S: Index<usize, Output = Line<'a>> + ?Sized,
+    D: diffs::Diff,
+{
+    Histogram { a, b }.diff_rec(d, a0, a1, b0, b1, 0)?;
+    d.finish()
+}
+
+/// The old and new versions being compared.
+struct Histogram<'s, S: ?Sized> {
+    a: &'s S,
+    b: &'s S,
+}
+
+impl<'s, 'a, S> Histogram<'s, S>
+where
+    S: Index<usize, Output = Line<'a>> + ?Sized,
+{
+    fn diff_rec<D: diffs::Diff>(
+        &self,
+        d: &mut D,
+        mut a0: usize,
+        mut a1: usize,
+        mut b0: usize,
+        mut b1: usize,
+        depth: usize,
+    ) -> Result<(), D::Error> {
+        let (a, b) = (self.a, self.b);
+        let mut prefix = 0;
+        while a0 + prefix < a1 && b0 + prefix < b1 && a[a0 + prefix] == b[b0 + prefix] {
+            prefix += 1
+        }
+        if prefix > 0 {
+            d.equal(a0, b0, prefix)?;
+        }
+        a0 += prefix;
+        b0 += prefix;
+        let mut suffix = 0;
+        while a0 + suffix < a1 && b0 + suffix < b1 && a[a1 - 1 - suffix] == b[b1 - 1 - suffix] {
+            suffix += 1
+        }
+        a1 -= suffix;
+        b1 -= suffix;
+
+        if a0 == a1 {
+            if b0 < b1 {
+                d.insert(a0, b0, b1 - b0)?;
+            }
+        } else if b0 == b1 {
+            d.delete(a0, a1 - a0, b0)?;
+        } else if let Some(r) = (depth < MAX_DEPTH)
+            .then(|| self.longest_region(a0, a1, b0, b1))
+            .flatten()
+        {
+            self.diff_rec(d, a0, r.a, b0, r.b, depth + 1)?;
+            d.equal(r.a, r.b, r.len)?;
+            self.diff_rec(d, r.a + r.len, a1, r.b + r.len, b1, depth + 1)?;
+        } else {
+            diffs::myers::diff(&mut NoFinish(d), a, a0, a1, b, b0, b1)?;
+        }
+
+        if suffix > 0 {
+            d.equal(a1, b1, suffix)?;
+        }
+        Ok(())
+    }
+
+    fn longest_region(&self, a0: usize, a1: usize, b0: usize, b1: usize) -> Option<Region> {
+        let (a, b) = (self.a, self.b);
+        // Lines are keyed by their contents only, the actual comparison
+        // (which also accounts for cyclic lines and end markers) is done
+        // by `==` below.
+        let mut occurrences: HashMap<&[u8], Vec<usize>> = HashMap::new();
+        for i in a0..a1 {
+            occurrences.entry(a[i].l).or_default().push(i)
+        }
+        let count = |i: usize| occurrences.get(a[i].l).map(|x| x.len()).unwrap_or(0);
+
+        let mut best: Option<Region> = None;
+        let mut j = b0;
+        while j < b1 {
+            let mut next = j + 1;
+            let positions = if let Some(p) = occurrences.get(b[j].l) {
+                p
+            } else {
+                j = next;
+                continue;
+            };
+            if positions.len() > best.as_ref().map(|r| r.count).unwrap_or(MAX_CHAIN) {
+                j = next;
+                continue;
+            }
+            for &i in positions {
+                if a[i] != b[j] {
+                    continue;
+                }
+                let (mut sa, mut sb) = (i, j);
+                while sa > a0 && sb > b0 && a[sa - 1] == b[sb - 1] {
+                    sa -= 1;
+                    sb -= 1;
+                }
+                let (mut ea, mut eb) = (i + 1, j + 1);
+                while ea < a1 && eb < b1 && a[ea] == b[eb] {
+                    ea += 1;
+                    eb += 1;
+                }
+                let region = Region {
+                    a: sa,
+                    b: sb,
+                    len: ea - sa,
+                    count: (sa..ea).map(count).min().unwrap_or(0),
+                };
+                let better = if let Some(ref best) = best {
+                    region.count < best.count
+                        || (region.count == best.count && region.len > best.len)
+                } else {
+                    true
+                };
+                if better {
+                    best = Some(region)
+                }
+                next = next.max(eb);
+            }
+            j = next;
+        }
+        best
+    }
+}
+
+/// A common region of length `len`, starting at `a` in the old
+/// version and `b` in the new one.
+struct Region {
+    a: usize,
+    b: usize,
+    len: usize,
+    /// Smallest number of occurrences in the old version of the
+    /// lines of this region.
+    count: usize,
+}
+
+/// Forwards everything to the underlying `Diff` except `finish`, so
+/// that other algorithms can be used on parts of the files.
+struct NoFinish<'d, D>(&'d mut D);
+
+impl<'d, D: diffs::Diff> diffs::Diff for NoFinish<'d, D> {
+    type Error = D::Error;
+    fn equal(&mut self, old: usize, new: usize, len: usize) -> Result<(), D::Error> {
+        self.0.equal(old, new, len)
+    }
+    fn delete(&mut self, old: usize, len: usize, new: usize) -> Result<(), D::Error> {
+        self.0.delete(old, len, new)
+    }
+    fn insert(&mut self, old: usize, new: usize, new_len: usize) -> Result<(), D::Error> {
+        self.0.insert(old, new, new_len)
+    }
+    fn replace(
+        &mut self,
+        old: usize,
+        old_len: usize,
+        new: usize,
+        new_len: usize,
+    ) -> Result<(), D::Error> {
+        self.0.replace(old, old_len, new, new_len)
+    }
+}
@@ -7,6 +7,7 @@ use crate::text_encoding::Encoding;
 mod bin;
 
 mod diff;
+mod histogram;
 mod split;
 mod vertex_buffer;
 pub use diff::Algorithm;
@@ -17,6 +18,46 @@ lazy_static! {
     pub static ref DEFAULT_SEPARATOR: regex::bytes::Regex = regex::bytes::Regex::new("\n").unwrap();
 }
 
+/// Separators used instead of the default one to split files into
+/// "lines" when diffing, for the files whose path matches a
+/// pattern. Finer separators (for example between words) make
+/// smaller changes, and concurrent edits to different parts of the
+/// same line don't conflict.
+#[derive(Debug, Clone, Default)]
+pub struct Separators {
+    patterns: Vec<(regex::bytes::Regex, regex::bytes::Regex)>,
+}
+
+impl Separators {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `separator` for the files whose full path (relative to the
+    /// root of the repository) matches `path`. When several patterns
+    /// match, the one added first is used.
+    pub fn push(&mut self, path: regex::bytes::Regex, separator: regex::bytes::Regex) {
+        self.patterns.push((path, separator))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// The separator for `path`, or `default` if no pattern matches.
+    pub fn get<'a>(
+        &'a self,
+        path: &str,
+        default: &'a regex::bytes::Regex,
+    ) -> &'a regex::bytes::Regex {
+        self.patterns
+            .iter()
+            .find(|(p, _)| p.is_match(path.as_bytes()))
+            .map(|(_, sep)| sep)
+            .unwrap_or(default)
+    }
+}
+
 #[derive(Clone, Copy)]
 struct Line<'a> {
     l: &'a [u8],
@@ -160,7 +201,7 @@ impl Recorded {
         separator: &regex::bytes::Regex,
     ) -> Result<(), DiffError<P::Error, T>> {
         self.largest_file = self.largest_file.max(b.len() as u64);
-        let mut d = vertex_buffer::Diff::new(inode, path.clone(), a);
+        let mut d = vertex_buffer::Diff::new(inode, path.clone(), a, self.conflict_style);
         output_graph(changes, txn, channel, &mut d, a, &mut self.redundant)?;
         let txn = txn.read();
         let channel = channel.read();
@@ -14,6 +14,7 @@ pub(super) struct Diff {
     conflict_stack: Vec<Conflict>,
     pub conflict_ends: Vec<ConflictEnds>,
     pub cyclic_conflict_bytes: Vec<(usize, usize)>,
+    style: vertex_buffer::ConflictStyle,
 }
 
 #[derive(Debug, Clone)]
@@ -44,6 +45,8 @@ pub enum ConflictMarker {
     Begin,
     Next,
     End,
+    /// The base section of a conflict, with its marker.
+    Base,
 }
 
 #[derive(Debug)]
@@ -59,6 +62,7 @@ impl Diff {
         inode: Position<Option<ChangeId>>,
         path: String,
         graph: &crate::alive::Graph,
+        style: vertex_buffer::ConflictStyle,
     ) -> Self {
         Diff {
             inode,
@@ -79,6 +83,7 @@ impl Diff {
                 conflict_type: ConflictType::Root,
             }],
             cyclic_conflict_bytes: Vec::new(),
+            style,
         }
     }
 }
@@ -219,6 +224,40 @@ impl vertex_buffer::VertexBuffer for Diff {
         self.output_conflict_marker(vertex_buffer::SEPARATOR, id, side)
     }
 
+    fn wants_conflict_base(&self) -> bool {
+        self.style == vertex_buffer::ConflictStyle::Diff3
+    }
+
+    fn conflict_base(&mut self, id: usize, base: &[u8]) -> Result<(), std::io::Error> {
+        if self.style != vertex_buffer::ConflictStyle::Diff3 {
+            return Ok(());
+        }
+        if let Some(line) = self.pos_a.last_mut() {
+            line.before_conflict = true
+        }
+        match self.contents_a.last() {
+            Some(&b'\n') | None => {}
+            _ => self.contents_a.push(b'\n'),
+        }
+        // The whole section is a single marker, so that deleting it
+        // or some of its lines doesn't delete anything.
+        let pos = self.contents_a.len();
+        use std::io::Write;
+        writeln!(self.contents_a, "{} {}", vertex_buffer::BASE_MARKER, id)?;
+        self.contents_a.extend_from_slice(base);
+        if !base.is_empty() && !base.ends_with(b"\n") {
+            self.contents_a.push(b'\n')
+        }
+        self.marker.insert(pos, ConflictMarker::Base);
+        self.pos_a.push(Vertex {
+            pos,
+            vertex: crate::pristine::Vertex::ROOT,
+            before_conflict: false,
+            conflict: self.conflict_stack.last().unwrap().counter,
+        });
+        Ok(())
+    }
+
     fn output_conflict_marker<C: ChangeStore>(
         &mut self,
         marker: &str,
@@ -239,7 +278,7 @@ impl vertex_buffer::VertexBuffer for Diff {
         }
         let pos = self.contents_a.len();
         use std::io::Write;
-        write!(self.contents_a, "{} {}", marker, id)?;
+        write!(self.contents_a, "{} {}", self.style.marker(marker), id)?;
         match sides {
             Some((changes, sides)) => {
                 for side in sides {
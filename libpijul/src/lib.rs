@@ -25,6 +25,7 @@ pub mod output;
 pub mod path;
 pub mod pristine;
 pub mod record;
+pub mod rename;
 pub mod small_string;
 mod text_encoding;
 mod unrecord;
@@ -64,7 +65,7 @@ pub enum RemoteError {
 
 pub use crate::apply::Workspace as ApplyWorkspace;
 pub use crate::apply::{apply_change_arc, ApplyError, LocalApplyError};
-pub use crate::diff::DEFAULT_SEPARATOR;
+pub use crate::diff::{Separators, DEFAULT_SEPARATOR};
 pub use crate::fs::{FsError, WorkingCopyIterator};
 pub use crate::output::{Archive, Conflict};
 pub use crate::pristine::{
@@ -1,5 +1,6 @@
 use super::*;
 use crate::changestore::ChangeStore;
+use crate::vertex_buffer::ConflictStyle;
 use crate::Conflict;
 use crate::{HashMap, HashSet};
 use std::collections::hash_map::Entry;
@@ -10,17 +11,116 @@ pub trait Archive {
     fn create_file(&mut self, path: &str, mtime: u64, perm: u16) -> Self::File;
     fn create_dir(&mut self, path: &str, mtime: u64, permissions: u16) -> Result<(), Self::Error>;
     fn close_file(&mut self, f: Self::File) -> Result<(), Self::Error>;
+    fn create_symlink(&mut self, path: &str, mtime: u64, target: &str) -> Result<(), Self::Error>;
 }
 
+/// The archive formats implemented in this module.
+#[cfg(feature = "tarball")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    #[cfg(feature = "zstd")]
+    TarZstd,
+    #[cfg(feature = "zip")]
+    Zip,
+}
+
+#[cfg(feature = "tarball")]
+impl ArchiveFormat {
+    /// The formats enabled by the features of this crate.
+    pub const ALL: &'static [ArchiveFormat] = &[
+        ArchiveFormat::Tar,
+        ArchiveFormat::TarGz,
+        #[cfg(feature = "zstd")]
+        ArchiveFormat::TarZstd,
+        #[cfg(feature = "zip")]
+        ArchiveFormat::Zip,
+    ];
+
+    /// The extension of files in this format, without the leading dot.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::TarGz => "tar.gz",
+            #[cfg(feature = "zstd")]
+            ArchiveFormat::TarZstd => "tar.zst",
+            #[cfg(feature = "zip")]
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+
+    /// Guess the format from the name of a file.
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        if name.ends_with(".tgz") {
+            return Some(ArchiveFormat::TarGz);
+        }
+        Self::ALL
+            .iter()
+            .find(|f| {
+                name.ends_with(f.extension())
+                    && name.as_bytes().get(name.len() - f.extension().len() - 1) == Some(&b'.')
+            })
+            .copied()
+    }
+
+    /// The name of an archive in this format called `name`, where the
+    /// extension of any other format is replaced with this format's.
+    pub fn file_name(&self, name: &str) -> String {
+        let stem = match Self::from_file_name(name) {
+            Some(f) if f == *self => return name.to_string(),
+            Some(_) if name.ends_with(".tgz") => &name[..name.len() - 4],
+            Some(f) => &name[..name.len() - f.extension().len() - 1],
+            None => name,
+        };
+        format!("{}.{}", stem, self.extension())
+    }
+}
+
+#[cfg(feature = "tarball")]
+impl std::fmt::Display for ArchiveFormat {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.write_str(self.extension())
+    }
+}
+
+#[cfg(feature = "tarball")]
+impl std::str::FromStr for ArchiveFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tgz" => Ok(ArchiveFormat::TarGz),
+            #[cfg(feature = "zstd")]
+            "tzst" => Ok(ArchiveFormat::TarZstd),
+            _ => Self::ALL
+                .iter()
+                .find(|f| f.extension() == s)
+                .copied()
+                .ok_or_else(|| format!("Unknown archive format: {:?}", s)),
+        }
+    }
+}
+
+#[cfg(any(feature = "tarball", feature = "zip"))]
+fn prefixed(prefix: &Option<String>, path: &str) -> String {
+    if let Some(ref prefix) = prefix {
+        prefix.clone() + path
+    } else {
+        path.to_string()
+    }
+}
+
+/// A tar archive, written to `W`. Compression is done by `W`, see
+/// the constructors.
 #[cfg(feature = "tarball")]
 pub struct Tarball<W: std::io::Write> {
-    pub archive: tar::Builder<flate2::write::GzEncoder<W>>,
+    pub archive: tar::Builder<W>,
     pub prefix: Option<String>,
     pub buffer: Vec<u8>,
     pub umask: u16,
 }
 
-#[cfg(feature = "tarball")]
+#[cfg(any(feature = "tarball", feature = "zip"))]
 pub struct File {
     buf: Vec<u8>,
     path: String,
@@ -28,7 +128,7 @@ pub struct File {
     mtime: u64,
 }
 
-#[cfg(feature = "tarball")]
+#[cfg(any(feature = "tarball", feature = "zip"))]
 impl std::io::Write for File {
     fn write(&mut self, buf: &[u8]) -> Result<usize, std::io::Error> {
         self.buf.write(buf)
@@ -39,11 +139,27 @@ impl std::io::Write for File {
 }
 
 #[cfg(feature = "tarball")]
-impl<W: std::io::Write> Tarball<W> {
+impl<W: std::io::Write> Tarball<flate2::write::GzEncoder<W>> {
+    /// A gzipped tarball.
     pub fn new(w: W, prefix: Option<String>, umask: u16) -> Self {
         let encoder = flate2::write::GzEncoder::new(w, flate2::Compression::best());
+        Tarball::uncompressed(encoder, prefix, umask)
+    }
+}
+
+#[cfg(all(feature = "tarball", feature = "zstd"))]
+impl<W: std::io::Write> Tarball<ZstdEncoder<W>> {
+    /// A tarball compressed with Zstandard.
+    pub fn zstd(w: W, prefix: Option<String>, umask: u16) -> Result<Self, std::io::Error> {
+        Ok(Tarball::uncompressed(ZstdEncoder::new(w)?, prefix, umask))
+    }
+}
+
+#[cfg(feature = "tarball")]
+impl<W: std::io::Write> Tarball<W> {
+    pub fn uncompressed(w: W, prefix: Option<String>, umask: u16) -> Self {
         Tarball {
-            archive: tar::Builder::new(encoder),
+            archive: tar::Builder::new(w),
             buffer: Vec::new(),
             prefix,
             umask,
@@ -59,11 +175,7 @@ impl<W: std::io::Write> Archive for Tarball<W> {
         self.buffer.clear();
         File {
             buf: std::mem::replace(&mut self.buffer, Vec::new()),
-            path: if let Some(ref prefix) = self.prefix {
-                prefix.clone() + path
-            } else {
-                path.to_string()
-            },
+            path: prefixed(&self.prefix, path),
             mtime,
             permissions: permissions & !self.umask,
         }
@@ -73,12 +185,8 @@ impl<W: std::io::Write> Archive for Tarball<W> {
         header.set_mode((permissions & !self.umask) as u32);
         header.set_mtime(mtime);
         header.set_entry_type(tar::EntryType::Directory);
-        if let Some(ref prefix) = self.prefix {
-            let path = prefix.clone() + path;
-            self.archive.append_data(&mut header, &path, &[][..])?;
-        } else {
-            self.archive.append_data(&mut header, &path, &[][..])?;
-        }
+        let path = prefixed(&self.prefix, path);
+        self.archive.append_data(&mut header, &path, &[][..])?;
         Ok(())
     }
 
@@ -93,6 +201,289 @@ impl<W: std::io::Write> Archive for Tarball<W> {
         self.buffer = file.buf;
         Ok(())
     }
+
+    fn create_symlink(&mut self, path: &str, mtime: u64, target: &str) -> Result<(), Self::Error> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(0);
+        header.set_mode(0o777);
+        header.set_mtime(mtime);
+        header.set_entry_type(tar::EntryType::Symlink);
+        let path = prefixed(&self.prefix, path);
+        self.archive.append_link(&mut header, path, target)?;
+        Ok(())
+    }
+}
+
+/// A streaming Zstandard compressor. Like `flate2`'s encoders, the
+/// stream is ended when the encoder is dropped, but errors are only
+/// reported by [`ZstdEncoder::finish`].
+#[cfg(all(feature = "tarball", feature = "zstd"))]
+pub struct ZstdEncoder<W: std::io::Write> {
+    w: Option<W>,
+    stream: zstd_seekable::CStream,
+    buf: Vec<u8>,
+}
+
+#[cfg(all(feature = "tarball", feature = "zstd"))]
+impl<W: std::io::Write> ZstdEncoder<W> {
+    const LEVEL: usize = 19;
+
+    pub fn new(w: W) -> Result<Self, std::io::Error> {
+        Ok(ZstdEncoder {
+            w: Some(w),
+            stream: zstd_seekable::CStream::new(Self::LEVEL).map_err(zstd_error)?,
+            buf: vec![0; zstd_seekable::CStream::out_size()],
+        })
+    }
+
+    /// Run `op` until the compressor has nothing left to output.
+    fn drain(&mut self, op: fn() -> zstd_seekable::EndDirective) -> Result<(), std::io::Error> {
+        let w = self.w.as_mut().unwrap();
+        loop {
+            let (out_pos, _, remaining) = self
+                .stream
+                .compress2(&mut self.buf, &[], op())
+                .map_err(zstd_error)?;
+            w.write_all(&self.buf[..out_pos])?;
+            if remaining == 0 {
+                return w.flush();
+            }
+        }
+    }
+
+    /// End the stream, and return the underlying writer.
+    pub fn finish(mut self) -> Result<W, std::io::Error> {
+        self.drain(|| zstd_seekable::EndDirective::End)?;
+        Ok(self.w.take().unwrap())
+    }
+}
+
+#[cfg(all(feature = "tarball", feature = "zstd"))]
+fn zstd_error(e: zstd_seekable::Error) -> std::io::Error {
+    std::io::Error::other(e)
+}
+
+#[cfg(all(feature = "tarball", feature = "zstd"))]
+impl<W: std::io::Write> std::io::Write for ZstdEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, std::io::Error> {
+        let mut consumed = 0;
+        while consumed == 0 && !buf.is_empty() {
+            let (out_pos, in_pos, _) = self
+                .stream
+                .compress2(&mut self.buf, buf, zstd_seekable::EndDirective::Continue)
+                .map_err(zstd_error)?;
+            self.w.as_mut().unwrap().write_all(&self.buf[..out_pos])?;
+            consumed = in_pos;
+        }
+        Ok(consumed)
+    }
+    fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.drain(|| zstd_seekable::EndDirective::Flush)
+    }
+}
+
+#[cfg(all(feature = "tarball", feature = "zstd"))]
+impl<W: std::io::Write> Drop for ZstdEncoder<W> {
+    fn drop(&mut self) {
+        if self.w.is_some() {
+            self.drain(|| zstd_seekable::EndDirective::End)
+                .unwrap_or(())
+        }
+    }
+}
+
+/// A zip archive. Zip archives need to be written to a seekable
+/// output.
+#[cfg(feature = "zip")]
+pub struct Zip<W: std::io::Write + std::io::Seek> {
+    pub archive: zip::ZipWriter<W>,
+    pub prefix: Option<String>,
+    pub buffer: Vec<u8>,
+    pub umask: u16,
+}
+
+#[cfg(feature = "zip")]
+impl<W: std::io::Write + std::io::Seek> Zip<W> {
+    pub fn new(w: W, prefix: Option<String>, umask: u16) -> Self {
+        Zip {
+            archive: zip::ZipWriter::new(w),
+            buffer: Vec::new(),
+            prefix,
+            umask,
+        }
+    }
+
+    fn options(mtime: u64, permissions: u16) -> zip::write::FileOptions {
+        use chrono::{Datelike, Timelike};
+        let options = zip::write::FileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .unix_permissions(permissions as u32);
+        // Zip timestamps are in local time, between 1980 and 2107. We
+        // use UTC, and the default date if the time is out of range.
+        let t = chrono::NaiveDateTime::from_timestamp_opt(mtime as i64, 0).and_then(|t| {
+            zip::DateTime::from_date_and_time(
+                u16::try_from(t.year()).ok()?,
+                t.month() as u8,
+                t.day() as u8,
+                t.hour() as u8,
+                t.minute() as u8,
+                t.second() as u8,
+            )
+            .ok()
+        });
+        if let Some(t) = t {
+            options.last_modified_time(t)
+        } else {
+            options
+        }
+    }
+}
+
+#[cfg(feature = "zip")]
+impl<W: std::io::Write + std::io::Seek> Archive for Zip<W> {
+    type File = File;
+    type Error = zip::result::ZipError;
+    fn create_file(&mut self, path: &str, mtime: u64, permissions: u16) -> Self::File {
+        self.buffer.clear();
+        File {
+            buf: std::mem::take(&mut self.buffer),
+            path: prefixed(&self.prefix, path),
+            mtime,
+            permissions: permissions & !self.umask,
+        }
+    }
+    fn create_dir(&mut self, path: &str, mtime: u64, permissions: u16) -> Result<(), Self::Error> {
+        let options = Self::options(mtime, permissions & !self.umask);
+        self.archive
+            .add_directory(prefixed(&self.prefix, path), options)
+    }
+
+    fn close_file(&mut self, file: Self::File) -> Result<(), Self::Error> {
+        use std::io::Write;
+        let options = Self::options(file.mtime, file.permissions);
+        self.archive.start_file(file.path, options)?;
+        self.archive.write_all(&file.buf)?;
+        self.buffer = file.buf;
+        Ok(())
+    }
+
+    fn create_symlink(&mut self, path: &str, mtime: u64, target: &str) -> Result<(), Self::Error> {
+        let options = Self::options(mtime, 0o777);
+        self.archive
+            .add_symlink(prefixed(&self.prefix, path), target, options)
+    }
+}
+
+/// An archive where all entries have the same modification time,
+/// used to make archives reproducible.
+pub struct FixedMtime<A: Archive> {
+    pub archive: A,
+    pub mtime: u64,
+}
+
+impl<A: Archive> Archive for FixedMtime<A> {
+    type File = A::File;
+    type Error = A::Error;
+    fn create_file(&mut self, path: &str, _: u64, permissions: u16) -> Self::File {
+        self.archive.create_file(path, self.mtime, permissions)
+    }
+    fn create_dir(&mut self, path: &str, _: u64, permissions: u16) -> Result<(), Self::Error> {
+        self.archive.create_dir(path, self.mtime, permissions)
+    }
+    fn close_file(&mut self, f: Self::File) -> Result<(), Self::Error> {
+        self.archive.close_file(f)
+    }
+    fn create_symlink(&mut self, path: &str, _: u64, target: &str) -> Result<(), Self::Error> {
+        self.archive.create_symlink(path, self.mtime, target)
+    }
+}
+
+/// An archive in any of the formats of [`ArchiveFormat`].
+#[cfg(feature = "tarball")]
+pub enum ArchiveWriter<W: std::io::Write + std::io::Seek> {
+    Tar(Tarball<W>),
+    TarGz(Tarball<flate2::write::GzEncoder<W>>),
+    #[cfg(feature = "zstd")]
+    TarZstd(Tarball<ZstdEncoder<W>>),
+    #[cfg(feature = "zip")]
+    Zip(Zip<W>),
+}
+
+#[cfg(feature = "tarball")]
+impl<W: std::io::Write + std::io::Seek> ArchiveWriter<W> {
+    pub fn new(
+        format: ArchiveFormat,
+        w: W,
+        prefix: Option<String>,
+        umask: u16,
+    ) -> Result<Self, std::io::Error> {
+        Ok(match format {
+            ArchiveFormat::Tar => ArchiveWriter::Tar(Tarball::uncompressed(w, prefix, umask)),
+            ArchiveFormat::TarGz => ArchiveWriter::TarGz(Tarball::new(w, prefix, umask)),
+            #[cfg(feature = "zstd")]
+            ArchiveFormat::TarZstd => ArchiveWriter::TarZstd(Tarball::zstd(w, prefix, umask)?),
+            #[cfg(feature = "zip")]
+            ArchiveFormat::Zip => ArchiveWriter::Zip(Zip::new(w, prefix, umask)),
+        })
+    }
+
+    /// Write the end of the archive, and return the output.
+    pub fn finish(self) -> Result<W, std::io::Error> {
+        match self {
+            ArchiveWriter::Tar(t) => t.archive.into_inner(),
+            ArchiveWriter::TarGz(t) => t.archive.into_inner()?.finish(),
+            #[cfg(feature = "zstd")]
+            ArchiveWriter::TarZstd(t) => t.archive.into_inner()?.finish(),
+            #[cfg(feature = "zip")]
+            ArchiveWriter::Zip(mut z) => Ok(z.archive.finish()?),
+        }
+    }
+}
+
+#[cfg(feature = "tarball")]
+impl<W: std::io::Write + std::io::Seek> Archive for ArchiveWriter<W> {
+    type File = File;
+    type Error = std::io::Error;
+    fn create_file(&mut self, path: &str, mtime: u64, permissions: u16) -> Self::File {
+        match self {
+            ArchiveWriter::Tar(t) => t.create_file(path, mtime, permissions),
+            ArchiveWriter::TarGz(t) => t.create_file(path, mtime, permissions),
+            #[cfg(feature = "zstd")]
+            ArchiveWriter::TarZstd(t) => t.create_file(path, mtime, permissions),
+            #[cfg(feature = "zip")]
+            ArchiveWriter::Zip(z) => z.create_file(path, mtime, permissions),
+        }
+    }
+    fn create_dir(&mut self, path: &str, mtime: u64, permissions: u16) -> Result<(), Self::Error> {
+        match self {
+            ArchiveWriter::Tar(t) => t.create_dir(path, mtime, permissions),
+            ArchiveWriter::TarGz(t) => t.create_dir(path, mtime, permissions),
+            #[cfg(feature = "zstd")]
+            ArchiveWriter::TarZstd(t) => t.create_dir(path, mtime, permissions),
+            #[cfg(feature = "zip")]
+            ArchiveWriter::Zip(z) => Ok(z.create_dir(path, mtime, permissions)?),
+        }
+    }
+    fn close_file(&mut self, file: Self::File) -> Result<(), Self::Error> {
+        match self {
+            ArchiveWriter::Tar(t) => t.close_file(file),
+            ArchiveWriter::TarGz(t) => t.close_file(file),
+            #[cfg(feature = "zstd")]
+            ArchiveWriter::TarZstd(t) => t.close_file(file),
+            #[cfg(feature = "zip")]
+            ArchiveWriter::Zip(z) => Ok(z.close_file(file)?),
+        }
+    }
+    fn create_symlink(&mut self, path: &str, mtime: u64, target: &str) -> Result<(), Self::Error> {
+        match self {
+            ArchiveWriter::Tar(t) => t.create_symlink(path, mtime, target),
+            ArchiveWriter::TarGz(t) => t.create_symlink(path, mtime, target),
+            #[cfg(feature = "zstd")]
+            ArchiveWriter::TarZstd(t) => t.create_symlink(path, mtime, target),
+            #[cfg(feature = "zip")]
+            ArchiveWriter::Zip(z) => Ok(z.create_symlink(path, mtime, target)?),
+        }
+    }
 }
 
 #[derive(Error)]
@@ -178,7 +569,11 @@ pub(crate) fn archive<
         next_files.clear();
         next_prefix_basename = prefix.next();
 
-        for (a, mut b) in files.drain() {
+        // Output the files in a deterministic order, so that archives
+        // of the same state are identical.
+        let mut sorted: Vec<_> = files.drain().collect();
+        sorted.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (a, mut b) in sorted {
             debug!("files: {:?} {:?}", a, b);
             {
                 let txn_ = txn.read();
@@ -262,6 +657,33 @@ pub(crate) fn archive<
                         arch.create_dir(&path, latest_touch, 0o777)
                             .map_err(ArchiveError::A)?;
                     }
+                } else if output_item.meta.is_symlink() {
+                    let mut l = crate::alive::retrieve(
+                        &*txn_,
+                        txn_.graph(&channel_),
+                        output_item.pos,
+                        false,
+                    )?;
+                    let mut f = crate::vertex_buffer::ConflictsWriter::new(
+                        Vec::new(),
+                        &path,
+                        output_item.pos,
+                        &mut conflicts,
+                        ConflictStyle::default(),
+                    );
+                    std::mem::drop(channel_);
+                    std::mem::drop(txn_);
+                    crate::alive::output_graph(
+                        changes,
+                        txn,
+                        channel,
+                        &mut f,
+                        &mut l,
+                        &mut Vec::new(),
+                    )?;
+                    let target = String::from_utf8_lossy(&f.w);
+                    arch.create_symlink(&path, latest_touch, &target)
+                        .map_err(ArchiveError::A)?;
                 } else {
                     debug!("latest_touch: {:?}", latest_touch);
                     let mut l = crate::alive::retrieve(
@@ -279,9 +701,10 @@ pub(crate) fn archive<
                     {
                         let mut f = crate::vertex_buffer::ConflictsWriter::new(
                             &mut f,
-                            &output_item.path,
+                            &path,
                             output_item.pos,
                             &mut conflicts,
+                            ConflictStyle::default(),
                         );
                         std::mem::drop(channel_);
                         std::mem::drop(txn_);
@@ -7,6 +7,7 @@ use crate::changestore::ChangeStore;
 use crate::fs::create_new_inode;
 use crate::pristine::*;
 use crate::small_string::SmallString;
+use crate::vertex_buffer::ConflictStyle;
 use crate::working_copy::WorkingCopy;
 use crate::{alive, path, vertex_buffer};
 use crate::{HashMap, HashSet};
@@ -105,6 +106,7 @@ pub fn output_repository_no_pending<
     if_modified_since: Option<std::time::SystemTime>,
     n_workers: usize,
     salt: u64,
+    conflict_style: ConflictStyle,
 ) -> Result<BTreeSet<Conflict>, OutputError<P::Error, T, R::Error>>
 where
     T::Channel: Send + Sync + 'static,
@@ -121,6 +123,7 @@ where
         if_modified_since,
         n_workers,
         salt,
+        conflict_style,
     )?;
 
     del_redundant(txn.clone(), channel.clone(), &f)?;
@@ -146,6 +149,7 @@ pub fn output_repository_no_pending_<
     if_modified_since: Option<std::time::SystemTime>,
     n_workers: usize,
     salt: u64,
+    conflict_style: ConflictStyle,
 ) -> Result<BTreeSet<Conflict>, OutputError<P::Error, T, R::Error>>
 where
     T::Channel: Send + Sync + 'static,
@@ -162,6 +166,7 @@ where
         if_modified_since,
         n_workers,
         salt,
+        conflict_style,
     )?;
     Ok(c)
 }
@@ -178,6 +183,7 @@ fn output_loop<
     work: Arc<crossbeam_deque::Injector<(OutputItem, Inode, String, Option<String>)>>,
     stop: Arc<std::sync::atomic::AtomicBool>,
     t: usize,
+    conflict_style: ConflictStyle,
 ) -> Result<(Vec<Conflict>, Vec<Redundant>), OutputError<P::Error, T, R::Error>> {
     use crossbeam_deque::*;
     // let backoff = crossbeam_utils::Backoff::new();
@@ -199,10 +205,13 @@ fn output_loop<
                     inode,
                     path,
                     &mut forward,
+                    conflict_style,
                 )?;
-                debug!("setting permissions for {:?}", path);
-                repo.set_permissions(path, item.meta.permissions())
-                    .map_err(OutputError::WorkingCopy)?;
+                if !item.meta.is_symlink() {
+                    debug!("setting permissions for {:?}", path);
+                    repo.set_permissions(path, item.meta.permissions())
+                        .map_err(OutputError::WorkingCopy)?;
+                }
                 debug!("output {:?}", path);
             }
             Steal::Retry => {}
@@ -233,6 +242,7 @@ fn output_repository<
     if_modified_after: Option<std::time::SystemTime>,
     n_workers: usize,
     salt: u64,
+    conflict_style: ConflictStyle,
 ) -> Result<(BTreeSet<Conflict>, Vec<Redundant>), OutputError<P::Error, T, R::Error>>
 where
     T::Channel: Send + Sync + 'static,
@@ -248,7 +258,16 @@ where
         let channel = channel.clone();
         let changes = changes.clone();
         threads.push(std::thread::spawn(move || {
-            output_loop(&repo, &changes, txn, channel, work, stop, t + 1)
+            output_loop(
+                &repo,
+                &changes,
+                txn,
+                channel,
+                work,
+                stop,
+                t + 1,
+                conflict_style,
+            )
         }))
     }
 
@@ -301,7 +320,16 @@ where
         std::mem::swap(&mut files, &mut next_files);
     }
     stop.store(true, std::sync::atomic::Ordering::Relaxed);
-    let o = output_loop(repo, changes, txn.clone(), channel, work, stop, 0);
+    let o = output_loop(
+        repo,
+        changes,
+        txn.clone(),
+        channel,
+        work,
+        stop,
+        0,
+        conflict_style,
+    );
     for t in threads {
         let (a, b) = t.join().unwrap()?;
         for x in a.into_iter() {
@@ -758,6 +786,7 @@ fn output_item<T: ChannelTxnT + TreeTxnT, P: ChangeStore, W: WorkingCopy>(
     inode: Inode,
     path: &str,
     forward: &mut Vec<Redundant>,
+    conflict_style: ConflictStyle,
 ) -> Result<(), OutputError<P::Error, T, W::Error>> {
     if !repo.is_writable(path).map_err(OutputError::WorkingCopy)? {
         return Ok(());
@@ -769,11 +798,28 @@ fn output_item<T: ChannelTxnT + TreeTxnT, P: ChangeStore, W: WorkingCopy>(
         let channel = channel.read();
         retrieve(&*txn, txn.graph(&*channel), output_item.pos, false)?
     };
+    if output_item.meta.is_symlink() {
+        // The contents of a symbolic link are its target.
+        let mut f = vertex_buffer::ConflictsWriter::new(
+            Vec::new(),
+            path,
+            output_item.pos,
+            conflicts,
+            conflict_style,
+        );
+        alive::output_graph(changes, &txn, &channel, &mut f, &mut l, forward)
+            .map_err(PristineOutputError::from)?;
+        let target = String::from_utf8_lossy(&f.w);
+        return repo
+            .create_symlink(path, &target)
+            .map_err(OutputError::WorkingCopy);
+    }
     let w = repo
         .write_file(&path, inode)
         .map_err(OutputError::WorkingCopy)?;
     debug!("vertex_buffer");
-    let mut f = vertex_buffer::ConflictsWriter::new(w, &path, output_item.pos, conflicts);
+    let mut f =
+        vertex_buffer::ConflictsWriter::new(w, &path, output_item.pos, conflicts, conflict_style);
     debug!("outputting graph");
     alive::output_graph(changes, &txn, &channel, &mut f, &mut l, forward)
         .map_err(PristineOutputError::from)?;
@@ -1,13 +1,18 @@
 /// Metadata about an inode, including unix-style permissions and
-/// whether this inode is a directory.
+/// whether this inode is a directory or a symbolic link.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
 #[doc(hidden)]
 pub struct InodeMetadata(pub u16);
 const DIR_BIT: u16 = 0x200;
+const SYMLINK_BIT: u16 = 0x400;
 
 impl InodeMetadata {
     pub const DIR: Self = InodeMetadata(DIR_BIT);
 
+    /// Metadata of a symbolic link. The contents of a symbolic link
+    /// are the path it points to.
+    pub const SYMLINK: Self = InodeMetadata(SYMLINK_BIT);
+
     /// Create a new file metadata with the given Unix permissions,
     /// and "is directory" bit.
     pub fn new(perm: usize, is_dir: bool) -> Self {
@@ -41,8 +46,20 @@ impl InodeMetadata {
         self.0 & DIR_BIT != 0
     }
 
-    /// Tell whether this `InodeMetadata` is a file.
+    /// Tell whether this `InodeMetadata` is a file (and not a
+    /// symbolic link).
     pub fn is_file(&self) -> bool {
+        self.0 & (DIR_BIT | SYMLINK_BIT) == 0
+    }
+
+    /// Tell whether this `InodeMetadata` is a symbolic link.
+    pub fn is_symlink(&self) -> bool {
+        self.0 & SYMLINK_BIT != 0
+    }
+
+    /// Tell whether this inode has contents, i.e. is a file or a
+    /// symbolic link.
+    pub fn has_contents(&self) -> bool {
         self.0 & DIR_BIT == 0
     }
 
@@ -53,6 +70,6 @@ impl InodeMetadata {
 
     /// Set the metadata to be a file.
     pub fn unset_dir(&mut self) {
-        self.0 &= 0o777
+        self.0 &= !DIR_BIT
     }
 }
@@ -1851,11 +1851,12 @@ impl ChannelMutTxnT for MutTxn<()> {
     ) -> Result<(), TxnErr<Self::GraphError>> {
         debug!("put_tags {:?}", m);
         let mm: SerializedMerkle = m.into();
-        if btree::get(&self.txn, &channel, &n.into(), None)?.is_some() {
+        let tl: L64 = n.into();
+        // `btree::get` returns the first entry at or after `tl`.
+        if matches!(btree::get(&self.txn, &channel, &tl, None)?, Some((k, _)) if k == &tl) {
             debug!("already tagged");
             Ok(())
         } else {
-            let tl = n.into();
             let mut repl = vec![(tl, mm)];
             replay_tags(self, channel, tl, &mut repl)?;
             Ok(())
@@ -71,6 +71,11 @@ pub struct Builder {
     deleted_vertices: Arc<Mutex<HashSet<Position<ChangeId>>>>,
     pub force_rediff: bool,
     pub ignore_missing: bool,
+    /// Separators to use instead of the one given to `record` for
+    /// some of the files.
+    pub separators: crate::diff::Separators,
+    /// Style of the conflict markers in the working copy.
+    pub conflict_style: crate::vertex_buffer::ConflictStyle,
     pub contents: Arc<Mutex<Vec<u8>>>,
     new_root: Arc<Mutex<Option<(Position<Option<ChangeId>>, u64)>>>,
 }
@@ -103,6 +108,7 @@ pub struct Recorded {
     pub redundant: Vec<crate::alive::Redundant>,
     /// Force a re-diff
     force_rediff: bool,
+    pub(crate) conflict_style: crate::vertex_buffer::ConflictStyle,
     deleted_vertices: Arc<Mutex<HashSet<Position<ChangeId>>>>,
     recorded_inodes: Arc<Mutex<HashMap<Inode, Position<Option<ChangeId>>>>>,
     new_root: Arc<Mutex<Option<(Position<Option<ChangeId>>, u64)>>>,
@@ -115,6 +121,8 @@ impl Default for Builder {
             recorded_inodes: Arc::new(Mutex::new(HashMap::default())),
             force_rediff: false,
             ignore_missing: false,
+            separators: crate::diff::Separators::default(),
+            conflict_style: crate::vertex_buffer::ConflictStyle::default(),
             deleted_vertices: Arc::new(Mutex::new(HashSet::default())),
             contents: Arc::new(Mutex::new(Vec::new())),
             new_root: Arc::new(Mutex::new(None)),
@@ -144,6 +152,7 @@ impl Builder {
             oldest_change: std::time::SystemTime::UNIX_EPOCH,
             redundant: Vec::new(),
             force_rediff: self.force_rediff,
+            conflict_style: self.conflict_style,
             deleted_vertices: self.deleted_vertices.clone(),
             recorded_inodes: self.recorded_inodes.clone(),
             new_root: self.new_root.clone(),
@@ -311,6 +320,7 @@ impl Builder {
             let work = work.clone();
             let txn = txn.clone();
             let sep: regex::bytes::Regex = diff_separator.clone();
+            let separators = self.separators.clone();
             let cl = move || {
                 loop {
                     let (w, stop) = {
@@ -324,7 +334,7 @@ impl Builder {
                             &txn,
                             diff_algorithm,
                             stop_early,
-                            &sep,
+                            separators.get(&item.full_path, &sep),
                             &channel,
                             working_copy.clone(),
                             &changes,
@@ -525,7 +535,7 @@ impl Builder {
                     &txn,
                     diff_algorithm,
                     stop_early,
-                    diff_separator,
+                    self.separators.get(&item.full_path, diff_separator),
                     &channel,
                     working_copy.clone(),
                     changes,
@@ -663,7 +673,7 @@ impl Builder {
                     &txn,
                     diff_algorithm,
                     stop_early,
-                    &diff_separator,
+                    self.separators.get(&item.full_path, diff_separator),
                     &channel,
                     working_copy.clone(),
                     changes,
@@ -1004,7 +1014,7 @@ impl Recorded {
         contents.push(0);
         let inode_pos = ChangePosition(contents.len().into());
         contents.push(0);
-        let (contents_, encoding) = if meta.is_file() {
+        let (contents_, encoding) = if meta.has_contents() {
             let start = ChangePosition(contents.len().into());
             let encoding = working_copy.decode_file(&item.full_path, &mut contents)?;
             self.has_binary_files |= encoding.is_none();
@@ -1211,7 +1221,7 @@ impl Recorded {
                 former_parents[0].encoding.clone(),
             )?
         }
-        if new_meta.is_file()
+        if new_meta.has_contents()
             && (self.force_rediff
                 || modified_since_last_commit(
                     &*txn.read(),
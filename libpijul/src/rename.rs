@@ -0,0 +1,177 @@
+//! Detection of files moved without telling Pijul (for example by an
+//! editor or a refactoring tool). Tracked files missing from the
+//! working copy are compared with the files that appeared since the
+//! last record, and the pairs that are similar enough are moved in
+//! the tree, so that recording yields a file move instead of a
+//! deletion and an addition, preserving the history of the file.
+use crate::changestore::ChangeStore;
+use crate::pristine::*;
+use crate::working_copy::WorkingCopyRead;
+use crate::HashMap;
+
+/// A file that was moved from `from` to `to`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+    /// Similarity of the contents, in percent.
+    pub similarity: u8,
+}
+
+#[derive(Error)]
+pub enum RenameError<
+    C: std::error::Error + 'static,
+    W: std::error::Error + 'static,
+    T: GraphTxnT + TreeTxnT,
+> {
+    #[error(transparent)]
+    WorkingCopy(W),
+    #[error(transparent)]
+    File(#[from] crate::output::FileError<C, T>),
+    #[error(transparent)]
+    Fs(#[from] crate::fs::FsError<T>),
+    #[error(transparent)]
+    Txn(#[from] TxnErr<T::GraphError>),
+    #[error(transparent)]
+    Tree(#[from] TreeErr<T::TreeError>),
+}
+
+impl<C: std::error::Error + 'static, W: std::error::Error + 'static, T: GraphTxnT + TreeTxnT>
+    std::fmt::Debug for RenameError<C, W, T>
+{
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RenameError::WorkingCopy(e) => std::fmt::Debug::fmt(e, fmt),
+            RenameError::File(e) => std::fmt::Debug::fmt(e, fmt),
+            RenameError::Fs(e) => std::fmt::Debug::fmt(e, fmt),
+            RenameError::Txn(e) => std::fmt::Debug::fmt(e, fmt),
+            RenameError::Tree(e) => std::fmt::Debug::fmt(e, fmt),
+        }
+    }
+}
+
+/// Find the tracked files missing from `working_copy` whose contents
+/// are at least `threshold` percent similar to one of the new files,
+/// and move them in the tree. New files are the files added to the
+/// tree but never recorded, and the files in `untracked`.
+///
+/// Empty files are never considered, since any two of them would
+/// match.
+pub fn detect_renames<T: ChannelTxnT + TreeMutTxnT, W: WorkingCopyRead, C: ChangeStore>(
+    txn: &ArcTxn<T>,
+    channel: &ChannelRef<T>,
+    working_copy: &W,
+    changes: &C,
+    untracked: &[String],
+    threshold: u8,
+) -> Result<Vec<Rename>, RenameError<C::Error, W::Error, T>>
+where
+    W::Error: 'static,
+{
+    let mut missing = Vec::new();
+    let mut added = Vec::new();
+    {
+        let txn_ = txn.read();
+        for x in crate::fs::iter_working_copy(&*txn_, Inode::ROOT) {
+            let (inode, path, is_dir) = x.map_err(|e| RenameError::Tree(TreeErr(e)))?;
+            if is_dir {
+                continue;
+            }
+            let exists = working_copy.file_metadata(&path).is_ok();
+            if let Some(pos) = txn_.get_inodes(&inode, None)? {
+                if !exists {
+                    missing.push((path, *pos))
+                }
+            } else if exists {
+                added.push(path)
+            }
+        }
+    }
+    if missing.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut new = Vec::new();
+    for path in added.into_iter().chain(untracked.iter().cloned()) {
+        let mut contents = Vec::new();
+        working_copy
+            .read_file(&path, &mut contents)
+            .map_err(RenameError::WorkingCopy)?;
+        if !contents.is_empty() {
+            new.push((path, contents))
+        }
+    }
+    if new.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut candidates = Vec::new();
+    for (i, (_, pos)) in missing.iter().enumerate() {
+        let mut old = crate::vertex_buffer::Writer::new(Vec::new());
+        crate::output::output_file(changes, txn, channel, *pos, &mut old)?;
+        let old = old.into_inner();
+        if old.is_empty() {
+            continue;
+        }
+        for (j, (_, contents)) in new.iter().enumerate() {
+            // Skip files whose sizes are too different for the
+            // similarity to reach the threshold.
+            let min = old.len().min(contents.len());
+            if 100 * 2 * min < threshold as usize * (old.len() + contents.len()) {
+                continue;
+            }
+            let s = similarity(&old, contents);
+            if s >= threshold {
+                candidates.push((s, i, j))
+            }
+        }
+    }
+    // Best matches first, then in path order.
+    candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+    let mut renames = Vec::new();
+    let mut done_missing = vec![false; missing.len()];
+    let mut done_new = vec![false; new.len()];
+    let mut txn_ = txn.write();
+    for (similarity, i, j) in candidates {
+        if done_missing[i] || done_new[j] {
+            continue;
+        }
+        done_missing[i] = true;
+        done_new[j] = true;
+        let (from, to) = (&missing[i].0, &new[j].0);
+        debug!("detected rename {:?} -> {:?} ({}%)", from, to, similarity);
+        if crate::fs::is_tracked(&*txn_, to)? {
+            crate::fs::remove_file(&mut *txn_, to)?;
+        }
+        crate::fs::move_file(&mut *txn_, from, to, 0)?;
+        renames.push(Rename {
+            from: from.clone(),
+            to: to.clone(),
+            similarity,
+        })
+    }
+    Ok(renames)
+}
+
+/// Similarity of two files in percent, computed as the proportion of
+/// bytes in lines common to both files.
+fn similarity(a: &[u8], b: &[u8]) -> u8 {
+    if a == b {
+        return 100;
+    }
+    let mut lines: HashMap<&[u8], usize> = HashMap::default();
+    for l in a.split_inclusive(|c| *c == b'\n') {
+        *lines.entry(l).or_default() += 1
+    }
+    let mut common = 0;
+    for l in b.split_inclusive(|c| *c == b'\n') {
+        if let Some(n) = lines.get_mut(l) {
+            if *n > 0 {
+                *n -= 1;
+                common += 2 * l.len()
+            }
+        }
+    }
+    // Identical files were handled above, don't round up to 100.
+    (100 * common / (a.len() + b.len())).min(99) as u8
+}
@@ -176,7 +176,18 @@ fn del_obsolete_test() -> Result<(), anyhow::Error> {
     record_all_output(&repo, changes.clone(), &txn, &channel, "")?;
 
     let repo2 = working_copy::memory::Memory::new();
-    output::output_repository_no_pending(&repo2, &changes, &txn, &channel, "", true, None, 1, 0)?;
+    output::output_repository_no_pending(
+        &repo2,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
     debug!("output done");
     assert_eq!(repo2.list_files(), vec!["a", "a/b"]);
 
@@ -278,7 +289,16 @@ fn permissions_test() -> Result<(), anyhow::Error> {
         &alice0,
     )?;
     output::output_repository_no_pending(
-        &repo_bob, &changes, &txn_bob, &channel, "", true, None, 1, 0,
+        &repo_bob,
+        &changes,
+        &txn_bob,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     let bob_perm = repo_bob.file_metadata("file")?;
     assert_eq!(bob_perm.0, 0);
@@ -290,7 +310,16 @@ fn permissions_test() -> Result<(), anyhow::Error> {
         &alice1,
     )?;
     output::output_repository_no_pending(
-        &repo_bob, &changes, &txn_bob, &channel, "", true, None, 1, 0,
+        &repo_bob,
+        &changes,
+        &txn_bob,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     let bob_perm = repo_bob.file_metadata("file")?;
     assert_eq!(bob_perm.0, 0o100);
@@ -299,6 +328,132 @@ fn permissions_test() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+/// Are symbolic links recorded as links, with their target as
+/// contents?
+#[test]
+fn symlink_test() -> Result<(), anyhow::Error> {
+    env_logger::try_init().unwrap_or(());
+
+    let repo_alice = working_copy::memory::Memory::new();
+    let changes = changestore::memory::Memory::new();
+    repo_alice.add_file("file", b"a\nb\nc\n".to_vec());
+    repo_alice.add_symlink("link", "file");
+
+    let env_alice = pristine::sanakirja::Pristine::new_anon()?;
+
+    let txn_alice = env_alice.arc_txn_begin().unwrap();
+    txn_alice.write().add_file("file", 0)?;
+    txn_alice.write().add_file("link", 0)?;
+
+    let channel = txn_alice.write().open_or_create_channel("main")?;
+    let alice0 = record_all(&repo_alice, &changes, &txn_alice, &channel, "")?;
+
+    repo_alice.create_symlink("link", "dir/other")?;
+    let alice1 = record_all(&repo_alice, &changes, &txn_alice, &channel, "")?;
+
+    let repo_bob = working_copy::memory::Memory::new();
+    let env_bob = pristine::sanakirja::Pristine::new_anon()?;
+    let txn_bob = env_bob.arc_txn_begin().unwrap();
+    let channel = txn_bob.write().open_or_create_channel("main")?;
+    apply::apply_change(
+        &changes,
+        &mut *txn_bob.write(),
+        &mut *channel.write(),
+        &alice0,
+    )?;
+    output::output_repository_no_pending(
+        &repo_bob,
+        &changes,
+        &txn_bob,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
+    assert!(repo_bob.file_metadata("link")?.is_symlink());
+    assert!(repo_bob.file_metadata("file")?.is_file());
+    let mut buf = Vec::new();
+    repo_bob.read_file("link", &mut buf)?;
+    assert_eq!(buf, b"file");
+
+    apply::apply_change(
+        &changes,
+        &mut *txn_bob.write(),
+        &mut *channel.write(),
+        &alice1,
+    )?;
+    output::output_repository_no_pending(
+        &repo_bob,
+        &changes,
+        &txn_bob,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
+    assert!(repo_bob.file_metadata("link")?.is_symlink());
+    buf.clear();
+    repo_bob.read_file("link", &mut buf)?;
+    assert_eq!(buf, b"dir/other");
+    Ok(())
+}
+
+/// Are files moved without telling Pijul recorded as moves when
+/// renames are detected?
+#[test]
+fn detect_renames_test() -> Result<(), anyhow::Error> {
+    env_logger::try_init().unwrap_or(());
+
+    let repo = working_copy::memory::Memory::new();
+    let changes = changestore::memory::Memory::new();
+    repo.add_file("file", b"a\nb\nc\nd\ne\nf\n".to_vec());
+    repo.add_file("other", b"1\n2\n3\n".to_vec());
+
+    let env = pristine::sanakirja::Pristine::new_anon()?;
+    let txn = env.arc_txn_begin().unwrap();
+    txn.write().add_file("file", 0)?;
+    txn.write().add_file("other", 0)?;
+    let channel = txn.write().open_or_create_channel("main")?;
+    record_all(&repo, &changes, &txn, &channel, "")?;
+
+    // Move and edit `file`, replace `other` with an unrelated file.
+    repo.remove_path("file", false)?;
+    repo.add_file("dir/file2", b"a\nb\nx\nd\ne\nf\n".to_vec());
+    repo.remove_path("other", false)?;
+    repo.add_file("new", b"4\n5\n6\n".to_vec());
+
+    let untracked = vec!["dir/file2".to_string(), "new".to_string()];
+    let renames = crate::rename::detect_renames(&txn, &channel, &repo, &changes, &untracked, 100)?;
+    assert!(renames.is_empty());
+    let renames = crate::rename::detect_renames(&txn, &channel, &repo, &changes, &untracked, 50)?;
+    assert_eq!(renames.len(), 1);
+    assert_eq!(renames[0].from, "file");
+    assert_eq!(renames[0].to, "dir/file2");
+    assert!(txn.read().is_tracked("dir/file2")?);
+    assert!(!txn.read().is_tracked("new")?);
+
+    let h = record_all(&repo, &changes, &txn, &channel, "")?;
+    let change = changes.get_change(&h)?;
+    let mut moves = Vec::new();
+    let mut dels = Vec::new();
+    for hunk in change.changes.iter() {
+        match hunk {
+            crate::change::Hunk::FileMove { path, .. } => moves.push(path.as_str()),
+            crate::change::Hunk::FileDel { path, .. } => dels.push(path.as_str()),
+            _ => {}
+        }
+    }
+    assert_eq!(moves, vec!["file"]);
+    assert_eq!(dels, vec!["other"]);
+    Ok(())
+}
+
 /// Move a file to a directory, then delete the file and clone the whole thing.
 #[test]
 fn move_file_test() -> Result<(), anyhow::Error> {
@@ -344,7 +499,16 @@ fn move_file_test() -> Result<(), anyhow::Error> {
         &alice0,
     )?;
     output::output_repository_no_pending(
-        &repo_bob, &changes, &txn_bob, &channel, "", true, None, 1, 0,
+        &repo_bob,
+        &changes,
+        &txn_bob,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     assert_eq!(repo_bob.list_files(), &["file"]);
 
@@ -355,7 +519,16 @@ fn move_file_test() -> Result<(), anyhow::Error> {
         &alice1,
     )?;
     output::output_repository_no_pending(
-        &repo_bob, &changes, &txn_bob, &channel, "", true, None, 1, 0,
+        &repo_bob,
+        &changes,
+        &txn_bob,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     let mut files = repo_bob.list_files();
     files.sort();
@@ -368,7 +541,16 @@ fn move_file_test() -> Result<(), anyhow::Error> {
         &alice2,
     )?;
     output::output_repository_no_pending(
-        &repo_bob, &changes, &txn_bob, &channel, "", true, None, 1, 0,
+        &repo_bob,
+        &changes,
+        &txn_bob,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     assert_eq!(repo_bob.list_files(), &["dir"]);
 
@@ -455,6 +637,7 @@ fn move_back_test_(resolve_by_deleting: bool) -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     repo_bob.remove_path("a", false)?;
     let bob1 = record_all(&repo_bob, &changes, &txn_bob, &channel_bob, "")?;
@@ -463,7 +646,16 @@ fn move_back_test_(resolve_by_deleting: bool) -> Result<(), anyhow::Error> {
         .unwrap();
 
     output::output_repository_no_pending(
-        &repo_bob, &changes, &txn_bob, &channel, "", true, None, 1, 0,
+        &repo_bob,
+        &changes,
+        &txn_bob,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     debug!("APPLYING {:?}", alice3);
     txn_bob
@@ -471,7 +663,16 @@ fn move_back_test_(resolve_by_deleting: bool) -> Result<(), anyhow::Error> {
         .apply_change(&changes, &mut *channel_bob.write(), &alice3)
         .unwrap();
     output::output_repository_no_pending(
-        &repo_bob, &changes, &txn_bob, &channel, "", true, None, 1, 0,
+        &repo_bob,
+        &changes,
+        &txn_bob,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
 
     if resolve_by_deleting {
@@ -494,6 +695,7 @@ fn move_back_test_(resolve_by_deleting: bool) -> Result<(), anyhow::Error> {
             None,
             1,
             0,
+            ConflictStyle::default(),
         )?;
         debug!("conflicts = {:?}", conflicts);
         assert!(!conflicts.is_empty());
@@ -513,6 +715,7 @@ fn move_back_test_(resolve_by_deleting: bool) -> Result<(), anyhow::Error> {
             None,
             1,
             0,
+            ConflictStyle::default(),
         )?;
         debug!("conflicts = {:?}", conflicts);
         assert!(conflicts.is_empty());
@@ -528,6 +731,7 @@ fn move_back_test_(resolve_by_deleting: bool) -> Result<(), anyhow::Error> {
             None,
             1,
             0,
+            ConflictStyle::default(),
         )?;
         debug!("conflicts = {:?}", conflicts);
         assert!(conflicts.is_empty());
@@ -542,6 +746,7 @@ fn move_back_test_(resolve_by_deleting: bool) -> Result<(), anyhow::Error> {
             None,
             1,
             0,
+            ConflictStyle::default(),
         )?;
 
         debug!("Bob records a solution");
@@ -563,6 +768,7 @@ fn move_back_test_(resolve_by_deleting: bool) -> Result<(), anyhow::Error> {
             None,
             1,
             0,
+            ConflictStyle::default(),
         )?;
         debug!("conflicts = {:?}", conflicts);
         assert_eq!(conflicts.len(), 1);
@@ -586,6 +792,7 @@ fn move_back_test_(resolve_by_deleting: bool) -> Result<(), anyhow::Error> {
             None,
             1,
             0,
+            ConflictStyle::default(),
         )?;
         debug!("conflicts = {:?}", conflicts);
         assert!(conflicts.is_empty());
@@ -601,6 +808,7 @@ fn move_back_test_(resolve_by_deleting: bool) -> Result<(), anyhow::Error> {
             None,
             1,
             0,
+            ConflictStyle::default(),
         )?;
         debug!("conflicts = {:?}", conflicts);
         assert!(conflicts.is_empty());
@@ -646,6 +854,7 @@ fn move_delete_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     repo_alice.rename("dir2/file", "dir/file").unwrap_or(());
@@ -861,7 +1070,18 @@ fn add_file2_test() -> Result<(), anyhow::Error> {
     let txn = env.arc_txn_begin().unwrap();
     let channel = txn.write().open_or_create_channel("main").unwrap();
     apply::apply_change(&changes, &mut *txn.write(), &mut *channel.write(), &change)?;
-    output::output_repository_no_pending(&repo, &changes, &txn, &channel, "", true, None, 1, 0)?;
+    output::output_repository_no_pending(
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
     {
         let txn_ = txn.write();
         let mut f = std::fs::File::create("add_file2.dot")?;
@@ -0,0 +1,93 @@
+use super::*;
+use crate::output::{FixedMtime, Tarball};
+use std::io::Write;
+
+/// Archives of the same state are identical, with sorted entries.
+#[test]
+fn reproducible_archive() -> Result<(), anyhow::Error> {
+    env_logger::try_init().unwrap_or(());
+
+    let repo = working_copy::memory::Memory::new();
+    let changes = changestore::memory::Memory::new();
+    for file in ["b/y", "a", "c", "b/x", "b/z/w"] {
+        repo.add_file(file, file.as_bytes().to_vec());
+    }
+
+    let env = pristine::sanakirja::Pristine::new_anon()?;
+    let txn = env.arc_txn_begin().unwrap();
+    for file in ["b/y", "a", "c", "b/x", "b/z/w"] {
+        txn.write().add_file(file, 0).unwrap();
+    }
+    let channel = txn.write().open_or_create_channel("main").unwrap();
+    record_all(&repo, &changes, &txn, &channel, "").unwrap();
+
+    let archive = || -> Result<Vec<u8>, anyhow::Error> {
+        let mut archive = FixedMtime {
+            archive: Tarball::uncompressed(Vec::new(), Some("p/".to_string()), 0o022),
+            mtime: 1234,
+        };
+        txn.archive(&changes, &channel, &mut archive)?;
+        Ok(archive.archive.archive.into_inner()?)
+    };
+    let a = archive()?;
+    assert_eq!(a, archive()?);
+
+    let mut tar = tar::Archive::new(&a[..]);
+    let mut names = Vec::new();
+    for entry in tar.entries()? {
+        let entry = entry?;
+        assert_eq!(entry.header().mtime()?, 1234);
+        assert_eq!(entry.header().mode()?, 0o644);
+        names.push(entry.path()?.to_string_lossy().to_string());
+    }
+    assert_eq!(names, vec!["p/a", "p/c", "p/b/x", "p/b/y", "p/b/z/w"]);
+    Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "zstd", feature = "zip"))]
+fn archive_file_name() {
+    use crate::output::ArchiveFormat;
+    assert_eq!(ArchiveFormat::Zip.file_name("foo.tar.gz"), "foo.zip");
+    assert_eq!(ArchiveFormat::Zip.file_name("foo.tgz"), "foo.zip");
+    assert_eq!(ArchiveFormat::TarGz.file_name("foo.zip"), "foo.tar.gz");
+    assert_eq!(ArchiveFormat::TarZstd.file_name("foo.tar"), "foo.tar.zst");
+    assert_eq!(ArchiveFormat::Tar.file_name("foo.tar.zst"), "foo.tar");
+    assert_eq!(ArchiveFormat::TarGz.file_name("foo.tgz"), "foo.tgz");
+    assert_eq!(ArchiveFormat::TarGz.file_name("foo-1.0"), "foo-1.0.tar.gz");
+}
+
+/// Conflicts found while archiving carry the path of their file.
+#[test]
+fn archive_conflict_path() -> Result<(), anyhow::Error> {
+    env_logger::try_init().unwrap_or(());
+
+    let repo = working_copy::memory::Memory::new();
+    let changes = changestore::memory::Memory::new();
+    repo.add_file("d/f", b"a\nb\n".to_vec());
+
+    let env = pristine::sanakirja::Pristine::new_anon()?;
+    let txn = env.arc_txn_begin().unwrap();
+    txn.write().add_file("d/f", 0)?;
+    let alice = txn.write().open_or_create_channel("alice")?;
+    let init_h = record_all(&repo, &changes, &txn, &alice, "")?;
+    let bob = txn.write().fork(&alice, "bob")?;
+
+    repo.write_file("d/f", Inode::ROOT)?
+        .write_all(b"a\nx\nb\n")?;
+    record_all(&repo, &changes, &txn, &alice, "")?;
+    repo.write_file("d/f", Inode::ROOT)?
+        .write_all(b"a\ny\nb\n")?;
+    let bob_h = record_all(&repo, &changes, &txn, &bob, "")?;
+    assert_ne!(bob_h, init_h);
+    apply::apply_change(&changes, &mut *txn.write(), &mut *alice.write(), &bob_h)?;
+
+    let mut archive = Tarball::uncompressed(Vec::new(), None, 0o022);
+    let conflicts = txn.archive(&changes, &alice, &mut archive)?;
+    assert_eq!(conflicts.len(), 1);
+    match conflicts[0] {
+        Conflict::Order { ref path, .. } => assert_eq!(path, "d/f"),
+        ref c => panic!("unexpected conflict {:?}", c),
+    }
+    Ok(())
+}
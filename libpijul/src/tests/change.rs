@@ -138,13 +138,35 @@ fn text() -> Result<(), anyhow::Error> {
     apply::apply_change(&store, &mut *txn2.write(), &mut *channel2.write(), &h0)?;
     apply::apply_change(&store, &mut *txn2.write(), &mut *channel2.write(), &h1)?;
     apply::apply_change(&store, &mut *txn2.write(), &mut *channel2.write(), &h2)?;
-    output::output_repository_no_pending(&repo2, &store, &txn2, &channel2, "", true, None, 1, 0)?;
+    output::output_repository_no_pending(
+        &repo2,
+        &store,
+        &txn2,
+        &channel2,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
     repo2.rename("file", "file4")?;
     txn2.write().move_file("file", "file4", 0)?;
     record_all(&repo2, &store, &txn2, &channel2, "")?;
 
     apply::apply_change(&store, &mut *txn2.write(), &mut *channel2.write(), &h3)?;
-    output::output_repository_no_pending(&repo2, &store, &txn2, &channel2, "", true, None, 1, 0)?;
+    output::output_repository_no_pending(
+        &repo2,
+        &store,
+        &txn2,
+        &channel2,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
     let h = record_all(&repo2, &store, &txn2, &channel2, "")?;
     let solution = store.get_change(&h).unwrap();
     text_test(&store, &solution, h);
@@ -50,7 +50,16 @@ fn clone_simple() -> Result<(), anyhow::Error> {
             info!("applying {:?}", h);
             apply::apply_change(&changes, &mut *txn2.write(), &mut *channel.write(), h).unwrap();
             output::output_repository_no_pending(
-                &repo2, &changes, &txn2, &channel, "", true, None, 1, 0,
+                &repo2,
+                &changes,
+                &txn2,
+                &channel,
+                "",
+                true,
+                None,
+                1,
+                0,
+                ConflictStyle::default(),
             )
             .unwrap();
         }
@@ -106,7 +115,16 @@ fn clone_prefixes() -> Result<(), anyhow::Error> {
         let channel = txn2.write().open_or_create_channel("main2").unwrap();
         apply::apply_change(&changes, &mut *txn2.write(), &mut *channel.write(), &h).unwrap();
         output::output_repository_no_pending(
-            &repo2, &changes, &txn2, &channel, "e/f", true, None, 1, 0,
+            &repo2,
+            &changes,
+            &txn2,
+            &channel,
+            "e/f",
+            true,
+            None,
+            1,
+            0,
+            ConflictStyle::default(),
         )?;
         assert_eq!(
             repo2.list_files(),
@@ -118,7 +136,16 @@ fn clone_prefixes() -> Result<(), anyhow::Error> {
 
         apply::apply_change(&changes, &mut *txn2.write(), &mut *channel.write(), &h2).unwrap();
         output::output_repository_no_pending(
-            &repo2, &changes, &txn2, &channel, "", true, None, 1, 0,
+            &repo2,
+            &changes,
+            &txn2,
+            &channel,
+            "",
+            true,
+            None,
+            1,
+            0,
+            ConflictStyle::default(),
         )?;
         let mut buf = Vec::new();
         repo2.read_file("a/b/c/d", &mut buf)?;
@@ -40,6 +40,7 @@ fn solve_order_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
     {
@@ -81,6 +82,7 @@ fn solve_order_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo_alice.read_file("file", &mut buf)?;
@@ -148,6 +150,7 @@ fn solve_order_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -171,6 +174,7 @@ fn solve_order_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -187,6 +191,7 @@ fn solve_order_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -231,6 +236,7 @@ fn order_conflict_simple() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
     {
@@ -258,6 +264,7 @@ fn order_conflict_simple() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Bob edits and records
@@ -301,6 +308,7 @@ fn order_conflict_simple() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo_alice.read_file("file", &mut buf)?;
@@ -371,6 +379,7 @@ fn order_conflict_simple() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -393,6 +402,7 @@ fn order_conflict_simple() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -440,6 +450,7 @@ fn order_conflict_simple() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_charlie.read_file("file", &mut buf)?;
@@ -457,6 +468,7 @@ fn order_conflict_simple() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_charlie.read_file("file", &mut buf)?;
@@ -500,6 +512,7 @@ fn order_conflict_edit() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
 
@@ -531,6 +544,7 @@ fn order_conflict_edit() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Alice solves the conflict.
@@ -571,6 +585,7 @@ fn order_conflict_edit() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo_bob.read_file("file", &mut buf)?;
@@ -586,6 +601,7 @@ fn order_conflict_edit() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -624,6 +640,7 @@ fn edit_conflict_sides() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
 
@@ -653,6 +670,7 @@ fn edit_conflict_sides() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo.read_file("file", &mut buf)?;
@@ -697,6 +715,7 @@ fn edit_conflict_sides() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf2 = Vec::new();
     repo.read_file("file", &mut buf2)?;
@@ -720,6 +739,7 @@ fn edit_conflict_sides() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf3 = Vec::new();
     repo.read_file("file", &mut buf3)?;
@@ -762,6 +782,7 @@ fn edit_after_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
 
@@ -791,6 +812,7 @@ fn edit_after_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo.read_file("file", &mut buf)?;
@@ -823,6 +845,7 @@ fn edit_after_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf2 = Vec::new();
     repo.read_file("file", &mut buf2)?;
@@ -844,6 +867,7 @@ fn edit_after_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     let mut buf3 = Vec::new();
@@ -890,6 +914,7 @@ fn delete_before_marker() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
 
@@ -933,6 +958,7 @@ fn delete_before_marker() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo.read_file("file", &mut buf)?;
@@ -971,6 +997,7 @@ fn delete_before_marker() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf2 = Vec::new();
     repo.read_file("file", &mut buf2)?;
@@ -992,6 +1019,7 @@ fn delete_before_marker() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf2.clear();
     repo.read_file("file", &mut buf2)?;
@@ -1036,6 +1064,7 @@ fn conflict_last_line() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
     {
@@ -1071,6 +1100,7 @@ fn conflict_last_line() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo_alice.read_file("file", &mut buf)?;
@@ -1113,6 +1143,7 @@ fn conflict_last_line() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -1129,6 +1160,7 @@ fn conflict_last_line() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -1168,6 +1200,7 @@ fn zombie_last_line() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
     {
@@ -1203,6 +1236,7 @@ fn zombie_last_line() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo_alice.read_file("file", &mut buf)?;
@@ -1257,6 +1291,7 @@ fn zombie_last_line() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -1273,6 +1308,7 @@ fn zombie_last_line() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -1419,6 +1455,7 @@ fn edit_post_conflict_<
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
     {
@@ -1454,6 +1491,7 @@ fn edit_post_conflict_<
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo_alice.read_file("file", &mut buf)?;
@@ -1480,6 +1518,7 @@ fn edit_post_conflict_<
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -1497,6 +1536,7 @@ fn edit_post_conflict_<
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     let mut buf2 = Vec::new();
@@ -1549,6 +1589,7 @@ fn nested_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
     {
@@ -1587,6 +1628,7 @@ fn nested_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Alice solves the conflict.
@@ -1612,6 +1654,7 @@ fn nested_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Bob resolves.
@@ -1640,6 +1683,7 @@ fn nested_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_alice.read_file("file", &mut buf)?;
@@ -1657,6 +1701,7 @@ fn nested_conflict() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf2 = Vec::new();
     repo_bob.read_file("file", &mut buf2)?;
@@ -1717,6 +1762,7 @@ fn zombie_context_resolution() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Bob creates an order conflict just to keep line "c" connected
@@ -1746,6 +1792,7 @@ fn zombie_context_resolution() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo_bob.read_file("file", &mut buf)?;
@@ -1770,6 +1817,7 @@ fn zombie_context_resolution() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -1788,6 +1836,7 @@ fn zombie_context_resolution() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf2 = Vec::new();
     repo_alice.read_file("file", &mut buf2)?;
@@ -1805,6 +1854,7 @@ fn zombie_context_resolution() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf2 = Vec::new();
     repo_alice.read_file("file", &mut buf2)?;
@@ -1855,6 +1905,7 @@ fn zombie_half_survivor() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Bob creates an order conflict just to keep line "c" connected
@@ -1880,6 +1931,7 @@ fn zombie_half_survivor() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo_bob.read_file("file", &mut buf)?;
@@ -1905,6 +1957,7 @@ fn zombie_half_survivor() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -1923,6 +1976,7 @@ fn zombie_half_survivor() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf2 = Vec::new();
     repo_alice.read_file("file", &mut buf2)?;
@@ -1964,6 +2018,7 @@ fn three_way_zombie() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
     {
@@ -1986,6 +2041,7 @@ fn three_way_zombie() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Alice adds a line.
@@ -2024,6 +2080,7 @@ fn three_way_zombie() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo_alice.read_file("file", &mut buf)?;
@@ -2047,6 +2104,7 @@ fn three_way_zombie() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Bob applies Alice's edits and resolution.
@@ -2060,6 +2118,7 @@ fn three_way_zombie() -> Result<(), anyhow::Error> {
     &mut txn,
     &mut channel_charlie,
     "",
+    ConflictStyle::default(),
     )?;*/
     apply::apply_change_arc(&changes, &txn, &channel_charlie, &bob_h)?;
     apply::apply_change_arc(&changes, &txn, &channel_charlie, &alice_h)?;
@@ -2101,6 +2160,7 @@ fn cyclic_conflict_resolution() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
     {
@@ -2123,6 +2183,7 @@ fn cyclic_conflict_resolution() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     repo_charlie
         .write_file("file", Inode::ROOT)
@@ -2165,6 +2226,7 @@ fn cyclic_conflict_resolution() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo_alice.read_file("file", &mut buf)?;
@@ -2195,6 +2257,7 @@ fn cyclic_conflict_resolution() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -2226,6 +2289,7 @@ fn cyclic_conflict_resolution() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -2254,6 +2318,7 @@ fn cyclic_conflict_resolution() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -2280,6 +2345,7 @@ fn cyclic_conflict_resolution() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -2327,6 +2393,7 @@ fn cyclic_zombies() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
     {
@@ -2389,6 +2456,7 @@ fn cyclic_zombies() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo_alice.read_file("file", &mut buf)?;
@@ -2422,6 +2490,7 @@ fn cyclic_zombies() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -2459,6 +2528,7 @@ fn cyclic_zombies() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     repo_charlie
         .write_file("file", Inode::ROOT)
@@ -2480,6 +2550,7 @@ fn cyclic_zombies() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     buf.clear();
     repo_bob.read_file("file", &mut buf)?;
@@ -2505,6 +2576,7 @@ fn cyclic_zombies() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let (alive, reachable) = check_alive(&*txn.read(), &channel_bob.read());
     if !alive.is_empty() {
@@ -2539,6 +2611,7 @@ fn cyclic_zombies() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     let (alive, reachable) = check_alive(&*txn.read(), &channel_charlie.read());
@@ -2586,6 +2659,7 @@ fn cyclic_files() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
     txn_bob.write().move_file("a", "b/a", 0).unwrap();
@@ -2608,6 +2682,7 @@ fn cyclic_files() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     let v: Vec<_> = txn_alice.write().iter_working_copy().collect();
@@ -2664,6 +2739,7 @@ fn tree_inodes_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
 
@@ -2686,6 +2762,7 @@ fn tree_inodes_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     check_tree_inodes(&*txn_alice.read(), &*channel_alice.read());
@@ -2727,3 +2804,188 @@ fn check_tree_inodes<T: TxnT>(txn: &T, channel: &T::Channel) {
         }
     }
 }
+
+/// Output the sides of an order conflict separately.
+#[test]
+fn order_conflict_sides_writer() -> Result<(), anyhow::Error> {
+    env_logger::try_init().unwrap_or(());
+
+    let repo_alice = working_copy::memory::Memory::new();
+    let repo_bob = working_copy::memory::Memory::new();
+    let changes = changestore::memory::Memory::new();
+    repo_alice.add_file("file", b"a\nb\n".to_vec());
+
+    let env = pristine::sanakirja::Pristine::new_anon()?;
+    let txn = env.arc_txn_begin().unwrap();
+    let channel_alice = txn.write().open_or_create_channel("alice")?;
+    let channel_bob = txn.write().open_or_create_channel("bob")?;
+    txn.write().add_file("file", 0)?;
+    let init_h = record_all(&repo_alice, &changes, &txn, &channel_alice, "")?;
+    apply::apply_change(
+        &changes,
+        &mut *txn.write(),
+        &mut *channel_bob.write(),
+        &init_h,
+    )?;
+    output::output_repository_no_pending(
+        &repo_bob,
+        &changes,
+        &txn,
+        &channel_bob,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
+
+    repo_alice
+        .write_file("file", Inode::ROOT)?
+        .write_all(b"a\nx\nb\n")?;
+    record_all(&repo_alice, &changes, &txn, &channel_alice, "")?;
+    repo_bob
+        .write_file("file", Inode::ROOT)?
+        .write_all(b"a\ny\nb\n")?;
+    let bob_h = record_all(&repo_bob, &changes, &txn, &channel_bob, "")?;
+    apply::apply_change(
+        &changes,
+        &mut *txn.write(),
+        &mut *channel_alice.write(),
+        &bob_h,
+    )?;
+
+    let pos = {
+        let txn = txn.read();
+        let inode = crate::fs::find_inode(&*txn, "file")?;
+        *txn.get_inodes(&inode, None)?.unwrap()
+    };
+    let mut sides = crate::vertex_buffer::SidesWriter::new();
+    output::output_file(&changes, &txn, &channel_alice, pos, &mut sides)?;
+    assert_eq!(sides.conflicts, 1);
+    assert_eq!(std::str::from_utf8(&sides.base), Ok("a\nb\n"));
+    let mut texts = [
+        std::str::from_utf8(&sides.local)?,
+        std::str::from_utf8(&sides.remote)?,
+    ];
+    texts.sort_unstable();
+    assert_eq!(texts, ["a\nx\nb\n", "a\ny\nb\n"]);
+    Ok(())
+}
+
+/// Output an order conflict with diff3-style markers, and check that
+/// recording the unmodified file is a no-op, and that the deleted
+/// base lines can be removed when solving the conflict.
+#[test]
+fn order_conflict_diff3_style() -> Result<(), anyhow::Error> {
+    env_logger::try_init().unwrap_or(());
+
+    let repo_alice = working_copy::memory::Memory::new();
+    let repo_bob = working_copy::memory::Memory::new();
+    let changes = changestore::memory::Memory::new();
+    repo_alice.add_file("file", b"a\nb\nc\n".to_vec());
+
+    let env = pristine::sanakirja::Pristine::new_anon()?;
+    let txn = env.arc_txn_begin().unwrap();
+    let channel_alice = txn.write().open_or_create_channel("alice")?;
+    let channel_bob = txn.write().open_or_create_channel("bob")?;
+    txn.write().add_file("file", 0)?;
+    let init_h = record_all(&repo_alice, &changes, &txn, &channel_alice, "")?;
+    apply::apply_change(
+        &changes,
+        &mut *txn.write(),
+        &mut *channel_bob.write(),
+        &init_h,
+    )?;
+    output::output_repository_no_pending(
+        &repo_bob,
+        &changes,
+        &txn,
+        &channel_bob,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
+
+    repo_alice
+        .write_file("file", Inode::ROOT)?
+        .write_all(b"a\nx\nc\n")?;
+    record_all(&repo_alice, &changes, &txn, &channel_alice, "")?;
+    repo_bob
+        .write_file("file", Inode::ROOT)?
+        .write_all(b"a\ny\nc\n")?;
+    let bob_h = record_all(&repo_bob, &changes, &txn, &channel_bob, "")?;
+    apply::apply_change(
+        &changes,
+        &mut *txn.write(),
+        &mut *channel_alice.write(),
+        &bob_h,
+    )?;
+    output::output_repository_no_pending(
+        &repo_alice,
+        &changes,
+        &txn,
+        &channel_alice,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::Diff3,
+    )?;
+    let mut buf = Vec::new();
+    repo_alice.read_file("file", &mut buf)?;
+    let file = std::str::from_utf8(&buf)?;
+    debug!("file = {:?}", file);
+    let lines: Vec<_> = file.lines().collect();
+    assert_eq!(lines.len(), 9);
+    assert!(lines[1].starts_with(crate::vertex_buffer::END_MARKER));
+    assert!(lines[3].starts_with(crate::vertex_buffer::BASE_MARKER));
+    assert_eq!(lines[4], "b");
+    assert!(lines[5].starts_with(crate::vertex_buffer::SEPARATOR));
+    assert!(lines[7].starts_with(crate::vertex_buffer::START_MARKER));
+
+    let record = |style| -> Result<usize, anyhow::Error> {
+        let mut state = Builder::new();
+        state.conflict_style = style;
+        state.record(
+            txn.clone(),
+            Algorithm::default(),
+            false,
+            &crate::DEFAULT_SEPARATOR,
+            channel_alice.clone(),
+            &repo_alice,
+            &changes,
+            "",
+            1,
+        )?;
+        Ok(state.finish().actions.len())
+    };
+    assert_eq!(record(ConflictStyle::Diff3)?, 0);
+
+    repo_alice
+        .write_file("file", Inode::ROOT)?
+        .write_all(b"a\nx\ny\nc\n")?;
+    assert!(record(ConflictStyle::Diff3)? > 0);
+    record_all(&repo_alice, &changes, &txn, &channel_alice, "")?;
+    let conflicts = output::output_repository_no_pending(
+        &repo_alice,
+        &changes,
+        &txn,
+        &channel_alice,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::Diff3,
+    )?;
+    assert!(conflicts.is_empty());
+    buf.clear();
+    repo_alice.read_file("file", &mut buf)?;
+    assert_eq!(std::str::from_utf8(&buf), Ok("a\nx\ny\nc\n"));
+    Ok(())
+}
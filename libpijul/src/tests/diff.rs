@@ -68,7 +68,12 @@ fn bin_diff_test() -> Result<(), anyhow::Error> {
             change: id,
             pos: ChangePosition(1u64.into()),
         };
-        let mut ret = retrieve(&*txn.read(), txn.read().graph(&*channel.read()), vertex)?;
+        let mut ret = retrieve(
+            &*txn.read(),
+            txn.read().graph(&*channel.read()),
+            vertex,
+            false,
+        )?;
         rec.lock().diff(
             &changes,
             &txn,
@@ -90,3 +95,148 @@ fn bin_diff_test() -> Result<(), anyhow::Error> {
     }
     Ok(())
 }
+
+/// Histogram diff should anchor the diff on the function names rather
+/// than on the closing braces and blank lines, where Myers' algorithm
+/// matches the braces of different functions.
+#[test]
+fn histogram_diff_test() -> Result<(), anyhow::Error> {
+    env_logger::try_init().unwrap_or(());
+
+    let repo = working_copy::memory::Memory::new();
+    let changes = changestore::memory::Memory::new();
+    repo.add_file(
+        "file",
+        b"int frob(int x)
+{
+    if(x)
+    {
+        print(x);
+    }
+}
+
+int fact(int n)
+{
+    if(n > 1)
+    {
+        return fact(n-1) * n;
+    }
+    return 1;
+}
+"
+        .to_vec(),
+    );
+    let env = pristine::sanakirja::Pristine::new_anon()?;
+    let txn = env.arc_txn_begin().unwrap();
+    txn.write().add_file("file", 0).unwrap();
+    let channel = txn.write().open_or_create_channel("main").unwrap();
+    record_all(&repo, &changes, &txn, &channel, "").unwrap();
+
+    repo.write_file("file", Inode::ROOT)
+        .unwrap()
+        .write_all(
+            b"int fib(int n)
+{
+    if(n > 2)
+    {
+        return fib(n-1) + fib(n-2);
+    }
+    return 1;
+}
+
+int frob(int x)
+{
+    if(x)
+    {
+        print(x);
+    }
+}
+",
+        )
+        .unwrap();
+    let record = |algorithm| -> Result<(usize, Vec<u8>), anyhow::Error> {
+        let mut state = crate::record::Builder::new();
+        state.record(
+            txn.clone(),
+            algorithm,
+            false,
+            &crate::DEFAULT_SEPARATOR,
+            channel.clone(),
+            &repo,
+            &changes,
+            "",
+            1,
+        )?;
+        let rec = state.finish();
+        debug!("{:#?}", rec.actions);
+        let contents = rec.contents.lock().to_vec();
+        Ok((rec.actions.len(), contents))
+    };
+    let (histogram_actions, histogram) = record(crate::record::Algorithm::Histogram)?;
+    let (_, myers) = record(crate::record::Algorithm::Myers)?;
+    assert_ne!(histogram, myers);
+    // One hunk adds `fib`, the other one deletes `fact`.
+    assert_eq!(histogram_actions, 2);
+    assert_eq!(
+        &histogram[..],
+        &b"\0int fib(int n)
+{
+    if(n > 2)
+    {
+        return fib(n-1) + fib(n-2);
+    }
+    return 1;
+}
+
+\0"[..]
+    );
+    Ok(())
+}
+
+/// Files matching a pattern of `Builder::separators` are split with
+/// that separator, so that changing a word only records that word.
+#[test]
+fn separators_test() -> Result<(), anyhow::Error> {
+    env_logger::try_init().unwrap_or(());
+
+    let repo = working_copy::memory::Memory::new();
+    let changes = changestore::memory::Memory::new();
+    repo.add_file("a.md", b"The quick brown fox\n".to_vec());
+    repo.add_file("b.txt", b"The quick brown fox\n".to_vec());
+    let env = pristine::sanakirja::Pristine::new_anon()?;
+    let txn = env.arc_txn_begin().unwrap();
+    txn.write().add_file("a.md", 0).unwrap();
+    txn.write().add_file("b.txt", 0).unwrap();
+    let channel = txn.write().open_or_create_channel("main").unwrap();
+    record_all(&repo, &changes, &txn, &channel, "").unwrap();
+
+    for file in ["a.md", "b.txt"] {
+        repo.write_file(file, Inode::ROOT)
+            .unwrap()
+            .write_all(b"The slow brown fox\n")
+            .unwrap();
+    }
+    let mut state = crate::record::Builder::new();
+    state.separators.push(
+        regex::bytes::Regex::new(r"\.md$").unwrap(),
+        regex::bytes::Regex::new(r"\s+").unwrap(),
+    );
+    state.record(
+        txn.clone(),
+        crate::record::Algorithm::default(),
+        false,
+        &crate::DEFAULT_SEPARATOR,
+        channel.clone(),
+        &repo,
+        &changes,
+        "",
+        1,
+    )?;
+    let rec = state.finish();
+    debug!("{:#?}", rec.actions);
+    let contents = rec.contents.lock();
+    let contents = std::str::from_utf8(&contents).unwrap();
+    assert!(contents.contains("\0slow \0"));
+    assert!(contents.contains("\0The slow brown fox\n\0"));
+    Ok(())
+}
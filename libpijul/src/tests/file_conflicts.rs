@@ -47,6 +47,7 @@ fn same_file_(file: &str, alice: &str, bob: &str) -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Alice renames "file" to "alice"
@@ -73,6 +74,7 @@ fn same_file_(file: &str, alice: &str, bob: &str) -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     match conflicts.iter().next().unwrap() {
         Conflict::MultipleNames { .. } => {}
@@ -91,6 +93,7 @@ fn same_file_(file: &str, alice: &str, bob: &str) -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts);
@@ -105,6 +108,7 @@ fn same_file_(file: &str, alice: &str, bob: &str) -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts);
@@ -120,6 +124,7 @@ fn same_file_(file: &str, alice: &str, bob: &str) -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     match conflicts.iter().next().unwrap() {
         Conflict::MultipleNames { .. } => {}
@@ -144,6 +149,7 @@ fn same_file_(file: &str, alice: &str, bob: &str) -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         let txn_ = txn_bob.write();
@@ -164,6 +170,7 @@ fn same_file_(file: &str, alice: &str, bob: &str) -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts);
@@ -215,6 +222,7 @@ fn same_name_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Alice renames "file1" to "file"
@@ -242,6 +250,7 @@ fn same_name_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Bob applies Alice's change
@@ -256,6 +265,7 @@ fn same_name_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     assert!(!conflicts.is_empty());
@@ -292,6 +302,7 @@ fn same_name_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts);
@@ -342,6 +353,7 @@ fn file_conflicts_same_name_and_two_names() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     repo_bob.rename("file2", "file")?;
     txn_bob.write().move_file("file2", "file", 0)?;
@@ -374,6 +386,7 @@ fn file_conflicts_same_name_and_two_names() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     repo_charlie.rename("file1", "file3")?;
     txn_charlie.write().move_file("file1", "file3", 0)?;
@@ -398,6 +411,7 @@ fn file_conflicts_same_name_and_two_names() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut files_charlie = repo_charlie.list_files();
     files_charlie.sort();
@@ -430,6 +444,7 @@ fn file_conflicts_same_name_and_two_names() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut files_charlie = repo_charlie.list_files();
     files_charlie.sort();
@@ -448,6 +463,7 @@ fn file_conflicts_same_name_and_two_names() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut files_alice = repo_alice.list_files();
     files_alice.sort();
@@ -469,6 +485,7 @@ fn file_conflicts_same_name_and_two_names() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let files_bob = repo_bob.list_files();
     debug!("files_bob {:?}", files_bob);
@@ -513,6 +530,7 @@ fn zombie_file_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Alice deletes "file"
@@ -539,6 +557,7 @@ fn zombie_file_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let files_alice = repo_alice.list_files();
     assert_eq!(files_alice, vec!["a", "a/b", "a/b/c", "a/b/c/file"]);
@@ -570,6 +589,7 @@ fn zombie_file_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     debug!("repo_alice = {:?}", repo_alice.list_files());
     debug!("repo_bob = {:?}", repo_bob.list_files());
@@ -584,6 +604,7 @@ fn zombie_file_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let files_bob = repo_bob.list_files();
     assert_eq!(files_bob, vec!["a", "a/b", "a/b/c", "a/b/c/file"]);
@@ -625,6 +646,7 @@ fn rename_zombie_file() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Alice deletes "file"
@@ -661,6 +683,7 @@ fn rename_zombie_file() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let files_alice = repo_alice.list_files();
     debug!("Alice records {:?}", files_alice);
@@ -687,6 +710,7 @@ fn rename_zombie_file() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     debug!("repo_alice = {:?}", repo_alice.list_files());
     debug!("repo_bob = {:?}", repo_bob.list_files());
@@ -701,6 +725,7 @@ fn rename_zombie_file() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let files_bob = repo_bob.list_files();
     assert!(["a", "a/b", "a/b/c", "a/b/c/file2"]
@@ -750,6 +775,7 @@ fn rename_zombie_dir() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Alice deletes "file"
@@ -785,6 +811,7 @@ fn rename_zombie_dir() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let files_alice = repo_alice.list_files();
     if files_alice.iter().any(|x| x == "a/b/d/file") {
@@ -810,6 +837,7 @@ fn rename_zombie_dir() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     debug!("repo_alice = {:?}", repo_alice.list_files());
     debug!("repo_bob = {:?}", repo_bob.list_files());
@@ -825,6 +853,7 @@ fn rename_zombie_dir() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let files_bob = repo_bob.list_files();
     debug!("files_bob = {:?}", files_bob);
@@ -873,6 +902,7 @@ fn double_zombie_file() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     let channel_charlie = txn_charlie
@@ -890,6 +920,7 @@ fn double_zombie_file() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("charlie has conflicts: {:?}", conflicts);
@@ -928,6 +959,7 @@ fn double_zombie_file() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?
     .into_iter()
     .collect::<Vec<_>>();
@@ -985,6 +1017,7 @@ fn double_zombie_file() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?
     .into_iter()
     .collect::<Vec<_>>();
@@ -1002,6 +1035,7 @@ fn double_zombie_file() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("bob has conflicts: {:?}", conflicts);
@@ -1021,6 +1055,7 @@ fn double_zombie_file() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?
     .into_iter()
     .collect::<Vec<_>>();
@@ -1037,6 +1072,7 @@ fn double_zombie_file() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("charlie has conflicts: {:?}", conflicts);
@@ -1088,6 +1124,7 @@ fn zombie_file_post_resolve() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Bob deletes "file"
@@ -1104,6 +1141,7 @@ fn zombie_file_post_resolve() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     debug!("conflicts = {:#?}", conflicts);
     assert_eq!(conflicts.len(), 1);
@@ -1124,6 +1162,7 @@ fn zombie_file_post_resolve() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("Bob has conflicts: {:?}", conflicts);
@@ -1143,6 +1182,7 @@ fn zombie_file_post_resolve() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("Alice has conflicts: {:?}", conflicts);
@@ -1165,6 +1205,7 @@ fn zombie_file_post_resolve() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("charlie has conflicts: {:?}", conflicts);
@@ -1190,6 +1231,7 @@ fn zombie_file_post_resolve() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     assert_eq!(conflicts.len(), 1);
     match conflicts.iter().next().unwrap() {
@@ -1210,6 +1252,7 @@ fn zombie_file_post_resolve() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     assert_eq!(conflicts.len(), 1);
     match conflicts.iter().next().unwrap() {
@@ -1232,6 +1275,7 @@ fn zombie_file_post_resolve() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     assert_eq!(conflicts.len(), 1);
     match conflicts.iter().next().unwrap() {
@@ -1275,6 +1319,7 @@ fn move_vs_delete_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Alice moves "file"
@@ -1305,6 +1350,7 @@ fn move_vs_delete_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     debug!("conflicts = {:#?}", conflicts);
     assert_eq!(conflicts.len(), 1);
@@ -1329,6 +1375,7 @@ fn move_vs_delete_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("Bob has conflicts: {:?}", conflicts);
@@ -1350,6 +1397,7 @@ fn move_vs_delete_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     assert_eq!(conflicts.len(), 1);
     match conflicts.iter().next().unwrap() {
@@ -1371,6 +1419,7 @@ fn move_vs_delete_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("Alice has conflicts: {:?}", conflicts);
@@ -1412,6 +1461,7 @@ fn delete_zombie_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Alice adds a zombie line.
@@ -1469,6 +1519,7 @@ fn delete_zombie_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     repo_alice.read_file("file", &mut buf)?;
@@ -1509,6 +1560,7 @@ fn move_into_deleted_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Alice moves "file"
@@ -1539,6 +1591,7 @@ fn move_into_deleted_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     debug!("conflicts = {:#?}", conflicts);
     assert_eq!(conflicts.len(), 1);
@@ -1557,6 +1610,7 @@ fn move_into_deleted_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("Bob has conflicts: {:?}", conflicts);
@@ -1578,6 +1632,7 @@ fn move_into_deleted_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     assert_eq!(conflicts.len(), 1);
     match conflicts.iter().next().unwrap() {
@@ -1599,6 +1654,7 @@ fn move_into_deleted_test() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("Alice has conflicts: {:?}", conflicts);
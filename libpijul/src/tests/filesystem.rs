@@ -1,3 +1,5 @@
+use crate::working_copy::WorkingCopyRead;
+
 use super::*;
 use std::io::Write;
 
@@ -28,8 +30,19 @@ fn filesystem() -> Result<(), anyhow::Error> {
     let channel = txn.write().open_or_create_channel("main2").unwrap();
     info!("applying");
     apply::apply_change_arc(&changes, &txn, &channel, &p)?;
-    output::output_repository_no_pending(&repo, &changes, &txn, &channel, "", true, None, 1, 0)
-        .unwrap();
+    output::output_repository_no_pending(
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )
+    .unwrap();
 
     txn.commit().unwrap();
 
@@ -67,8 +80,19 @@ fn symlink() -> Result<(), anyhow::Error> {
     info!("applying");
     let channel = txn.write().open_or_create_channel("main2").unwrap();
     apply::apply_change_arc(&changes, &txn, &channel, &p)?;
-    output::output_repository_no_pending(&repo, &changes, &txn, &channel, "", true, None, 1, 0)
-        .unwrap();
+    output::output_repository_no_pending(
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )
+    .unwrap();
 
     txn.commit().unwrap();
 
@@ -78,6 +102,65 @@ fn symlink() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+#[test]
+fn record_symlink_target() -> Result<(), anyhow::Error> {
+    env_logger::try_init().unwrap_or(());
+
+    let r = tempfile::tempdir()?;
+    let repo = working_copy::filesystem::FileSystem::from_root(r.path());
+
+    let f = tempfile::tempdir()?;
+    let changes = changestore::filesystem::FileSystem::from_root(f.path(), MAX_FILES);
+
+    repo.write_file("dir/file", Inode::ROOT)
+        .unwrap()
+        .write_all(&b"a\nb\nc\nd\ne\nf\n"[..])
+        .unwrap();
+    std::os::unix::fs::symlink("file", r.path().join("dir/link")).unwrap();
+    assert!(repo.file_metadata("dir/link")?.is_symlink());
+
+    let f = tempfile::tempdir()?;
+    let env = pristine::sanakirja::Pristine::new(f.path().join("pristine"))?;
+    let txn = env.arc_txn_begin().unwrap();
+    txn.write().add_file("dir/file", 0).unwrap();
+    txn.write().add_file("dir/link", 0).unwrap();
+
+    let channel = txn.write().open_or_create_channel("main").unwrap();
+    record_all(&repo, &changes, &txn, &channel, "").unwrap();
+
+    // Output the channel into a fresh working copy.
+    let r2 = tempfile::tempdir()?;
+    let repo2 = working_copy::filesystem::FileSystem::from_root(r2.path());
+    output::output_repository_no_pending(
+        &repo2,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )
+    .unwrap();
+    let link = r2.path().join("dir/link");
+    assert!(std::fs::symlink_metadata(&link)?.file_type().is_symlink());
+    assert_eq!(std::fs::read_link(&link)?, std::path::Path::new("file"));
+    assert_eq!(std::fs::read(&link)?, b"a\nb\nc\nd\ne\nf\n");
+
+    // Retargeting the link is recorded, and removing it doesn't
+    // touch its target.
+    repo.create_symlink("dir/link", "../file")?;
+    let p = record_all(&repo, &changes, &txn, &channel, "").unwrap();
+    assert!(!changes.get_change(&p)?.changes.is_empty());
+    repo.remove_path("dir/link", true)?;
+    assert!(r.path().join("dir/file").exists());
+
+    txn.commit().unwrap();
+    Ok(())
+}
+
 #[test]
 fn record_dead_symlink() -> Result<(), anyhow::Error> {
     env_logger::try_init().unwrap_or(());
@@ -102,8 +185,19 @@ fn record_dead_symlink() -> Result<(), anyhow::Error> {
     info!("applying");
     let channel = txn.write().open_or_create_channel("main2").unwrap();
     apply::apply_change_arc(&changes, &txn, &channel, &p)?;
-    output::output_repository_no_pending(&repo, &changes, &txn, &channel, "", true, None, 1, 0)
-        .unwrap();
+    output::output_repository_no_pending(
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )
+    .unwrap();
 
     txn.commit().unwrap();
     Ok(())
@@ -141,9 +235,99 @@ fn overwrite_dead_symlink() -> Result<(), anyhow::Error> {
     debug!("meta = {:?}", std::fs::metadata("dir/file"));
     // And output.
     apply::apply_change_arc(&changes, &txn, &channel, &p)?;
-    output::output_repository_no_pending(&repo, &changes, &txn, &channel, "", true, None, 1, 0)
-        .unwrap();
+    output::output_repository_no_pending(
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )
+    .unwrap();
 
     txn.commit().unwrap();
     Ok(())
 }
+
+/// Outputting a file doesn't write through a symbolic link to a
+/// directory outside of the working copy.
+#[test]
+fn output_through_symlink() -> Result<(), anyhow::Error> {
+    env_logger::try_init().unwrap_or(());
+
+    let repo = working_copy::memory::Memory::new();
+    let f = tempfile::tempdir()?;
+    let changes = changestore::filesystem::FileSystem::from_root(f.path(), MAX_FILES);
+    repo.add_file("dir/file", b"a\n".to_vec());
+
+    let env = pristine::sanakirja::Pristine::new_anon()?;
+    let txn = env.arc_txn_begin().unwrap();
+    txn.write().add_file("dir/file", 0).unwrap();
+    let channel = txn.write().open_or_create_channel("main").unwrap();
+    record_all(&repo, &changes, &txn, &channel, "").unwrap();
+
+    let r = tempfile::tempdir()?;
+    let outside = tempfile::tempdir()?;
+    std::os::unix::fs::symlink(outside.path(), r.path().join("dir")).unwrap();
+    let repo = working_copy::filesystem::FileSystem::from_root(r.path());
+    assert!(output::output_repository_no_pending(
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )
+    .is_err());
+    assert!(!outside.path().join("file").exists());
+    assert!(repo.write_file("dir/file", Inode::ROOT).is_err());
+    assert!(repo.create_dir_all("dir/sub").is_err());
+    assert!(!outside.path().join("sub").exists());
+    Ok(())
+}
+
+/// The change index agrees with the change files, and is kept up to
+/// date when changes are saved and deleted.
+#[test]
+fn change_index() -> Result<(), anyhow::Error> {
+    env_logger::try_init().unwrap_or(());
+
+    let repo = working_copy::memory::Memory::new();
+    let f = tempfile::tempdir()?;
+    let mut changes = changestore::filesystem::FileSystem::from_root(f.path(), MAX_FILES);
+    assert!(!changes.has_index());
+
+    let env = pristine::sanakirja::Pristine::new_anon()?;
+    let txn = env.arc_txn_begin().unwrap();
+    let channel = txn.write().open_or_create_channel("main").unwrap();
+    repo.add_file("a", b"a\n".to_vec());
+    txn.write().add_file("a", 0)?;
+    let h0 = record_all(&repo, &changes, &txn, &channel, "")?;
+
+    assert_eq!(changes.build_index()?, 1);
+    repo.write_file("a", Inode::ROOT)?.write_all(b"a\nb\n")?;
+    let h1 = record_all(&repo, &changes, &txn, &channel, "")?;
+
+    let changes = changestore::filesystem::FileSystem::from_root(f.path(), MAX_FILES);
+    assert!(changes.has_index());
+    for h in [h0, h1] {
+        let entry = changes.get_index_entry(&h)?.unwrap();
+        let change = changes.get_change(&h)?;
+        assert_eq!(entry.header, change.hashed.header);
+        assert_eq!(entry.dependencies, change.hashed.dependencies);
+        assert_eq!(entry.size, std::fs::metadata(changes.filename(&h))?.len());
+    }
+    assert_eq!(changes.get_dependencies(&h1)?, vec![h0]);
+
+    changes.del_change(&h1)?;
+    assert!(changes.get_index_entry(&h1).is_err());
+    Ok(())
+}
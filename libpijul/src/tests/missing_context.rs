@@ -45,6 +45,7 @@ fn missing_context_newnodes(alice: Option<&str>) -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )
     .unwrap();
 
@@ -90,6 +91,7 @@ fn missing_context_newnodes(alice: Option<&str>) -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     crate::unrecord::unrecord(
@@ -160,6 +162,7 @@ fn missing_context_newnodes(alice: Option<&str>) -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     repo_bob.read_file("file", &mut buf)?;
@@ -223,6 +226,7 @@ fn missing_context_newedges() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     info!("Done outputting Bob's working_copy");
     {
@@ -261,6 +265,7 @@ fn missing_context_newedges() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Bob reverts his change.
@@ -290,6 +295,7 @@ fn missing_context_newedges() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     Ok(())
@@ -2,11 +2,14 @@ use crate::change::Change;
 use crate::changestore::ChangeStore;
 use crate::pristine::*;
 use crate::record::{Algorithm, Builder};
+use crate::vertex_buffer::ConflictStyle;
 use crate::working_copy::WorkingCopy;
 use crate::*;
 use chrono::*;
 
 mod add_file;
+#[cfg(feature = "tarball")]
+mod archive;
 mod change;
 mod clone;
 mod conflict;
@@ -18,6 +21,8 @@ mod partial;
 mod performance;
 mod rm_file;
 mod rollback;
+mod split;
+mod tag;
 mod text;
 mod text_changes;
 mod unrecord;
@@ -123,7 +128,18 @@ where
     R::Error: Send + Sync + 'static,
 {
     let hash = record_all(repo, &changes, txn, channel, prefix)?;
-    output::output_repository_no_pending(repo, &changes, txn, channel, "", true, None, 1, 0)
-        .unwrap();
+    output::output_repository_no_pending(
+        repo,
+        &changes,
+        txn,
+        channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )
+    .unwrap();
     Ok(hash)
 }
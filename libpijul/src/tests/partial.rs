@@ -87,7 +87,16 @@ fn clone_prefixes() -> Result<(), anyhow::Error> {
         let channel = txn2.write().open_or_create_channel("main2").unwrap();
         apply::apply_change_arc(&changes, &txn2, &channel, &h).unwrap();
         output::output_repository_no_pending(
-            &repo2, &changes, &txn2, &channel, "e/f", true, None, 1, 0,
+            &repo2,
+            &changes,
+            &txn2,
+            &channel,
+            "e/f",
+            true,
+            None,
+            1,
+            0,
+            ConflictStyle::default(),
         )?;
         assert_eq!(
             repo2.list_files(),
@@ -99,7 +108,16 @@ fn clone_prefixes() -> Result<(), anyhow::Error> {
 
         apply::apply_change_arc(&changes, &txn2, &channel, &h2).unwrap();
         output::output_repository_no_pending(
-            &repo2, &changes, &txn2, &channel, "", true, None, 1, 0,
+            &repo2,
+            &changes,
+            &txn2,
+            &channel,
+            "",
+            true,
+            None,
+            1,
+            0,
+            ConflictStyle::default(),
         )?;
         let mut buf = Vec::new();
         repo2.read_file("a/b/c/d", &mut buf)?;
@@ -35,6 +35,7 @@ fn remove_file() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
 
     // Bob removes a/b and records
@@ -54,6 +55,7 @@ fn remove_file() -> Result<(), anyhow::Error> {
         None,
         1,
         0,
+        ConflictStyle::default(),
     )?;
     Ok(())
 }
@@ -37,8 +37,19 @@ fn rollback_conflict_resolution_simple() {
     apply::apply_change_arc(&changes, &txn, &channelb, &ha).unwrap();
     apply::apply_change_arc(&changes, &txn, &channela, &hb).unwrap();
 
-    output::output_repository_no_pending(&repo, &changes, &txn, &channela, "", true, None, 1, 0)
-        .unwrap();
+    output::output_repository_no_pending(
+        &repo,
+        &changes,
+        &txn,
+        &channela,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )
+    .unwrap();
     let mut buf = Vec::new();
     repo.read_file("file", &mut buf).unwrap();
     debug!("{}", std::str::from_utf8(&buf).unwrap());
@@ -107,7 +118,18 @@ fn rollback_conflict_resolution_swap() -> Result<(), anyhow::Error> {
     apply::apply_change_arc(&changes, &txn, &channelb, &ha)?;
     apply::apply_change_arc(&changes, &txn, &channela, &hb)?;
 
-    output::output_repository_no_pending(&repo, &changes, &txn, &channela, "", true, None, 1, 0)?;
+    output::output_repository_no_pending(
+        &repo,
+        &changes,
+        &txn,
+        &channela,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
     let mut buf = Vec::new();
     repo.read_file("file", &mut buf)?;
     debug!("{}", std::str::from_utf8(&buf).unwrap());
@@ -0,0 +1,85 @@
+use super::*;
+use crate::working_copy::{WorkingCopy, WorkingCopyRead};
+use std::io::Write;
+
+/// Record some of the lines of a replacement.
+#[test]
+fn split_replacement_test() -> Result<(), anyhow::Error> {
+    env_logger::try_init().unwrap_or(());
+
+    let repo = working_copy::memory::Memory::new();
+    let changes = changestore::memory::Memory::new();
+    repo.add_file("file", b"a\nb\nc\nd\n".to_vec());
+
+    let env = pristine::sanakirja::Pristine::new_anon()?;
+    let txn = env.arc_txn_begin().unwrap();
+    txn.write().add_file("file", 0)?;
+    let channel = txn.write().open_or_create_channel("main")?;
+    let h0 = record_all(&repo, &changes, &txn, &channel, "")?;
+
+    repo.write_file("file", Inode::ROOT)?
+        .write_all(b"a\nx\ny\nz\nd\n")?;
+    let mut state = Builder::new();
+    state.record(
+        txn.clone(),
+        Algorithm::default(),
+        false,
+        &crate::DEFAULT_SEPARATOR,
+        channel.clone(),
+        &repo,
+        &changes,
+        "",
+        1,
+    )?;
+    let rec = state.finish();
+    let hunks: Vec<_> = rec
+        .actions
+        .into_iter()
+        .map(|rec| rec.globalize(&*txn.read()).unwrap())
+        .collect();
+    let mut contents = std::mem::take(&mut *rec.contents.lock());
+    assert_eq!(hunks.len(), 1);
+
+    // Delete "c", insert "x" and "z".
+    let parts = crate::change::split_lines(&changes, &hunks[0], &contents)?.unwrap();
+    assert_eq!(parts.len(), 5);
+    assert!(crate::change::split_lines(&changes, &parts[0], &contents)?.is_none());
+    let selected = [false, true, true, false, true];
+    let hunks = crate::change::join_lines(parts, &selected, &mut contents);
+    assert_eq!(hunks.len(), 1);
+    assert!(matches!(hunks[0], crate::change::Hunk::Replacement { .. }));
+
+    let mut change = Change::make_change(
+        &*txn.read(),
+        &channel,
+        hunks,
+        contents,
+        crate::change::ChangeHeader::default(),
+        Vec::new(),
+    )?;
+    let h1 = changes.save_change(&mut change, |_, _| Ok::<_, anyhow::Error>(()))?;
+
+    let repo2 = working_copy::memory::Memory::new();
+    let env2 = pristine::sanakirja::Pristine::new_anon()?;
+    let txn2 = env2.arc_txn_begin().unwrap();
+    let channel2 = txn2.write().open_or_create_channel("main")?;
+    for h in [h0, h1].iter() {
+        apply::apply_change(&changes, &mut *txn2.write(), &mut *channel2.write(), h)?;
+    }
+    output::output_repository_no_pending(
+        &repo2,
+        &changes,
+        &txn2,
+        &channel2,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
+    let mut file = Vec::new();
+    repo2.read_file("file", &mut file)?;
+    assert_eq!(std::str::from_utf8(&file), Ok("a\nb\nx\nz\nd\n"));
+    Ok(())
+}
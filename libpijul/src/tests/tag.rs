@@ -0,0 +1,26 @@
+use super::*;
+
+/// Tagging a position before the last tagged one doesn't consider it
+/// already tagged.
+#[test]
+fn tag_earlier_position() -> Result<(), anyhow::Error> {
+    env_logger::try_init().unwrap_or(());
+    let env = pristine::sanakirja::Pristine::new_anon()?;
+    let txn = env.arc_txn_begin()?;
+    let channel = txn.write().open_or_create_channel("main")?;
+    let m0 = Merkle::zero();
+    let m1 = m0.next(&m0);
+    let mut txn_ = txn.write();
+    let mut channel_ = channel.write();
+    txn_.put_tags(&mut channel_.tags, 5, &m1)?;
+    txn_.put_tags(&mut channel_.tags, 2, &m0)?;
+    assert!(txn_.is_tagged(&channel_.tags, 2)?);
+    assert!(txn_.is_tagged(&channel_.tags, 5)?);
+    assert!(!txn_.is_tagged(&channel_.tags, 3)?);
+    let tags: Vec<u64> = txn_
+        .iter_tags(&channel_.tags, 0)?
+        .map(|t| t.map(|(t, _)| (*t).into()))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(tags, vec![2, 5]);
+    Ok(())
+}
@@ -29,7 +29,16 @@ fn test() -> Result<(), anyhow::Error> {
     let _channel2 = txn.write().fork(&channel, "main2")?;
     crate::unrecord::unrecord(&mut *txn.write(), &channel, &changes, &h1, 0)?;
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts);
@@ -67,7 +76,16 @@ fn replace() -> Result<(), anyhow::Error> {
     let _channel2 = txn.write().fork(&channel, "main2")?;
     crate::unrecord::unrecord(&mut *txn.write(), &channel, &changes, &h1, 0)?;
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts);
@@ -113,7 +131,18 @@ fn file_move() -> Result<(), anyhow::Error> {
     );
     assert_eq!(repo.list_files(), vec!["dir", "dir/file"]);
 
-    output::output_repository_no_pending(&repo, &changes, &txn, &channel, "", true, None, 1, 0)?;
+    output::output_repository_no_pending(
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
     assert_eq!(
         crate::fs::iter_working_copy(&*txn.read(), Inode::ROOT)
             .map(|n| n.unwrap().1)
@@ -167,9 +196,31 @@ fn reconnect_(delete_file: bool) -> Result<(), anyhow::Error> {
     let channel3 = txn3.write().open_or_create_channel("main")?;
 
     apply::apply_change_arc(&changes, &txn2, &channel2, &h0)?;
-    output::output_repository_no_pending(&repo2, &changes, &txn2, &channel2, "", true, None, 1, 0)?;
+    output::output_repository_no_pending(
+        &repo2,
+        &changes,
+        &txn2,
+        &channel2,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
     apply::apply_change_arc(&changes, &txn3, &channel3, &h0)?;
-    output::output_repository_no_pending(&repo3, &changes, &txn3, &channel3, "", true, None, 1, 0)?;
+    output::output_repository_no_pending(
+        &repo3,
+        &changes,
+        &txn3,
+        &channel3,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
 
     // This test removes a line (in h1), then replaces it with another
     // one (in h2), removes the pseudo-edges (output, below), and then
@@ -198,7 +249,18 @@ fn reconnect_(delete_file: bool) -> Result<(), anyhow::Error> {
     ///////////
     apply::apply_change_arc(&changes, &txn, &channel, &h2)?;
     apply::apply_change_arc(&changes, &txn, &channel, &h3)?;
-    output::output_repository_no_pending(&repo, &changes, &txn, &channel, "", true, None, 1, 0)?;
+    output::output_repository_no_pending(
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
 
     crate::unrecord::unrecord(&mut *txn.write(), &channel, &changes, &h2, 0)?;
 
@@ -235,7 +297,18 @@ fn zombie_(file: Option<&[u8]>) -> Result<(), anyhow::Error> {
     let channel2 = txn2.write().open_or_create_channel("main")?;
 
     apply::apply_change_arc(&changes, &txn2, &channel2, &h0)?;
-    output::output_repository_no_pending(&repo2, &changes, &txn2, &channel2, "", true, None, 1, 0)?;
+    output::output_repository_no_pending(
+        &repo2,
+        &changes,
+        &txn2,
+        &channel2,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
 
     ///////////
     if let Some(file) = file {
@@ -258,7 +331,16 @@ fn zombie_(file: Option<&[u8]>) -> Result<(), anyhow::Error> {
     debug!("unrecording");
     crate::unrecord::unrecord(&mut *txn.write(), &channel, &changes, &h2, 0)?;
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     let mut buf = Vec::new();
     if let Some(f) = file {
@@ -293,7 +375,16 @@ fn zombie_(file: Option<&[u8]>) -> Result<(), anyhow::Error> {
     debug!("unrecording h1 = {:?}", h1);
     crate::unrecord::unrecord(&mut *txn2.write(), &channel2, &changes, &h1, 0)?;
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn2, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn2,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts)
@@ -323,7 +414,18 @@ fn zombie_dir() -> Result<(), anyhow::Error> {
 
     repo.remove_path("a/b", true)?;
     let _h2 = record_all_output(&repo, changes.clone(), &txn, &channel, "")?;
-    output::output_repository_no_pending(&repo, &changes, &txn, &channel, "", true, None, 1, 0)?;
+    output::output_repository_no_pending(
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
     let files = repo.list_files();
     assert_eq!(files, &["a"]);
     debug!("files={:?}", files);
@@ -331,7 +433,16 @@ fn zombie_dir() -> Result<(), anyhow::Error> {
     crate::unrecord::unrecord(&mut *txn.write(), &channel, &changes, &h1, 0)?;
 
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?
     .into_iter()
     .collect::<Vec<_>>();
@@ -411,7 +522,18 @@ fn nodep() -> Result<(), anyhow::Error> {
     debug_inodes(&*txn.read());
     crate::unrecord::unrecord(&mut *txn.write(), &channel, &changes, &h0, 0)?;
 
-    output::output_repository_no_pending(&repo, &changes, &txn, &channel, "", true, None, 1, 0)?;
+    output::output_repository_no_pending(
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
+    )?;
 
     // Checking that unrecord doesn't delete files on the filesystem,
     // but updates the tree/revtree tables.
@@ -450,7 +572,16 @@ fn file_del() -> Result<(), anyhow::Error> {
     // Unrecording the deletion.
     crate::unrecord::unrecord(&mut *txn.write(), &channel, &changes, &h, 0)?;
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts);
@@ -461,7 +592,16 @@ fn file_del() -> Result<(), anyhow::Error> {
     debug!("unrecord h0");
     crate::unrecord::unrecord(&mut *txn.write(), &channel, &changes, &h0, 0)?;
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts);
@@ -502,7 +642,16 @@ fn self_context() -> Result<(), anyhow::Error> {
 
     apply::apply_change_arc(&changes, &txn, &channel, &b)?;
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     debug!("conflicts = {:#?}", conflicts);
     let mut buf = Vec::new();
@@ -532,7 +681,16 @@ fn self_context() -> Result<(), anyhow::Error> {
     crate::unrecord::unrecord(&mut *txn.write(), &mut channel, &changes, &c, 0)?;
 
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     debug!("conflicts = {:#?}", conflicts);
     assert_eq!(conflicts.len(), 1);
@@ -609,7 +767,16 @@ fn rollback_(delete_file: bool) -> Result<(), anyhow::Error> {
     let h_inv = changes.save_change(&mut p_inv, |_, _| Ok::<_, anyhow::Error>(()))?;
     apply::apply_change_arc(&changes, &txn, &channel, &h_inv)?;
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts)
@@ -621,7 +788,16 @@ fn rollback_(delete_file: bool) -> Result<(), anyhow::Error> {
     // Unrecord the rollback
     crate::unrecord::unrecord(&mut *txn.write(), &channel, &changes, &h_inv, 0)?;
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts)
@@ -680,7 +856,16 @@ fn double_test() -> Result<(), anyhow::Error> {
     crate::unrecord::unrecord(&mut *txn.write(), &channel, &changes, &h2, 0)?;
 
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts);
@@ -736,7 +921,16 @@ fn double_convoluted() -> Result<(), anyhow::Error> {
     crate::unrecord::unrecord(&mut *txn.write(), &mut channel, &changes, &h2, 0)?;
 
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts);
@@ -771,7 +965,16 @@ fn double_convoluted() -> Result<(), anyhow::Error> {
     crate::unrecord::unrecord(&mut *txn.write(), &mut channel2, &changes, &h1, 0)?;
 
     let conflicts = output::output_repository_no_pending(
-        &repo, &changes, &txn, &channel, "", true, None, 1, 0,
+        &repo,
+        &changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        1,
+        0,
+        ConflictStyle::default(),
     )?;
     if !conflicts.is_empty() {
         panic!("conflicts = {:#?}", conflicts)
@@ -6,6 +6,37 @@ pub const SEPARATOR: &str = "=======";
 
 pub const END_MARKER: &str = "<<<<<<<";
 
+pub const BASE_MARKER: &str = "|||||||";
+
+/// The style of the conflict markers written to files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictStyle {
+    /// Conflicts start with [`START_MARKER`] and end with
+    /// [`END_MARKER`].
+    #[default]
+    Pijul,
+    /// Conflicts start with `<<<<<<<` and end with `>>>>>>>`, as in
+    /// Git, so that editors and merge tools recognise them.
+    Git,
+    /// Like `Git`, with a section starting with [`BASE_MARKER`] after
+    /// the first side of order conflicts, containing the lines
+    /// deleted by the changes in conflict.
+    Diff3,
+}
+
+impl ConflictStyle {
+    /// The marker written in this style instead of `marker`, which is
+    /// one of [`START_MARKER`], [`SEPARATOR`] and [`END_MARKER`].
+    pub fn marker<'a>(&self, marker: &'a str) -> &'a str {
+        match self {
+            ConflictStyle::Pijul => marker,
+            _ if marker == START_MARKER => END_MARKER,
+            _ if marker == END_MARKER => START_MARKER,
+            _ => marker,
+        }
+    }
+}
+
 /// A trait for outputting keys and their contents. This trait allows
 /// to retain more information about conflicts than directly
 /// outputting as bytes to a `Write`. The diff algorithm uses that
@@ -55,6 +86,16 @@ pub trait VertexBuffer {
     fn end_cyclic_conflict<C: ChangeStore>(&mut self, id: usize) -> Result<(), std::io::Error> {
         self.output_conflict_marker::<C>(END_MARKER, id, None)
     }
+    /// Whether [`VertexBuffer::conflict_base`] should be called. The
+    /// base is not computed otherwise, since this is costly.
+    fn wants_conflict_base(&self) -> bool {
+        false
+    }
+    /// Output the lines deleted by the changes of order conflict `id`,
+    /// between its first and second sides.
+    fn conflict_base(&mut self, _id: usize, _base: &[u8]) -> Result<(), std::io::Error> {
+        Ok(())
+    }
 }
 
 pub(crate) struct ConflictsWriter<'a, 'b, W: std::io::Write> {
@@ -65,6 +106,7 @@ pub(crate) struct ConflictsWriter<'a, 'b, W: std::io::Write> {
     pub inode_vertex: Position<ChangeId>,
     pub conflicts: &'a mut Vec<crate::output::Conflict>,
     pub buf: Vec<u8>,
+    pub style: ConflictStyle,
 }
 
 impl<'a, 'b, W: std::io::Write> ConflictsWriter<'a, 'b, W> {
@@ -73,6 +115,7 @@ impl<'a, 'b, W: std::io::Write> ConflictsWriter<'a, 'b, W> {
         path: &'b str,
         inode_vertex: Position<ChangeId>,
         conflicts: &'a mut Vec<crate::output::Conflict>,
+        style: ConflictStyle,
     ) -> Self {
         ConflictsWriter {
             inode_vertex,
@@ -82,6 +125,7 @@ impl<'a, 'b, W: std::io::Write> ConflictsWriter<'a, 'b, W> {
             path,
             conflicts,
             buf: Vec::new(),
+            style,
         }
     }
 }
@@ -133,7 +177,7 @@ impl<'a, 'b, W: std::io::Write> VertexBuffer for ConflictsWriter<'a, 'b, W> {
             self.lines += 1;
             debug!("{:?}", s.as_bytes());
         }
-        write!(self.w, "{} {}", s, id)?;
+        write!(self.w, "{} {}", self.style.marker(s), id)?;
         match sides {
             Some((changes, sides)) => {
                 for side in sides {
@@ -201,6 +245,27 @@ impl<'a, 'b, W: std::io::Write> VertexBuffer for ConflictsWriter<'a, 'b, W> {
         });
         self.output_conflict_marker::<C>(START_MARKER, id, None)
     }
+    fn wants_conflict_base(&self) -> bool {
+        self.style == ConflictStyle::Diff3
+    }
+    fn conflict_base(&mut self, id: usize, base: &[u8]) -> Result<(), std::io::Error> {
+        if self.style != ConflictStyle::Diff3 {
+            return Ok(());
+        }
+        if !self.new_line {
+            self.lines += 1;
+            self.w.write_all(b"\n")?;
+        }
+        writeln!(self.w, "{} {}", BASE_MARKER, id)?;
+        self.w.write_all(base)?;
+        self.lines += 1 + base.iter().filter(|c| **c == b'\n').count();
+        if !base.is_empty() && !base.ends_with(b"\n") {
+            self.lines += 1;
+            self.w.write_all(b"\n")?;
+        }
+        self.new_line = true;
+        Ok(())
+    }
     fn conflict_next<C: ChangeStore>(
         &mut self,
         id_: usize,
@@ -242,6 +307,7 @@ pub struct Writer<W: std::io::Write> {
     buf: Vec<u8>,
     new_line: bool,
     is_zombie: bool,
+    style: ConflictStyle,
 }
 
 impl<W: std::io::Write> Writer<W> {
@@ -251,8 +317,14 @@ impl<W: std::io::Write> Writer<W> {
             new_line: true,
             buf: Vec::new(),
             is_zombie: false,
+            style: ConflictStyle::default(),
         }
     }
+    /// Write the conflict markers in style `style`.
+    pub fn with_style(mut self, style: ConflictStyle) -> Self {
+        self.style = style;
+        self
+    }
     pub fn into_inner(self) -> W {
         self.w
     }
@@ -300,7 +372,7 @@ impl<W: std::io::Write> VertexBuffer for Writer<W> {
         if !self.new_line {
             self.w.write_all(b"\n")?;
         }
-        write!(self.w, "{} {}", s, id)?;
+        write!(self.w, "{} {}", self.style.marker(s), id)?;
         match sides {
             Some((changes, sides)) => {
                 for side in sides {
@@ -349,4 +421,150 @@ impl<W: std::io::Write> VertexBuffer for Writer<W> {
     fn begin_cyclic_conflict<C: ChangeStore>(&mut self, id: usize) -> Result<(), std::io::Error> {
         self.output_conflict_marker::<C>(START_MARKER, id, None)
     }
+    fn wants_conflict_base(&self) -> bool {
+        self.style == ConflictStyle::Diff3
+    }
+    fn conflict_base(&mut self, id: usize, base: &[u8]) -> Result<(), std::io::Error> {
+        if self.style != ConflictStyle::Diff3 {
+            return Ok(());
+        }
+        if !self.new_line {
+            self.w.write_all(b"\n")?;
+        }
+        writeln!(self.w, "{} {}", BASE_MARKER, id)?;
+        self.w.write_all(base)?;
+        if !base.is_empty() && !base.ends_with(b"\n") {
+            self.w.write_all(b"\n")?;
+        }
+        self.new_line = true;
+        Ok(())
+    }
+}
+
+/// A [`VertexBuffer`] writing the sides of the conflicts of a file to
+/// separate buffers, for use by three-way merge tools. The lines
+/// outside conflicts are written to all three buffers.
+///
+/// In order and cyclic conflicts, the first side goes to `local`
+/// and the other sides to `remote`, while `base` gets the lines
+/// deleted by the changes of order conflicts, if any. The
+/// lines of zombie conflicts were deleted by one side and kept by
+/// the other, so they go to `local` and `base`. Nested conflicts are
+/// flattened into the side of the outermost conflict.
+#[derive(Debug, Default)]
+pub struct SidesWriter {
+    pub base: Vec<u8>,
+    pub local: Vec<u8>,
+    pub remote: Vec<u8>,
+    /// Number of conflicts found in the file.
+    pub conflicts: usize,
+    stack: Vec<SidesFrame>,
+    buf: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SidesFrame {
+    Order { side: usize },
+    Zombie,
+}
+
+impl SidesWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn begin(&mut self, frame: SidesFrame) {
+        if self.stack.is_empty() {
+            self.conflicts += 1
+        }
+        self.stack.push(frame)
+    }
+}
+
+impl VertexBuffer for SidesWriter {
+    fn output_line<E, C>(&mut self, v: Vertex<ChangeId>, c: C) -> Result<(), E>
+    where
+        E: From<std::io::Error>,
+        C: FnOnce(&mut [u8]) -> Result<(), E>,
+    {
+        self.buf.resize(v.end - v.start, 0);
+        c(&mut self.buf)?;
+        let (base, local, remote) = match self.stack.first() {
+            None => (true, true, true),
+            Some(SidesFrame::Order { side: 0 }) => (false, true, false),
+            Some(SidesFrame::Order { .. }) => (false, false, true),
+            Some(SidesFrame::Zombie) => (true, true, false),
+        };
+        if base {
+            self.base.extend_from_slice(&self.buf)
+        }
+        if local {
+            self.local.extend_from_slice(&self.buf)
+        }
+        if remote {
+            self.remote.extend_from_slice(&self.buf)
+        }
+        Ok(())
+    }
+
+    fn output_conflict_marker<C: ChangeStore>(
+        &mut self,
+        _: &str,
+        _: usize,
+        _: Option<(&C, &[&Hash])>,
+    ) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+
+    fn begin_conflict<C: ChangeStore>(
+        &mut self,
+        _: usize,
+        _: Option<(&C, &[&Hash])>,
+    ) -> Result<(), std::io::Error> {
+        self.begin(SidesFrame::Order { side: 0 });
+        Ok(())
+    }
+    fn begin_zombie_conflict<C: ChangeStore>(
+        &mut self,
+        _: usize,
+        _: Option<(&C, &[&Hash])>,
+    ) -> Result<(), std::io::Error> {
+        self.begin(SidesFrame::Zombie);
+        Ok(())
+    }
+    fn begin_cyclic_conflict<C: ChangeStore>(&mut self, _: usize) -> Result<(), std::io::Error> {
+        self.begin(SidesFrame::Order { side: 0 });
+        Ok(())
+    }
+    fn conflict_next<C: ChangeStore>(
+        &mut self,
+        _: usize,
+        _: Option<(&C, &[&Hash])>,
+    ) -> Result<(), std::io::Error> {
+        if let Some(SidesFrame::Order { side }) = self.stack.last_mut() {
+            *side += 1
+        }
+        Ok(())
+    }
+    fn end_conflict<C: ChangeStore>(&mut self, _: usize) -> Result<(), std::io::Error> {
+        self.stack.pop();
+        Ok(())
+    }
+    fn end_zombie_conflict<C: ChangeStore>(&mut self, _: usize) -> Result<(), std::io::Error> {
+        self.stack.pop();
+        Ok(())
+    }
+    fn end_cyclic_conflict<C: ChangeStore>(&mut self, _: usize) -> Result<(), std::io::Error> {
+        self.stack.pop();
+        Ok(())
+    }
+    fn wants_conflict_base(&self) -> bool {
+        true
+    }
+    fn conflict_base(&mut self, _: usize, base: &[u8]) -> Result<(), std::io::Error> {
+        if self.stack.len() == 1 {
+            self.base.extend_from_slice(base)
+        }
+        Ok(())
+    }
 }
@@ -245,7 +245,7 @@ impl FileSystem {
         follow: F,
     ) -> Result<Untracked, std::io::Error> {
         debug!("full = {:?}", full);
-        let meta = std::fs::metadata(&full)?;
+        let meta = std::fs::symlink_metadata(&full)?;
         debug!("meta = {:?}", meta);
         let (sender, receiver) = std::sync::mpsc::sync_channel(100);
 
@@ -372,13 +372,46 @@ impl FileSystem {
         path.extend(crate::path::components(file));
         path
     }
+
+    /// The path of `file`, for writing. Writing through a symbolic
+    /// link to a directory could write outside of the working copy,
+    /// so this fails if one of the parents of `file` is a symbolic
+    /// link.
+    fn write_path(&self, file: &str) -> Result<PathBuf, std::io::Error> {
+        let mut path = self.root.clone();
+        let mut components = crate::path::components(file).peekable();
+        while let Some(c) = components.next() {
+            path.push(c);
+            if components.peek().is_none() {
+                break;
+            }
+            match std::fs::symlink_metadata(&path) {
+                Ok(meta) if meta.file_type().is_symlink() => {
+                    return Err(std::io::Error::other(format!(
+                        "Not writing {:?} through the symbolic link {:?}",
+                        file, path
+                    )))
+                }
+                Ok(_) => {}
+                // The remaining parents don't exist either.
+                Err(_) => {
+                    path.extend(components);
+                    break;
+                }
+            }
+        }
+        Ok(path)
+    }
 }
 
 impl WorkingCopyRead for FileSystem {
     type Error = std::io::Error;
     fn file_metadata(&self, file: &str) -> Result<InodeMetadata, Self::Error> {
         debug!("metadata {:?}", file);
-        let attr = std::fs::metadata(&self.path(file))?;
+        let attr = std::fs::symlink_metadata(self.path(file))?;
+        if attr.file_type().is_symlink() {
+            return Ok(InodeMetadata::SYMLINK);
+        }
         let permissions = permissions(&attr).unwrap_or(0o700);
         debug!("permissions = {:?}", permissions);
         Ok(InodeMetadata::new(permissions & 0o100, attr.is_dir()))
@@ -386,7 +419,15 @@ impl WorkingCopyRead for FileSystem {
     fn read_file(&self, file: &str, buffer: &mut Vec<u8>) -> Result<(), Self::Error> {
         use std::io::Read;
         debug!("read_file {:?}", file);
-        let mut f = std::fs::File::open(&self.path(file))?;
+        let path = self.path(file);
+        if std::fs::symlink_metadata(&path)?.file_type().is_symlink() {
+            // The contents of a symbolic link are its target.
+            use path_slash::PathExt;
+            let target = std::fs::read_link(&path)?;
+            buffer.extend(target.to_slash_lossy().as_bytes());
+            return Ok(());
+        }
+        let mut f = std::fs::File::open(&path)?;
         f.read_to_end(buffer)?;
         Ok(())
     }
@@ -394,7 +435,7 @@ impl WorkingCopyRead for FileSystem {
     #[cfg(not(unix))]
     fn modified_time(&self, file: &str) -> Result<std::time::SystemTime, Self::Error> {
         debug!("modified_time {:?}", file);
-        let attr = std::fs::metadata(&self.path(file))?;
+        let attr = std::fs::symlink_metadata(&self.path(file))?;
         Ok(attr.modified()?)
     }
 
@@ -414,13 +455,20 @@ impl WorkingCopyRead for FileSystem {
 impl WorkingCopy for FileSystem {
     fn create_dir_all(&self, file: &str) -> Result<(), Self::Error> {
         debug!("create_dir_all {:?}", file);
-        Ok(std::fs::create_dir_all(&self.path(file))?)
+        let path = self.write_path(file)?;
+        if std::fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_symlink()) {
+            return Err(std::io::Error::other(format!(
+                "Not creating directory {:?}, which is a symbolic link",
+                path
+            )));
+        }
+        Ok(std::fs::create_dir_all(&path)?)
     }
 
     fn remove_path(&self, path: &str, rec: bool) -> Result<(), Self::Error> {
         debug!("remove_path {:?}", path);
-        let path = self.path(path);
-        if let Ok(meta) = std::fs::metadata(&path) {
+        let path = self.write_path(path)?;
+        if let Ok(meta) = std::fs::symlink_metadata(&path) {
             if let Err(e) = if meta.is_dir() {
                 if rec {
                     std::fs::remove_dir_all(&path)
@@ -437,8 +485,8 @@ impl WorkingCopy for FileSystem {
     }
     fn rename(&self, former: &str, new: &str) -> Result<(), Self::Error> {
         debug!("rename {:?} {:?}", former, new);
-        let former = self.path(former);
-        let new = self.path(new);
+        let former = self.write_path(former)?;
+        let new = self.write_path(new)?;
         if let Some(p) = new.parent() {
             std::fs::create_dir_all(p)?
         }
@@ -448,7 +496,7 @@ impl WorkingCopy for FileSystem {
     #[cfg(not(windows))]
     fn set_permissions(&self, name: &str, permissions: u16) -> Result<(), Self::Error> {
         use std::os::unix::fs::PermissionsExt;
-        let name = self.path(name);
+        let name = self.write_path(name)?;
         debug!("set_permissions: {:?}", name);
         let metadata = std::fs::metadata(&name)?;
         let mut current = metadata.permissions();
@@ -471,9 +519,25 @@ impl WorkingCopy for FileSystem {
         Ok(())
     }
 
+    fn create_symlink(&self, file: &str, target: &str) -> Result<(), Self::Error> {
+        let path = self.write_path(file)?;
+        debug!("create_symlink {:?} {:?}", path, target);
+        if let Some(p) = path.parent() {
+            std::fs::create_dir_all(p)?
+        }
+        if let Ok(meta) = std::fs::symlink_metadata(&path) {
+            if meta.is_dir() {
+                std::fs::remove_dir_all(&path)?
+            } else {
+                std::fs::remove_file(&path)?
+            }
+        }
+        symlink(target, &path)
+    }
+
     type Writer = std::io::BufWriter<std::fs::File>;
     fn write_file(&self, file: &str, _: Inode) -> Result<Self::Writer, Self::Error> {
-        let path = self.path(file);
+        let path = self.write_path(file)?;
         debug!("path = {:?}", path);
         if let Some(p) = path.parent() {
             std::fs::create_dir_all(p).unwrap_or(())
@@ -486,6 +550,15 @@ impl WorkingCopy for FileSystem {
     }
 }
 
+#[cfg(unix)]
+fn symlink(target: &str, path: &Path) -> Result<(), std::io::Error> {
+    std::os::unix::fs::symlink(target, path)
+}
+#[cfg(windows)]
+fn symlink(target: &str, path: &Path) -> Result<(), std::io::Error> {
+    std::os::windows::fs::symlink_file(target.replace('/', "\\"), path)
+}
+
 #[cfg(not(windows))]
 fn permissions(attr: &std::fs::Metadata) -> Option<usize> {
     use std::os::unix::fs::PermissionsExt;
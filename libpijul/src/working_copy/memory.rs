@@ -88,6 +88,20 @@ impl Memory {
         )
     }
 
+    /// Add a symbolic link to `target`. The contents of the link are
+    /// the target path.
+    pub fn add_symlink(&self, file: &str, target: &str) {
+        let last = SystemTime::now();
+        self.add_inode(
+            file,
+            Inode::File {
+                meta: InodeMetadata::SYMLINK,
+                last_modified: last,
+                contents: Arc::new(Mutex::new(target.as_bytes().to_vec())),
+            },
+        )
+    }
+
     pub fn add_dir(&self, file: &str) {
         let file_meta = InodeMetadata::new(0o100, true);
         let last = SystemTime::now();
@@ -290,6 +304,7 @@ impl WorkingCopy for Memory {
         debug!("set_permissions {:?}", file);
         let mut m = self.0.lock();
         match m.get_file_mut(file) {
+            Some(Inode::File { meta, .. }) if meta.is_symlink() => {}
             Some(Inode::File { ref mut meta, .. }) => {
                 *meta = InodeMetadata::new(permissions as usize & 0o100, false);
             }
@@ -300,15 +315,26 @@ impl WorkingCopy for Memory {
         }
         Ok(())
     }
+    fn create_symlink(&self, file: &str, target: &str) -> Result<(), Self::Error> {
+        debug!("create_symlink {:?} {:?}", file, target);
+        self.0.lock().remove_path_(file);
+        self.add_symlink(file, target);
+        Ok(())
+    }
 
     type Writer = Writer;
     fn write_file(&self, file: &str, _: crate::Inode) -> Result<Self::Writer, Self::Error> {
         let mut m = self.0.lock();
         if let Some(f) = m.get_file_mut(file) {
             if let Inode::File {
-                ref mut contents, ..
+                ref mut contents,
+                ref mut meta,
+                ..
             } = f
             {
+                if meta.is_symlink() {
+                    *meta = InodeMetadata::new(0, false)
+                }
                 contents.lock().clear();
                 return Ok(Writer {
                     w: contents.clone(),
@@ -44,6 +44,9 @@ pub trait WorkingCopy: WorkingCopyRead {
     fn remove_path(&self, name: &str, rec: bool) -> Result<(), Self::Error>;
     fn rename(&self, former: &str, new: &str) -> Result<(), Self::Error>;
     fn set_permissions(&self, name: &str, permissions: u16) -> Result<(), Self::Error>;
+    /// Create a symbolic link at `file`, pointing to `target`,
+    /// replacing `file` if it already exists.
+    fn create_symlink(&self, file: &str, target: &str) -> Result<(), Self::Error>;
 
     type Writer: std::io::Write;
     fn write_file(&self, file: &str, inode: Inode) -> Result<Self::Writer, Self::Error>;
@@ -85,6 +88,9 @@ impl WorkingCopy for Sink {
     fn set_permissions(&self, _name: &str, _permissions: u16) -> Result<(), Self::Error> {
         Ok(())
     }
+    fn create_symlink(&self, _file: &str, _target: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
 
     type Writer = std::io::Sink;
     fn write_file(&self, _file: &str, _inode: Inode) -> Result<Self::Writer, Self::Error> {
@@ -6,7 +6,7 @@ use std::path::{Path, PathBuf};
 
 use anyhow::anyhow;
 use dialoguer::theme;
-use log::debug;
+use log::{debug, warn};
 use serde_derive::{Deserialize, Serialize};
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -19,6 +19,8 @@ pub struct Global {
     pub pager: Option<Choice>,
     pub template: Option<Templates>,
     pub ignore_kinds: Option<HashMap<String, Vec<String>>>,
+    pub require_signatures: Option<RequireSignatures>,
+    pub merge_tool: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -65,6 +67,85 @@ impl Default for Choice {
     }
 }
 
+/// What to do with changes whose signature cannot be verified, when
+/// they are applied, pulled or cloned.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RequireSignatures {
+    #[serde(rename = "warn")]
+    Warn,
+    #[serde(rename = "deny")]
+    Deny,
+}
+
+/// The algorithm used by `record` and `diff` to compare files.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DiffAlgorithm {
+    #[serde(rename = "myers")]
+    Myers,
+    #[serde(rename = "patience")]
+    Patience,
+    #[serde(rename = "histogram")]
+    Histogram,
+}
+
+/// The style of the conflict markers written to files.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ConflictStyle {
+    /// `>>>>>>>` before the first side of a conflict and `<<<<<<<`
+    /// after the last one.
+    #[serde(rename = "pijul")]
+    Pijul,
+    /// `<<<<<<<` before the first side and `>>>>>>>` after the last
+    /// one, as in Git.
+    #[serde(rename = "git")]
+    Git,
+    /// Like `git`, with the lines deleted by the changes in conflict
+    /// after the first side, introduced by `|||||||`.
+    #[serde(rename = "diff3")]
+    Diff3,
+}
+
+/// The separator used to split the files matching a pattern into
+/// "lines" when comparing them, instead of newlines.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiffSeparator {
+    /// Glob pattern of the paths of the files, relative to the root
+    /// of the repository, such as `*.md`.
+    pub files: String,
+    /// Either `"line"`, `"word"`, or a regular expression matching
+    /// the end of each "line".
+    pub separator: String,
+}
+
+impl DiffSeparator {
+    /// A regular expression matching the paths of the files.
+    pub fn files_regex(&self) -> Result<String, anyhow::Error> {
+        Ok(globset::Glob::new(&self.files)?.regex().to_string())
+    }
+
+    /// The regular expression matching the end of each "line".
+    pub fn separator_regex(&self) -> &str {
+        match self.separator.as_str() {
+            "line" => "\n",
+            "word" => r"\s+|[^\w\s]",
+            s => s,
+        }
+    }
+}
+
+/// The command line of the merge tool `tool`, run by `pijul resolve`.
+/// `tool` is either `"meld"`, `"kdiff3"`, `"vimdiff"`, or a custom
+/// command run by the shell, with the paths of the files in the
+/// `$BASE`, `$LOCAL`, `$REMOTE` and `$MERGED` environment variables.
+pub fn merge_tool_command(tool: &str) -> &str {
+    match tool {
+        "meld" => r#"meld "$LOCAL" "$BASE" "$REMOTE" --output "$MERGED""#,
+        "kdiff3" => r#"kdiff3 "$BASE" "$LOCAL" "$REMOTE" -o "$MERGED""#,
+        "vimdiff" => r#"vimdiff -f -d -c "wincmd J" "$MERGED" "$LOCAL" "$BASE" "$REMOTE""#,
+        s => s,
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Templates {
     pub message: Option<PathBuf>,
@@ -161,6 +242,90 @@ pub struct Config {
     pub reset_overwrites_changes: Option<Choice>,
     pub colors: Option<Choice>,
     pub pager: Option<Choice>,
+    pub require_signatures: Option<RequireSignatures>,
+    pub diff_algorithm: Option<DiffAlgorithm>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diff_separators: Vec<DiffSeparator>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected_channels: Vec<ProtectedChannel>,
+    pub merge_tool: Option<String>,
+    pub conflict_style: Option<ConflictStyle>,
+}
+
+impl Config {
+    /// The keys allowed to perform `op` on channel `channel`, or
+    /// `None` if the operation is not restricted. The first protected
+    /// channel entry matching `channel` and restricting `op` is used.
+    pub fn allowed_keys(&self, channel: &str, op: ChannelOperation) -> Option<&[String]> {
+        self.protected_channels
+            .iter()
+            .filter(|p| p.matches(channel))
+            .find_map(|p| match op {
+                ChannelOperation::Push => p.push.as_deref(),
+                ChannelOperation::Unrecord => p.unrecord.as_deref(),
+                ChannelOperation::Tag => p.tag.as_deref(),
+            })
+    }
+
+    /// Whether any of `keys` (base58-encoded public keys) is allowed
+    /// to perform `op` on channel `channel`.
+    pub fn is_allowed<'a, I: IntoIterator<Item = &'a str>>(
+        &self,
+        channel: &str,
+        op: ChannelOperation,
+        keys: I,
+    ) -> bool {
+        if let Some(allowed) = self.allowed_keys(channel, op) {
+            keys.into_iter().any(|k| allowed.iter().any(|a| a == k))
+        } else {
+            true
+        }
+    }
+}
+
+/// The identities allowed to write to the channels matching a
+/// pattern. Operations without a list of keys aren't restricted by
+/// this entry, but may be by the next ones matching the channel.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProtectedChannel {
+    /// Glob pattern of channel names, such as `main` or `release-*`.
+    pub channels: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub push: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unrecord: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<Vec<String>>,
+}
+
+impl ProtectedChannel {
+    fn matches(&self, channel: &str) -> bool {
+        match globset::Glob::new(&self.channels) {
+            Ok(g) => g.compile_matcher().is_match(channel),
+            Err(e) => {
+                // Better safe than sorry.
+                warn!("Invalid channel pattern {:?}: {}", self.channels, e);
+                true
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelOperation {
+    Push,
+    Unrecord,
+    Tag,
+}
+
+impl std::fmt::Display for ChannelOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ChannelOperation::Push => write!(f, "push to"),
+            ChannelOperation::Unrecord => write!(f, "unrecord from"),
+            ChannelOperation::Tag => write!(f, "tag"),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -199,10 +364,43 @@ pub struct Shell {
     pub shell: String,
 }
 
+/// Commands run before and after operations on the repository. Hooks
+/// run before an operation (`pre_*`, and `record`) can veto it by
+/// exiting with a non-zero status.
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Hooks {
-    #[serde(default)]
+    #[serde(default, alias = "pre_record")]
     pub record: Vec<HookEntry>,
+    #[serde(default)]
+    pub post_record: Vec<HookEntry>,
+    #[serde(default)]
+    pub pre_apply: Vec<HookEntry>,
+    #[serde(default)]
+    pub post_apply: Vec<HookEntry>,
+    #[serde(default)]
+    pub pre_pull: Vec<HookEntry>,
+    #[serde(default)]
+    pub post_pull: Vec<HookEntry>,
+    #[serde(default)]
+    pub pre_push: Vec<HookEntry>,
+    #[serde(default)]
+    pub post_push: Vec<HookEntry>,
+    #[serde(default)]
+    pub pre_unrecord: Vec<HookEntry>,
+    #[serde(default)]
+    pub post_unrecord: Vec<HookEntry>,
+    #[serde(default)]
+    pub pre_channel_switch: Vec<HookEntry>,
+    #[serde(default)]
+    pub post_channel_switch: Vec<HookEntry>,
+    #[serde(default)]
+    pub pre_tag: Vec<HookEntry>,
+    #[serde(default)]
+    pub post_tag: Vec<HookEntry>,
+    #[serde(default)]
+    pub pre_reset: Vec<HookEntry>,
+    #[serde(default)]
+    pub post_reset: Vec<HookEntry>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -214,6 +412,60 @@ struct RawHook {
     args: Vec<String>,
 }
 
+/// The context of the operation a hook is run for. It is passed to
+/// hooks as JSON on their standard input, and in `PIJUL_*` environment
+/// variables. Since environment variables are limited in size,
+/// `PIJUL_CHANGES` is only set if there are at most
+/// [`MAX_ENV_CHANGES`] changes, the JSON always has all of them.
+#[derive(Debug, Default, Serialize)]
+pub struct HookContext {
+    /// The name of the hook, for example `pre_apply`.
+    pub hook: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// The channel being left, when switching channels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_channel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    /// Hashes of the changes involved, in base32.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<String>,
+    /// The state of the channel, in base32.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+}
+
+/// Maximal number of changes listed in `PIJUL_CHANGES`.
+pub const MAX_ENV_CHANGES: usize = 100;
+
+impl HookContext {
+    fn env(&self) -> Vec<(&'static str, String)> {
+        let mut env = vec![("PIJUL_HOOK", self.hook.to_string())];
+        if let Some(ref c) = self.channel {
+            env.push(("PIJUL_CHANNEL", c.clone()))
+        }
+        if let Some(ref c) = self.from_channel {
+            env.push(("PIJUL_FROM_CHANNEL", c.clone()))
+        }
+        if let Some(ref r) = self.remote {
+            env.push(("PIJUL_REMOTE", r.clone()))
+        }
+        if !self.changes.is_empty() && self.changes.len() <= MAX_ENV_CHANGES {
+            env.push(("PIJUL_CHANGES", self.changes.join(" ")))
+        }
+        if let Some(ref s) = self.state {
+            env.push(("PIJUL_STATE", s.clone()))
+        }
+        if !self.paths.is_empty() {
+            env.push(("PIJUL_PATHS", self.paths.join("\n")))
+        }
+        env
+    }
+}
+
 pub fn shell_cmd(s: &str) -> Result<String, anyhow::Error> {
     let out = if cfg!(target_os = "windows") {
         std::process::Command::new("cmd")
@@ -231,43 +483,45 @@ pub fn shell_cmd(s: &str) -> Result<String, anyhow::Error> {
 }
 
 impl HookEntry {
-    pub fn run(&self, path: PathBuf) -> Result<(), anyhow::Error> {
-        let (proc, s) = match &self.0 {
+    /// The command to run for this hook, along with its name, or
+    /// `None` if the hook is empty.
+    fn command(
+        &self,
+        path: &Path,
+    ) -> Result<Option<(std::process::Command, String)>, anyhow::Error> {
+        match &self.0 {
             toml::Value::String(ref s) => {
                 if s.is_empty() {
-                    return Ok(());
+                    return Ok(None);
                 }
-                (
-                    if cfg!(target_os = "windows") {
-                        std::process::Command::new("cmd")
-                            .current_dir(path)
-                            .args(&["/C", s])
-                            .output()
-                            .expect("failed to execute process")
-                    } else {
-                        std::process::Command::new(
-                            std::env::var("SHELL").unwrap_or("sh".to_string()),
-                        )
-                        .current_dir(path)
-                        .arg("-c")
-                        .arg(s)
-                        .output()
-                        .expect("failed to execute process")
-                    },
-                    s.clone(),
-                )
+                let mut cmd = if cfg!(target_os = "windows") {
+                    let mut cmd = std::process::Command::new("cmd");
+                    cmd.args(&["/C", s]);
+                    cmd
+                } else {
+                    let mut cmd = std::process::Command::new(
+                        std::env::var("SHELL").unwrap_or("sh".to_string()),
+                    );
+                    cmd.arg("-c").arg(s);
+                    cmd
+                };
+                cmd.current_dir(path);
+                Ok(Some((cmd, s.clone())))
             }
             v => {
                 let hook = v.clone().try_into::<RawHook>()?;
-                (
-                    std::process::Command::new(&hook.command)
-                        .current_dir(path)
-                        .args(&hook.args)
-                        .output()
-                        .expect("failed to execute process"),
-                    hook.command,
-                )
+                let mut cmd = std::process::Command::new(&hook.command);
+                cmd.current_dir(path).args(&hook.args);
+                Ok(Some((cmd, hook.command)))
             }
+        }
+    }
+
+    pub fn run(&self, path: PathBuf) -> Result<(), anyhow::Error> {
+        let (proc, s) = if let Some((mut cmd, s)) = self.command(&path)? {
+            (cmd.output().expect("failed to execute process"), s)
+        } else {
+            return Ok(());
         };
         if !proc.status.success() {
             let mut stderr = std::io::stderr();
@@ -276,6 +530,79 @@ impl HookEntry {
         }
         Ok(())
     }
+
+    /// Run this hook with the given context. The output of the hook
+    /// is forwarded to the standard error. If the hook fails, its
+    /// output is returned as an error instead.
+    pub fn run_with_context(
+        &self,
+        path: &Path,
+        context: &HookContext,
+    ) -> Result<(), anyhow::Error> {
+        use std::process::Stdio;
+        let (mut cmd, s) = if let Some(cmd) = self.command(path)? {
+            cmd
+        } else {
+            return Ok(());
+        };
+        let mut child = cmd
+            .envs(context.env())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("Could not run hook {:?}: {}", s, e))?;
+        // Write the input from another thread, since the hook may
+        // write its output before reading all of it.
+        let input = serde_json::to_vec(context)?;
+        let mut stdin = child.stdin.take().unwrap();
+        let writer = std::thread::spawn(move || {
+            // The hook may exit without reading its input.
+            stdin.write_all(&input).unwrap_or(())
+        });
+        let proc = child.wait_with_output()?;
+        writer.join().unwrap();
+        let mut output = proc.stdout;
+        output.extend(&proc.stderr);
+        if !proc.status.success() {
+            let output = String::from_utf8_lossy(&output);
+            let output = output.trim();
+            if output.is_empty() {
+                return Err(anyhow!("Hook {:?} exited with {}", s, proc.status));
+            } else {
+                return Err(anyhow!(
+                    "Hook {:?} exited with {}: {}",
+                    s,
+                    proc.status,
+                    output
+                ));
+            }
+        }
+        std::io::stderr().write_all(&output)?;
+        Ok(())
+    }
+}
+
+/// Run all the `hooks` in directory `path` with the given context,
+/// stopping at the first one that fails.
+pub fn run_hooks(
+    hooks: &[HookEntry],
+    path: &Path,
+    context: &HookContext,
+) -> Result<(), anyhow::Error> {
+    for h in hooks {
+        h.run_with_context(path, context)?
+    }
+    Ok(())
+}
+
+/// Run all the `hooks` in directory `path` with the given context,
+/// after the operation they are run for. Since that operation is
+/// already done, failures are only reported as warnings.
+pub fn run_post_hooks(hooks: &[HookEntry], path: &Path, context: &HookContext) {
+    if let Err(e) = run_hooks(hooks, path, context) {
+        writeln!(std::io::stderr(), "Warning: {}", e).unwrap_or(())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -360,3 +687,76 @@ pub fn load_theme() -> Result<Box<dyn theme::Theme>, anyhow::Error> {
         Ok(Box::new(theme::ColorfulTheme::default()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hook_many_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = HookContext {
+            hook: "post_pull",
+            changes: (0..10_000).map(|i| format!("{:053}", i)).collect(),
+            ..Default::default()
+        };
+        let hook: Hooks =
+            toml::from_str("post_pull = [\"test -z \\\"$PIJUL_CHANGES\\\" && cat > input\"]")
+                .unwrap();
+        run_hooks(&hook.post_pull, dir.path(), &context).unwrap();
+        let input: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.path().join("input")).unwrap()).unwrap();
+        assert_eq!(input["changes"].as_array().unwrap().len(), 10_000);
+    }
+
+    fn protected(config: &str) -> Config {
+        toml::from_str(config).unwrap()
+    }
+
+    #[test]
+    fn protected_channel_glob() {
+        let config = protected(
+            r#"
+[[protected_channels]]
+channels = "release-*"
+push = ["A"]
+"#,
+        );
+        assert!(!config.is_allowed("release-1.0", ChannelOperation::Push, None));
+        assert!(config.is_allowed("release-1.0", ChannelOperation::Push, Some("A")));
+        assert!(!config.is_allowed("release-", ChannelOperation::Push, Some("B")));
+        // Channels not matching the pattern aren't restricted.
+        assert!(config.is_allowed("main", ChannelOperation::Push, None));
+        assert!(config.is_allowed("release", ChannelOperation::Push, None));
+    }
+
+    #[test]
+    fn protected_channel_operations() {
+        let config = protected(
+            r#"
+[[protected_channels]]
+channels = "main"
+push = ["A", "B"]
+tag = ["A"]
+
+[[protected_channels]]
+channels = "*"
+unrecord = ["C"]
+"#,
+        );
+        assert!(config.is_allowed("main", ChannelOperation::Push, Some("B")));
+        assert!(!config.is_allowed("main", ChannelOperation::Push, Some("C")));
+        assert!(config.is_allowed("main", ChannelOperation::Tag, Some("A")));
+        assert!(!config.is_allowed("main", ChannelOperation::Tag, Some("B")));
+        // Entries not restricting an operation don't lift the
+        // restrictions of the next matching ones.
+        assert!(!config.is_allowed("main", ChannelOperation::Unrecord, None));
+        assert!(!config.is_allowed("main", ChannelOperation::Unrecord, Some("A")));
+        assert!(config.is_allowed("main", ChannelOperation::Unrecord, Some("C")));
+        assert!(!config.is_allowed("dev", ChannelOperation::Unrecord, Some("A")));
+        assert!(config.is_allowed("dev", ChannelOperation::Unrecord, Some("C")));
+        // Any of the keys is enough.
+        assert!(config.is_allowed("main", ChannelOperation::Tag, ["B", "A"]));
+        assert!(!config.is_allowed("main", ChannelOperation::Tag, []));
+    }
+}
@@ -37,10 +37,12 @@
 mod create;
 mod load;
 mod repair;
+mod verify;
 
 pub use load::{choose_identity_name, public_key};
 use log::warn;
 pub use repair::fix_identities;
+pub use verify::{ChangeSignature, SignatureStatus, Verifier};
 
 use pijul_config as config;
 use pijul_config::Author;
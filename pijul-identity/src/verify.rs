@@ -0,0 +1,357 @@
+use super::Complete;
+
+use libpijul::change::Change;
+use libpijul::key::KeyError;
+use libpijul::Hash;
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use serde::Serialize;
+
+/// The outcome of checking the signature of a change.
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    /// The change was signed by the key of one of its authors.
+    Valid,
+    /// The key of the author had expired when the change was recorded.
+    KeyExpired,
+    /// The signature does not match the key of any of the authors.
+    Invalid,
+    /// None of the authors' keys could be found in the known identities.
+    UnknownKey,
+    /// The change does not carry a signature.
+    Unsigned,
+}
+
+impl Display for SignatureStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Valid => "valid signature",
+            Self::KeyExpired => "key expired",
+            Self::Invalid => "invalid signature",
+            Self::UnknownKey => "unknown key",
+            Self::Unsigned => "unsigned",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// The signature information of a change.
+#[derive(Clone, Debug, Serialize)]
+pub struct ChangeSignature {
+    pub status: SignatureStatus,
+    /// The public key the signature was checked against.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// The name of the identity owning that key, if it is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+}
+
+impl ChangeSignature {
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.status == SignatureStatus::Valid
+    }
+}
+
+/// Checks change signatures against the identities known to a
+/// repository, which are either the ones stored in its `.pijul/identities`
+/// directory, or the user's own identities.
+pub struct Verifier {
+    id_path: PathBuf,
+    identities: HashMap<String, Option<Complete>>,
+    local: Option<Vec<Complete>>,
+}
+
+impl Verifier {
+    /// Creates a verifier for the repository at `repo_path`.
+    #[must_use]
+    pub fn new(repo_path: &Path) -> Self {
+        let mut id_path = repo_path.join(libpijul::DOT_DIR);
+        id_path.push("identities");
+        Self {
+            id_path,
+            identities: HashMap::new(),
+            local: None,
+        }
+    }
+
+    /// Returns the identity owning public key `key`, preferring the
+    /// user's own identities to the ones stored in the repository.
+    ///
+    /// Keys come from the authors of changes, which anyone can write,
+    /// and identity files from other repositories, so only plain keys
+    /// are looked up, and only identities with that exact key are
+    /// returned.
+    pub fn identity(&mut self, key: &str) -> Option<&Complete> {
+        if !is_base58(key) {
+            debug!("invalid key {key:?}");
+            return None;
+        }
+        if !self.identities.contains_key(key) {
+            let local = self
+                .local
+                .get_or_insert_with(|| Complete::load_all().unwrap_or_default());
+            let id = if let Some(id) = local.iter().find(|id| id.public_key.key == key) {
+                Some(id.clone())
+            } else {
+                std::fs::File::open(self.id_path.join(key))
+                    .ok()
+                    .and_then(|f| serde_json::from_reader::<_, Complete>(f).ok())
+                    .filter(|id| id.public_key.key == key)
+            };
+            debug!("identity for {key:?}: {id:?}");
+            self.identities.insert(key.to_string(), id);
+        }
+        self.identities.get(key).and_then(Option::as_ref)
+    }
+
+    /// Checks the signature of `change`, whose hash is `hash`.
+    ///
+    /// Changes can have several authors, the signature is considered
+    /// valid if it matches the key of any of them, at the date the
+    /// change was recorded.
+    pub fn verify(&mut self, change: &Change, hash: &Hash) -> ChangeSignature {
+        let keys: Vec<&String> = change
+            .hashed
+            .header
+            .authors
+            .iter()
+            .filter_map(|a| a.0.get("key"))
+            .collect();
+        let signature = change
+            .unhashed
+            .as_ref()
+            .and_then(|u| u.get("signature"))
+            .and_then(serde_json::Value::as_str);
+        let Some(signature) = signature else {
+            return ChangeSignature {
+                status: SignatureStatus::Unsigned,
+                key: keys.first().map(|k| (*k).clone()),
+                identity: keys.first().and_then(|k| self.identity_name(k)),
+            };
+        };
+        let mut result = ChangeSignature {
+            status: SignatureStatus::UnknownKey,
+            key: keys.first().map(|k| (*k).clone()),
+            identity: None,
+        };
+        let timestamp = change.hashed.header.timestamp;
+        for key in keys {
+            let Some(id) = self.identity(key) else {
+                continue;
+            };
+            let status = match id
+                .public_key
+                .load()
+                .and_then(|pk| pk.verify(&hash.to_bytes(), signature, &timestamp))
+            {
+                Ok(()) => SignatureStatus::Valid,
+                Err(KeyError::Expired) => SignatureStatus::KeyExpired,
+                Err(e) => {
+                    debug!("signature of {hash:?} by {key:?}: {e:?}");
+                    SignatureStatus::Invalid
+                }
+            };
+            if status < result.status {
+                result = ChangeSignature {
+                    status,
+                    key: Some(key.clone()),
+                    identity: self.identity_name(key),
+                };
+                if status == SignatureStatus::Valid {
+                    break;
+                }
+            }
+        }
+        result
+    }
+
+    /// A human-readable name for the identity owning `key`: the name
+    /// of the identity if it is one of the user's, or else the name
+    /// its owner goes by.
+    fn identity_name(&mut self, key: &str) -> Option<String> {
+        let id = self.identity(key)?;
+        let author = &id.config.author;
+        [&id.name, &author.username, &author.display_name]
+            .into_iter()
+            .find(|s| !s.is_empty())
+            .cloned()
+    }
+}
+
+/// Whether `key` is a non-empty base58 string, as public keys are.
+fn is_base58(key: &str) -> bool {
+    const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    !key.is_empty() && key.bytes().all(|b| ALPHABET.contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SignatureStatus, Verifier};
+    use crate::{Complete, Config};
+
+    use libpijul::change::{Author, Change, ChangeHeader, Hashed, Offsets, VERSION};
+    use libpijul::key::SKey;
+    use libpijul::Hash;
+
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    /// A verifier for a repository in `dir` knowing the identities of `keys`.
+    fn verifier(dir: &Path, keys: &[&SKey]) -> Verifier {
+        let id_path = dir.join(libpijul::DOT_DIR).join("identities");
+        std::fs::create_dir_all(&id_path).unwrap();
+        for (i, key) in keys.iter().enumerate() {
+            let id = Complete::new(
+                format!("id{i}"),
+                Config::from(pijul_config::Author {
+                    display_name: format!("id{i}"),
+                    ..Default::default()
+                }),
+                key.public_key(),
+                None,
+            );
+            let f = std::fs::File::create(id_path.join(&key.public_key().key)).unwrap();
+            serde_json::to_writer(f, &id).unwrap();
+        }
+        Verifier::new(dir)
+    }
+
+    /// A change authored by `author`, recorded at `timestamp` and
+    /// signed by `signer`.
+    fn signed_change(
+        author: &SKey,
+        timestamp: chrono::DateTime<chrono::Utc>,
+        signer: Option<&SKey>,
+    ) -> (Change, Hash) {
+        let mut key = BTreeMap::new();
+        key.insert("key".to_string(), author.public_key().key);
+        let mut change = Change {
+            offsets: Offsets::default(),
+            hashed: Hashed {
+                version: VERSION,
+                header: ChangeHeader {
+                    message: "test".to_string(),
+                    description: None,
+                    timestamp,
+                    authors: vec![Author(key)],
+                },
+                dependencies: Vec::new(),
+                extra_known: Vec::new(),
+                metadata: Vec::new(),
+                changes: Vec::new(),
+                contents_hash: Hash::None,
+            },
+            unhashed: None,
+            contents: Vec::new(),
+        };
+        let hash = change.hash().unwrap();
+        if let Some(signer) = signer {
+            change.unhashed = Some(serde_json::json!({
+                "signature": signer.sign_raw(&hash.to_bytes()).unwrap(),
+            }));
+        }
+        (change, hash)
+    }
+
+    #[test]
+    fn valid() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SKey::generate(None);
+        let mut verifier = verifier(dir.path(), &[&key]);
+        let (change, hash) = signed_change(&key, chrono::Utc::now(), Some(&key));
+        let sig = verifier.verify(&change, &hash);
+        assert_eq!(sig.status, SignatureStatus::Valid);
+        assert_eq!(sig.key, Some(key.public_key().key));
+        assert_eq!(sig.identity.as_deref(), Some("id0"));
+    }
+
+    #[test]
+    fn invalid() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SKey::generate(None);
+        let other = SKey::generate(None);
+        let mut verifier = verifier(dir.path(), &[&key, &other]);
+        let (change, hash) = signed_change(&key, chrono::Utc::now(), Some(&other));
+        assert_eq!(
+            verifier.verify(&change, &hash).status,
+            SignatureStatus::Invalid
+        );
+    }
+
+    #[test]
+    fn unsigned() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SKey::generate(None);
+        let mut verifier = verifier(dir.path(), &[&key]);
+        let (change, hash) = signed_change(&key, chrono::Utc::now(), None);
+        let sig = verifier.verify(&change, &hash);
+        assert_eq!(sig.status, SignatureStatus::Unsigned);
+        assert_eq!(sig.identity.as_deref(), Some("id0"));
+    }
+
+    #[test]
+    fn unknown_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SKey::generate(None);
+        let mut verifier = verifier(dir.path(), &[]);
+        let (change, hash) = signed_change(&key, chrono::Utc::now(), Some(&key));
+        let sig = verifier.verify(&change, &hash);
+        assert_eq!(sig.status, SignatureStatus::UnknownKey);
+        assert_eq!(sig.identity, None);
+    }
+
+    #[test]
+    fn expired_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = chrono::Utc::now();
+        let key = SKey::generate(Some(now + chrono::Duration::hours(1)));
+        let mut verifier = verifier(dir.path(), &[&key]);
+        let (change, hash) = signed_change(&key, now + chrono::Duration::hours(2), Some(&key));
+        assert_eq!(
+            verifier.verify(&change, &hash).status,
+            SignatureStatus::KeyExpired
+        );
+        // Changes recorded before the expiry date remain valid.
+        let (change, hash) = signed_change(&key, now, Some(&key));
+        assert_eq!(
+            verifier.verify(&change, &hash).status,
+            SignatureStatus::Valid
+        );
+    }
+
+    #[test]
+    fn mismatched_identity_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let victim = SKey::generate(None);
+        let attacker = SKey::generate(None);
+        let mut verifier = verifier(dir.path(), &[&attacker]);
+        // The attacker's identity, stored under the victim's key.
+        let id_path = dir.path().join(libpijul::DOT_DIR).join("identities");
+        std::fs::rename(
+            id_path.join(attacker.public_key().key),
+            id_path.join(victim.public_key().key),
+        )
+        .unwrap();
+        let (change, hash) = signed_change(&victim, chrono::Utc::now(), Some(&attacker));
+        let sig = verifier.verify(&change, &hash);
+        assert_eq!(sig.status, SignatureStatus::UnknownKey);
+        assert_eq!(sig.identity, None);
+    }
+
+    #[test]
+    fn path_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SKey::generate(None);
+        let mut verifier = verifier(dir.path(), &[&key]);
+        let path = format!("../identities/{}", key.public_key().key);
+        assert!(verifier.identity(&path).is_none());
+        assert!(verifier.identity(&key.public_key().key).is_some());
+    }
+}
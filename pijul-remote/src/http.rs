@@ -430,6 +430,7 @@ impl Http {
 
     pub async fn archive<W: std::io::Write + Send + 'static>(
         &mut self,
+        format: libpijul::output::ArchiveFormat,
         prefix: Option<String>,
         state: Option<(libpijul::Merkle, &[Hash])>,
         mut w: W,
@@ -457,6 +458,11 @@ impl Http {
         } else {
             res
         };
+        let res = if format != libpijul::output::ArchiveFormat::TarGz {
+            res.query(&[("format", format.extension())])
+        } else {
+            res
+        };
         let res = res
             .header(reqwest::header::USER_AGENT, USER_AGENT)
             .send()
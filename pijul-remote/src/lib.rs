@@ -1036,8 +1036,9 @@ impl RemoteRepo {
         }
     }
 
-    pub async fn archive<W: std::io::Write + Send + 'static>(
+    pub async fn archive<W: std::io::Write + std::io::Seek + Send + 'static>(
         &mut self,
+        format: libpijul::output::ArchiveFormat,
         prefix: Option<String>,
         state: Option<(Merkle, &[Hash])>,
         umask: u16,
@@ -1050,26 +1051,27 @@ impl RemoteRepo {
                     &l.root,
                     pijul_repository::max_files()?,
                 );
-                let mut tarball = libpijul::output::Tarball::new(w, prefix, umask);
+                let mut archive = libpijul::output::ArchiveWriter::new(format, w, prefix, umask)?;
                 let conflicts = if let Some((state, extra)) = state {
                     let txn = l.pristine.arc_txn_begin()?;
                     let channel = {
                         let txn = txn.read();
                         txn.load_channel(&l.channel)?.unwrap()
                     };
-                    txn.archive_with_state(&changes, &channel, &state, extra, &mut tarball, 0)?
+                    txn.archive_with_state(&changes, &channel, &state, extra, &mut archive, 0)?
                 } else {
                     let txn = l.pristine.arc_txn_begin()?;
                     let channel = {
                         let txn = txn.read();
                         txn.load_channel(&l.channel)?.unwrap()
                     };
-                    txn.archive(&changes, &channel, &mut tarball)?
+                    txn.archive(&changes, &channel, &mut archive)?
                 };
+                archive.finish()?;
                 Ok(conflicts.len() as u64)
             }
-            RemoteRepo::Ssh(ref mut s) => s.archive(prefix, state, w).await,
-            RemoteRepo::Http(ref mut h) => h.archive(prefix, state, w).await,
+            RemoteRepo::Ssh(ref mut s) => s.archive(format, prefix, state, w).await,
+            RemoteRepo::Http(ref mut h) => h.archive(format, prefix, state, w).await,
             RemoteRepo::LocalChannel(_) => unreachable!(),
             RemoteRepo::None => unreachable!(),
         }
@@ -188,6 +188,7 @@ impl Local {
             None,
             std::thread::available_parallelism()?.get(),
             0,
+            libpijul::vertex_buffer::ConflictStyle::default(),
         )?;
         txn.commit()?;
         Ok(())
@@ -814,6 +814,7 @@ impl Ssh {
 
     pub async fn archive<W: std::io::Write + Send + 'static>(
         &mut self,
+        format: libpijul::output::ArchiveFormat,
         prefix: Option<String>,
         state: Option<(Merkle, &[Hash])>,
         w: W,
@@ -828,30 +829,25 @@ impl Ssh {
             w: Box::new(w),
         };
         self.run_protocol().await?;
+        // The format is omitted for tar.gz, which older servers
+        // understand.
+        let mut cmd = if format == libpijul::output::ArchiveFormat::TarGz {
+            format!("archive {}", self.channel)
+        } else {
+            format!("archive format={} {}", format, self.channel)
+        };
         if let Some((ref state, ref extra)) = state {
-            let mut cmd = format!("archive {} {}", self.channel, state.to_base32(),);
+            cmd.push_str(&format!(" {}", state.to_base32()));
             for e in extra.iter() {
                 cmd.push_str(&format!(" {}", e.to_base32()));
             }
-            if let Some(ref p) = prefix {
-                cmd.push_str(" :");
-                cmd.push_str(p)
-            }
-            cmd.push('\n');
-            self.c.data(cmd.as_bytes()).await?;
-        } else {
-            self.c
-                .data(
-                    format!(
-                        "archive {}{}{}\n",
-                        self.channel,
-                        if prefix.is_some() { " :" } else { "" },
-                        prefix.unwrap_or_else(String::new)
-                    )
-                    .as_bytes(),
-                )
-                .await?;
         }
+        if let Some(ref p) = prefix {
+            cmd.push_str(" :");
+            cmd.push_str(p)
+        }
+        cmd.push('\n');
+        self.c.data(cmd.as_bytes()).await?;
         let conflicts = receiver.await.unwrap_or(0);
         Ok(conflicts)
     }
@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use anyhow::bail;
 use clap::{Parser, ValueHint};
 use libpijul::changestore::ChangeStore;
-use libpijul::{DepsTxnT, GraphTxnT, MutTxnTExt, TxnT};
+use libpijul::{Base32, DepsTxnT, GraphTxnT, MutTxnTExt, TxnT};
 use libpijul::{HashMap, HashSet};
 use log::*;
 
@@ -14,15 +14,18 @@ use pijul_repository::Repository;
 pub struct Apply {
     /// Set the repository where this command should run. Defaults to the first ancestor of the current directory that contains a `.pijul` directory.
     #[clap(long = "repository", value_hint = ValueHint::DirPath)]
-    repo_path: Option<PathBuf>,
+    pub repo_path: Option<PathBuf>,
     /// Apply change to this channel
     #[clap(long = "channel")]
-    channel: Option<String>,
+    pub channel: Option<String>,
     /// Only apply the dependencies of the change, not the change itself. Only applicable for a single change.
     #[clap(long = "deps-only")]
-    deps_only: bool,
+    pub deps_only: bool,
+    /// Show the paths that would be touched and the conflicts that would result, without modifying the channel
+    #[clap(long = "dry-run", conflicts_with = "deps_only")]
+    pub dry_run: bool,
     /// The change that need to be applied. If this value is missing, read the change in text format on the standard input.
-    change: Vec<String>,
+    pub change: Vec<String>,
 }
 
 impl Apply {
@@ -46,6 +49,11 @@ impl Apply {
         } else {
             bail!("Channel {:?} not found", channel_name)
         };
+        super::protected::check_channel_permission(
+            &repo,
+            channel_name,
+            pijul_config::ChannelOperation::Push,
+        )?;
 
         let mut hashes = Vec::new();
         if self.change.is_empty() {
@@ -93,6 +101,17 @@ impl Apply {
                 }
             })
         }
+        super::signatures::check_signatures(&repo, hashes.iter())?;
+        if self.dry_run {
+            return super::dry_run::dry_run(&repo, &txn, &channel, &[], &hashes);
+        }
+        let mut hook = pijul_config::HookContext {
+            hook: "pre_apply",
+            channel: Some(channel_name.to_string()),
+            changes: hashes.iter().map(|h| h.to_base32()).collect(),
+            ..Default::default()
+        };
+        pijul_config::run_hooks(&repo.config.hooks.pre_apply, &repo.path, &hook)?;
         if self.deps_only {
             if hashes.len() > 1 {
                 bail!("--deps-only is only applicable to a single change")
@@ -148,6 +167,8 @@ impl Apply {
 
             {
                 let mut state = libpijul::RecordBuilder::new();
+                state.separators = super::config::diff_separators(&repo)?;
+                state.conflict_style = super::config::conflict_style(&repo);
                 if touched_files.is_empty() {
                     state.record(
                         txn.clone(),
@@ -196,11 +217,15 @@ impl Apply {
                         None,
                         std::thread::available_parallelism()?.get(),
                         0,
+                        super::config::conflict_style(&repo),
                     )?
                     .into_iter(),
                 );
             }
-            if !touched_files.is_empty() {
+            // Some touched inodes weren't found in the channel before
+            // the apply (for instance files added by these changes),
+            // so output the whole repository.
+            if touched_files.is_empty() {
                 conflicts.extend(
                     libpijul::output::output_repository_no_pending(
                         &repo.working_copy,
@@ -212,6 +237,7 @@ impl Apply {
                         None,
                         std::thread::available_parallelism()?.get(),
                         0,
+                        super::config::conflict_style(&repo),
                     )?
                     .into_iter(),
                 );
@@ -222,7 +248,10 @@ impl Apply {
             std::mem::drop(forked);
             txn.write().drop_channel(&forked_s)?;
         }
+        hook.hook = "post_apply";
+        hook.state = super::hooks::hook_state(&*txn.read(), &*channel.read())?;
         txn.commit()?;
+        pijul_config::run_post_hooks(&repo.config.hooks.post_apply, &repo.path, &hook);
         Ok(())
     }
 }
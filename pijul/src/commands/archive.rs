@@ -2,7 +2,10 @@ use std::path::PathBuf;
 
 use anyhow::bail;
 use clap::{Parser, ValueHint};
-use libpijul::{Hash, Merkle, TxnT};
+use libpijul::changestore::ChangeStore;
+use libpijul::output::{ArchiveFormat, ArchiveWriter, FixedMtime};
+use libpijul::pristine::sanakirja::MutTxn;
+use libpijul::*;
 use log::debug;
 
 use pijul_repository::Repository;
@@ -33,6 +36,16 @@ pub struct Archive {
     /// Append this path in front of each path inside the archive
     #[clap(long = "umask")]
     umask: Option<String>,
+    /// Archive format: tar, tar.gz, tar.zst or zip. Defaults to the
+    /// extension of the output file, or tar.gz.
+    #[clap(long = "format")]
+    format: Option<ArchiveFormat>,
+    /// Make the archive reproducible: all files get the same
+    /// modification time, which is the time of the last change of the
+    /// archived state, or `SOURCE_DATE_EPOCH` if set. Entries are
+    /// always sorted, and owned by root.
+    #[clap(long = "reproducible")]
+    reproducible: bool,
     /// Name of the output file
     #[clap(short = 'o', value_hint = ValueHint::FilePath)]
     name: String,
@@ -63,6 +76,11 @@ impl Archive {
         for h in self.change.iter() {
             extra.push(h.parse()?);
         }
+        let format = self
+            .format
+            .or_else(|| ArchiveFormat::from_file_name(&self.name))
+            .unwrap_or(ArchiveFormat::TarGz);
+        let p = format.file_name(&self.name);
         if let Some(ref mut p) = self.prefix {
             if std::path::Path::new(p).is_absolute() {
                 bail!("Prefix path cannot be absolute")
@@ -93,62 +111,110 @@ impl Archive {
                     path.push(rem);
                 }
             } else {
-                let mut p = std::path::Path::new(&self.name).to_path_buf();
-                if !self.name.ends_with(".tar.gz") {
-                    p.set_extension("tar.gz");
+                if self.reproducible {
+                    bail!("Reproducible archives cannot be made by remote repositories")
                 }
                 let f = std::fs::File::create(&p)?;
                 remote
-                    .archive(self.prefix, state.map(|x| (x, &extra[..])), umask, f)
+                    .archive(
+                        format,
+                        self.prefix,
+                        state.map(|x| (x, &extra[..])),
+                        umask,
+                        f,
+                    )
                     .await?;
                 return Ok(());
             }
         }
         if let Ok(repo) = Repository::find_root(self.repo_path.clone()) {
-            let mut p = std::path::Path::new(&self.name).to_path_buf();
-            if !self.name.ends_with(".tar.gz") {
-                p.set_extension("tar.gz");
-            }
-            let mut f = std::fs::File::create(&p)?;
-            let mut tarball = libpijul::output::Tarball::new(&mut f, self.prefix, umask);
-            let conflicts = if let Some(state) = state {
-                let txn = repo.pristine.arc_txn_begin()?;
-                let channel = {
-                    let txn = txn.read();
-                    let channel_name = if let Some(ref c) = self.channel {
-                        c
-                    } else {
-                        txn.current_channel().unwrap_or(libpijul::DEFAULT_CHANNEL)
-                    };
-                    txn.load_channel(&channel_name)?.unwrap()
+            let txn = repo.pristine.arc_txn_begin()?;
+            let channel = {
+                let txn = txn.read();
+                let channel_name = if let Some(ref c) = self.channel {
+                    c
+                } else {
+                    txn.current_channel().unwrap_or(libpijul::DEFAULT_CHANNEL)
                 };
-                txn.archive_with_state(
-                    &repo.changes,
-                    &channel,
-                    &state,
-                    &extra[..],
-                    &mut tarball,
-                    0,
-                )?
+                if let Some(channel) = txn.load_channel(channel_name)? {
+                    channel
+                } else {
+                    bail!("No such channel: {:?}", channel_name);
+                }
+            };
+            let f = std::fs::File::create(&p)?;
+            let archive = ArchiveWriter::new(format, f, self.prefix, umask)?;
+            let conflicts = if self.reproducible {
+                let mtime = reproducible_mtime(&repo, &txn, &channel, state.as_ref(), &extra)?;
+                debug!("mtime = {:?}", mtime);
+                let mut archive = FixedMtime { archive, mtime };
+                let conflicts = archive_state(&repo, &txn, &channel, state, &extra, &mut archive)?;
+                archive.archive.finish()?;
+                conflicts
             } else {
-                let txn = repo.pristine.arc_txn_begin()?;
-                let channel = {
-                    let txn = txn.read();
-                    let channel_name = if let Some(ref c) = self.channel {
-                        c
-                    } else {
-                        txn.current_channel().unwrap_or(libpijul::DEFAULT_CHANNEL)
-                    };
-                    if let Some(channel) = txn.load_channel(&channel_name)? {
-                        channel
-                    } else {
-                        bail!("No such channel: {:?}", channel_name);
-                    }
-                };
-                txn.archive(&repo.changes, &channel, &mut tarball)?
+                let mut archive = archive;
+                let conflicts = archive_state(&repo, &txn, &channel, state, &extra, &mut archive)?;
+                archive.finish()?;
+                conflicts
             };
             super::print_conflicts(&conflicts)?;
         }
         Ok(())
     }
 }
+
+fn archive_state<A: libpijul::output::Archive<Error = std::io::Error>>(
+    repo: &Repository,
+    txn: &ArcTxn<MutTxn<()>>,
+    channel: &ChannelRef<MutTxn<()>>,
+    state: Option<Merkle>,
+    extra: &[Hash],
+    archive: &mut A,
+) -> Result<Vec<libpijul::output::Conflict>, anyhow::Error> {
+    if let Some(state) = state {
+        Ok(txn.archive_with_state(&repo.changes, channel, &state, extra, archive, 0)?)
+    } else {
+        Ok(txn.archive(&repo.changes, channel, archive)?)
+    }
+}
+
+/// The modification time of the files in reproducible archives, in
+/// seconds since the Unix epoch.
+fn reproducible_mtime(
+    repo: &Repository,
+    txn: &ArcTxn<MutTxn<()>>,
+    channel: &ChannelRef<MutTxn<()>>,
+    state: Option<&Merkle>,
+    extra: &[Hash],
+) -> Result<u64, anyhow::Error> {
+    if let Ok(epoch) = std::env::var("SOURCE_DATE_EPOCH") {
+        if let Ok(epoch) = epoch.parse() {
+            return Ok(epoch);
+        }
+        bail!("Invalid SOURCE_DATE_EPOCH: {:?}", epoch)
+    }
+    let txn = txn.read();
+    let n = if let Some(state) = state {
+        let c = channel.read();
+        if let Some(n) = txn.channel_has_state(txn.states(&*c), &state.into())? {
+            Some(u64::from(n))
+        } else {
+            bail!("State not found: {}", state.to_base32())
+        }
+    } else if let Some(x) = txn.reverse_log(&*channel.read(), None)?.next() {
+        Some(x?.0)
+    } else {
+        None
+    };
+    let last = if let Some(n) = n {
+        txn.get_changes(channel, n)?.map(|(h, _)| h)
+    } else {
+        None
+    };
+    let mut mtime = 0;
+    for h in last.iter().chain(extra.iter()) {
+        let header = repo.changes.get_header(h)?;
+        mtime = mtime.max(header.timestamp.timestamp().max(0) as u64)
+    }
+    Ok(mtime)
+}
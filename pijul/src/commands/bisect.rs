@@ -0,0 +1,391 @@
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use clap::{Parser, ValueHint};
+use libpijul::changestore::ChangeStore;
+use libpijul::*;
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+
+use pijul_repository::Repository;
+
+#[derive(Parser, Debug)]
+pub struct Bisect {
+    /// Set the repository where this command should run. Defaults to the first ancestor of the current directory that contains a `.pijul` directory.
+    #[clap(long = "repository", value_hint = ValueHint::DirPath)]
+    repo_path: Option<PathBuf>,
+    #[clap(subcommand)]
+    subcmd: SubCommand,
+}
+
+#[derive(Parser, Debug)]
+pub enum SubCommand {
+    /// Start bisecting the log of the current channel.
+    /// There must not be unrecorded changes in the working copy.
+    #[clap(name = "start")]
+    Start,
+    /// Mark the state after a change as good (defaults to the state
+    /// being tested).
+    #[clap(name = "good")]
+    Good { change: Option<String> },
+    /// Mark the state after a change as bad (defaults to the state
+    /// being tested).
+    #[clap(name = "bad")]
+    Bad { change: Option<String> },
+    /// Skip the state being tested, for instance if it cannot be built.
+    #[clap(name = "skip")]
+    Skip,
+    /// Test the states automatically with a command, run from the root
+    /// of the repository. Exit code 0 means good, 125 means the state
+    /// cannot be tested, any other code between 1 and 127 means bad.
+    #[clap(name = "run")]
+    Run {
+        #[clap(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Stop bisecting, and reset the working copy to the bisected
+    /// channel.
+    #[clap(name = "reset")]
+    Reset,
+}
+
+/// The bisection in progress, saved in `.pijul/bisect`. The states
+/// are numbered by the number of changes of the log applied in them,
+/// so state 0 is the empty channel.
+#[derive(Serialize, Deserialize)]
+struct BisectState {
+    channel: String,
+    /// Prefix of the scratch channels holding the states being
+    /// tested, chosen so that no channel started with it when the
+    /// bisection started.
+    scratch: String,
+    /// The log of the channel when the bisection started.
+    changes: Vec<String>,
+    current: Option<usize>,
+    good: Option<usize>,
+    bad: Option<usize>,
+    skip: BTreeSet<usize>,
+}
+
+const BISECT_FILE: &str = "bisect";
+
+/// Exit code of `bisect run` commands meaning "skip this state".
+const SKIP_CODE: i32 = 125;
+
+enum Step {
+    Testing(usize),
+    Waiting,
+    Done,
+}
+
+impl BisectState {
+    fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        match std::fs::read(path) {
+            Ok(s) => Ok(serde_json::from_slice(&s)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                bail!("Not bisecting, start with `pijul bisect start`")
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The name of the scratch channel holding state `n`.
+    fn scratch_name(&self, n: usize) -> String {
+        format!("{}-{}", self.scratch, n)
+    }
+
+    /// The last change applied in state `n`.
+    fn hash(&self, n: usize) -> Result<Hash, anyhow::Error> {
+        let h = if let Some(h) = n.checked_sub(1).and_then(|i| self.changes.get(i)) {
+            h
+        } else {
+            bail!(
+                "Invalid bisection state {}, the bisect file may be corrupted",
+                n
+            )
+        };
+        if let Some(hash) = Hash::from_base32(h.as_bytes()) {
+            Ok(hash)
+        } else {
+            bail!("Invalid hash {:?}, the bisect file may be corrupted", h)
+        }
+    }
+
+    /// The state after `change`, or the state being tested.
+    fn state_after(&self, repo: &Repository, change: Option<&str>) -> Result<usize, anyhow::Error> {
+        if let Some(change) = change {
+            let txn = repo.pristine.txn_begin()?;
+            let (hash, _) = txn.hash_from_prefix(change)?;
+            let hash = hash.to_base32();
+            if let Some(i) = self.changes.iter().position(|h| *h == hash) {
+                Ok(i + 1)
+            } else {
+                bail!("Change {} is not in channel {:?}", hash, self.channel)
+            }
+        } else if let Some(current) = self.current {
+            Ok(current)
+        } else {
+            Ok(self.changes.len())
+        }
+    }
+
+    /// The next state to test, if the bisection isn't over.
+    fn next(&self, repo: &Repository, stdout: &mut std::io::Stdout) -> Result<Step, anyhow::Error> {
+        let (good, bad) = match (self.good, self.bad) {
+            (Some(good), Some(bad)) => (good, bad),
+            (None, _) => {
+                writeln!(stdout, "Waiting for a good state")?;
+                return Ok(Step::Waiting);
+            }
+            (_, None) => {
+                writeln!(stdout, "Waiting for a bad state")?;
+                return Ok(Step::Waiting);
+            }
+        };
+        if good >= bad {
+            bail!("The good state must come before the bad state in the log")
+        }
+        if bad == good + 1 {
+            writeln!(stdout, "First bad change:")?;
+            self.print_change(repo, stdout, bad)?;
+            return Ok(Step::Done);
+        }
+        let mid = (good + bad) / 2;
+        let next = (good + 1..bad)
+            .filter(|n| !self.skip.contains(n))
+            .min_by_key(|n| n.abs_diff(mid));
+        if let Some(next) = next {
+            writeln!(
+                stdout,
+                "Bisecting: {} states left to test, testing the state after:",
+                bad - good - 1,
+            )?;
+            self.print_change(repo, stdout, next)?;
+            Ok(Step::Testing(next))
+        } else {
+            writeln!(
+                stdout,
+                "The first bad change could not be found because of the skipped states, it is one of:"
+            )?;
+            for n in good + 1..=bad {
+                self.print_change(repo, stdout, n)?;
+            }
+            Ok(Step::Done)
+        }
+    }
+
+    /// Print the last change applied in state `n`.
+    fn print_change(
+        &self,
+        repo: &Repository,
+        stdout: &mut std::io::Stdout,
+        n: usize,
+    ) -> Result<(), anyhow::Error> {
+        let hash = self.hash(n)?;
+        let header = repo.changes.get_header(&hash)?;
+        writeln!(
+            stdout,
+            "  {} {}",
+            hash.to_base32(),
+            header.message.lines().next().unwrap_or("")
+        )?;
+        Ok(())
+    }
+}
+
+/// A prefix for the scratch channels of a bisection of `channel`,
+/// such that no existing channel name starts with it.
+fn scratch_prefix<T: TxnT>(txn: &T, channel: &str) -> Result<String, anyhow::Error> {
+    let mut names = Vec::new();
+    for c in txn.channels("")? {
+        names.push(txn.name(&*c.read()).to_string())
+    }
+    let mut prefix = format!("{}-bisect", channel);
+    let mut i = 1;
+    while names.iter().any(|n| n.starts_with(&prefix)) {
+        i += 1;
+        prefix = format!("{}-bisect{}", channel, i);
+    }
+    Ok(prefix)
+}
+
+impl Bisect {
+    pub fn run(self) -> Result<(), anyhow::Error> {
+        let repo = Repository::find_root(self.repo_path.clone())?;
+        let path = repo.path.join(libpijul::DOT_DIR).join(BISECT_FILE);
+        let mut stdout = std::io::stdout();
+        if let SubCommand::Start = self.subcmd {
+            if path.exists() {
+                bail!("Already bisecting, use `pijul bisect reset` to stop")
+            }
+            let txn = repo.pristine.arc_txn_begin()?;
+            let channel_name = txn
+                .read()
+                .current_channel()
+                .unwrap_or(libpijul::DEFAULT_CHANNEL)
+                .to_string();
+            let channel = if let Some(channel) = txn.read().load_channel(&channel_name)? {
+                channel
+            } else {
+                bail!("No such channel: {:?}", channel_name)
+            };
+            if super::reset::has_unrecorded_changes(txn.clone(), channel.clone(), &repo)? {
+                bail!("Cannot bisect, as there are unrecorded changes.")
+            }
+            let txn = txn.read();
+            let mut changes = Vec::new();
+            for l in txn.log(&*channel.read(), 0)? {
+                let (_, (h, _)) = l?;
+                changes.push(Hash::from(h).to_base32())
+            }
+            BisectState {
+                scratch: scratch_prefix(&*txn, &channel_name)?,
+                channel: channel_name,
+                changes,
+                current: None,
+                good: None,
+                bad: None,
+                skip: BTreeSet::new(),
+            }
+            .save(&path)?;
+            return Ok(());
+        }
+
+        let mut state = BisectState::load(&path)?;
+        match self.subcmd {
+            SubCommand::Start => unreachable!(),
+            SubCommand::Good { ref change } => {
+                state.good = Some(state.state_after(&repo, change.as_deref())?);
+                std::mem::drop(repo);
+                self.step(&path, &mut state, &mut stdout)?;
+            }
+            SubCommand::Bad { ref change } => {
+                state.bad = Some(state.state_after(&repo, change.as_deref())?);
+                std::mem::drop(repo);
+                self.step(&path, &mut state, &mut stdout)?;
+            }
+            SubCommand::Skip => {
+                if let Some(current) = state.current {
+                    state.skip.insert(current);
+                } else {
+                    bail!("No state is being tested")
+                }
+                std::mem::drop(repo);
+                self.step(&path, &mut state, &mut stdout)?;
+            }
+            SubCommand::Run { ref command } => {
+                if state.current.is_none() {
+                    bail!("Mark a good and a bad state before running a command")
+                }
+                let root = repo.path.clone();
+                std::mem::drop(repo);
+                loop {
+                    let current = state.current.unwrap();
+                    stdout.flush()?;
+                    let status = std::process::Command::new(&command[0])
+                        .args(&command[1..])
+                        .current_dir(&root)
+                        .status()?;
+                    debug!("status = {:?}", status);
+                    match status.code() {
+                        Some(0) => state.good = Some(current),
+                        Some(SKIP_CODE) => {
+                            state.skip.insert(current);
+                        }
+                        Some(c) if (1..128).contains(&c) => state.bad = Some(current),
+                        _ => bail!("The command was interrupted ({})", status),
+                    }
+                    if !self.step(&path, &mut state, &mut stdout)? {
+                        break;
+                    }
+                }
+            }
+            SubCommand::Reset => {
+                std::mem::drop(repo);
+                let scratch = state.current.map(|n| state.scratch_name(n));
+                if scratch.is_some() {
+                    (super::Reset {
+                        repo_path: self.repo_path.clone(),
+                        channel: Some(state.channel.clone()),
+                        dry_run: false,
+                        files: Vec::new(),
+                        force: false,
+                    })
+                    .switch()?;
+                }
+                let repo = Repository::find_root(self.repo_path)?;
+                if let Some(scratch) = scratch {
+                    let mut txn = repo.pristine.mut_txn_begin()?;
+                    txn.drop_channel(&scratch)?;
+                    txn.commit()?;
+                }
+                std::fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Save the bisection, and switch to the next state to test.
+    /// Returns whether there is a state to test.
+    fn step(
+        &self,
+        path: &Path,
+        state: &mut BisectState,
+        stdout: &mut std::io::Stdout,
+    ) -> Result<bool, anyhow::Error> {
+        let repo = Repository::find_root(self.repo_path.clone())?;
+        let n = if let Step::Testing(n) = state.next(&repo, stdout)? {
+            n
+        } else {
+            state.save(path)?;
+            return Ok(false);
+        };
+        let txn = repo.pristine.arc_txn_begin()?;
+        let name = state.scratch_name(n);
+        let channel = if let Some(channel) = txn.read().load_channel(&state.channel)? {
+            channel
+        } else {
+            bail!("No such channel: {:?}", state.channel)
+        };
+        if txn.read().load_channel(&name)?.is_some() {
+            if state.current != Some(n) {
+                bail!("Channel {:?} already exists", name)
+            }
+            txn.write().drop_channel(&name)?;
+        }
+        let scratch = txn.write().fork(&channel, &name)?;
+        for m in (n + 1..=state.changes.len()).rev() {
+            let hash = state.hash(m)?;
+            txn.write().unrecord(&repo.changes, &scratch, &hash, 0)?;
+        }
+        txn.commit()?;
+        std::mem::drop(repo);
+
+        (super::Reset {
+            repo_path: self.repo_path.clone(),
+            channel: Some(name),
+            dry_run: false,
+            files: Vec::new(),
+            force: false,
+        })
+        .switch()?;
+
+        if let Some(previous) = state.current.replace(n) {
+            if previous != n {
+                let repo = Repository::find_root(self.repo_path.clone())?;
+                let mut txn = repo.pristine.mut_txn_begin()?;
+                txn.drop_channel(&state.scratch_name(previous))?;
+                txn.commit()?;
+            }
+        }
+        state.save(path)?;
+        Ok(true)
+    }
+}
@@ -14,6 +14,9 @@ pub struct Change {
     /// The hash of the change to show, or an unambiguous prefix thereof
     #[clap(value_name = "HASH")]
     hash: Option<String>,
+    /// Check the signature of the change against the known identities
+    #[clap(long = "verify")]
+    verify: bool,
 }
 
 impl Change {
@@ -43,6 +46,13 @@ impl Change {
             }
         };
         let change = changes.get_change(&hash)?;
+        if self.verify && !super::signatures::is_root_change(&change) {
+            let signature = pijul_identity::Verifier::new(&repo.path).verify(&change, &hash);
+            println!(
+                "# Signature: {}",
+                super::signatures::SignatureDisplay(&signature)
+            );
+        }
         let colors = super::diff::is_colored(repo.config.pager.as_ref());
         change.write(
             &changes,
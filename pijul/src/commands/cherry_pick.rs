@@ -0,0 +1,192 @@
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use clap::{Parser, ValueHint};
+use libpijul::changestore::ChangeStore;
+use libpijul::*;
+use log::debug;
+
+use pijul_interaction::Confirm;
+use pijul_repository::Repository;
+
+#[derive(Parser, Debug)]
+pub struct CherryPick {
+    /// Set the repository where this command should run. Defaults to the first ancestor of the current directory that contains a `.pijul` directory.
+    #[clap(long = "repository", value_hint = ValueHint::DirPath)]
+    repo_path: Option<PathBuf>,
+    /// Pick the changes from this channel
+    #[clap(long = "from-channel")]
+    from_channel: String,
+    /// Apply the changes to this channel instead of the current channel
+    #[clap(long = "channel")]
+    channel: Option<String>,
+    /// Apply without asking for confirmation
+    #[clap(short = 'y', long = "yes")]
+    yes: bool,
+    /// The changes to pick (unambiguous prefixes are accepted)
+    #[clap(required = true)]
+    changes: Vec<String>,
+}
+
+impl CherryPick {
+    pub fn run(self) -> Result<(), anyhow::Error> {
+        let repo = Repository::find_root(self.repo_path.clone())?;
+        let txn = repo.pristine.arc_txn_begin()?;
+        let channel_name = if let Some(ref c) = self.channel {
+            c.clone()
+        } else {
+            txn.read()
+                .current_channel()
+                .unwrap_or(libpijul::DEFAULT_CHANNEL)
+                .to_string()
+        };
+        if channel_name == self.from_channel {
+            bail!("Cannot cherry-pick from a channel to itself")
+        }
+        let channel = if let Some(channel) = txn.read().load_channel(&channel_name)? {
+            channel
+        } else {
+            bail!("No such channel: {:?}", channel_name)
+        };
+        let from = if let Some(channel) = txn.read().load_channel(&self.from_channel)? {
+            channel
+        } else {
+            bail!("No such channel: {:?}", self.from_channel)
+        };
+
+        let mut picked = Vec::new();
+        for c in self.changes.iter() {
+            let (hash, _) = txn.read().hash_from_prefix(c)?;
+            if txn.read().has_change(&from, &hash)?.is_none() {
+                bail!(
+                    "Change {} is not in channel {:?}",
+                    hash.to_base32(),
+                    self.from_channel
+                )
+            }
+            if txn.read().has_change(&channel, &hash)?.is_some() {
+                writeln!(
+                    std::io::stderr(),
+                    "Change {} is already in channel {:?}",
+                    hash.to_base32(),
+                    channel_name
+                )?;
+                continue;
+            }
+            picked.push(hash)
+        }
+        if picked.is_empty() {
+            return Ok(());
+        }
+
+        // The dependencies missing from the target channel, which
+        // will be applied along with the picked changes.
+        let mut extra = BTreeSet::new();
+        let mut stack = picked.clone();
+        while let Some(h) = stack.pop() {
+            for dep in repo.changes.get_dependencies(&h)? {
+                if txn.read().has_change(&channel, &dep)?.is_none()
+                    && !picked.contains(&dep)
+                    && extra.insert(dep)
+                {
+                    stack.push(dep)
+                }
+            }
+        }
+        let extra: Vec<_> = {
+            let txn = txn.read();
+            let mut extra = extra
+                .into_iter()
+                .map(|h| Ok((txn.has_change(&from, &h)?.unwrap_or(0), h)))
+                .collect::<Result<Vec<_>, anyhow::Error>>()?;
+            extra.sort();
+            extra.into_iter().map(|(_, h)| h).collect()
+        };
+
+        // Apply to a scratch fork of the target first, to find the
+        // conflicts this would create.
+        let before = super::conflicts::channel_conflicts(&repo, &txn, &channel, &[], true)?;
+        let scratch_name = format!("cherry-pick-{}", picked[0].to_base32());
+        let scratch = txn.write().fork(&channel, &scratch_name)?;
+        let after = (|| {
+            for h in picked.iter() {
+                txn.write()
+                    .apply_change_rec(&repo.changes, &mut *scratch.write(), h)?;
+            }
+            super::conflicts::channel_conflicts(&repo, &txn, &scratch, &[], true)
+        })();
+        std::mem::drop(scratch);
+        txn.write().drop_channel(&scratch_name)?;
+        let after = after?;
+        let before: BTreeSet<_> = before
+            .iter()
+            .map(super::conflicts::describe_conflict)
+            .collect();
+        let conflicts: Vec<_> = after
+            .iter()
+            .map(super::conflicts::describe_conflict)
+            .filter(|c| !before.contains(c))
+            .collect();
+        debug!("conflicts: {:?}", conflicts);
+
+        let mut stdout = std::io::stdout();
+        writeln!(stdout, "Changes to pick into channel {:?}:", channel_name)?;
+        for h in picked.iter() {
+            print_change(&mut stdout, &repo, h)?;
+        }
+        if !extra.is_empty() {
+            writeln!(stdout, "\nDependencies that will also be applied:")?;
+            for h in extra.iter() {
+                print_change(&mut stdout, &repo, h)?;
+            }
+        }
+        if conflicts.is_empty() {
+            writeln!(stdout, "\nNo new conflicts.")?;
+        } else {
+            writeln!(stdout, "\nNew conflicts:")?;
+            for c in conflicts.iter() {
+                writeln!(stdout, "  {}", c)?;
+            }
+        }
+        stdout.flush()?;
+
+        if !self.yes
+            && !Confirm::new()?
+                .with_prompt("Apply these changes?")
+                .with_default(false)
+                .interact()?
+        {
+            bail!("User did not wish to continue");
+        }
+
+        std::mem::drop(channel);
+        std::mem::drop(from);
+        std::mem::drop(txn);
+        std::mem::drop(repo);
+        super::Apply {
+            repo_path: self.repo_path,
+            channel: Some(channel_name),
+            deps_only: false,
+            dry_run: false,
+            change: extra
+                .iter()
+                .chain(picked.iter())
+                .map(|h| h.to_base32())
+                .collect(),
+        }
+        .run()
+    }
+}
+
+fn print_change<W: Write>(w: &mut W, repo: &Repository, h: &Hash) -> Result<(), anyhow::Error> {
+    let header = repo.changes.get_header(h)?;
+    writeln!(
+        w,
+        "  {} {}",
+        h.to_base32(),
+        header.message.lines().next().unwrap_or("")
+    )?;
+    Ok(())
+}
@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use anyhow::bail;
 use clap::{Parser, ValueHint};
-use libpijul::{ChannelMutTxnT, MutTxnT};
+use libpijul::{ChannelMutTxnT, MutTxnT, TxnTExt};
 use log::debug;
 use pijul_repository::*;
 
@@ -106,6 +106,13 @@ impl Clone {
                 .await?;
         }
 
+        let cloned = txn
+            .read()
+            .log(&*channel.read(), 0)?
+            .map(|x| Ok(x?.1 .0.into()))
+            .collect::<Result<Vec<libpijul::Hash>, anyhow::Error>>()?;
+        super::signatures::check_signatures(&repo, cloned.iter())?;
+
         if self.partial_paths.is_empty() {
             libpijul::output::output_repository_no_pending(
                 &repo.working_copy,
@@ -117,6 +124,7 @@ impl Clone {
                 None,
                 1, // std::thread::available_parallelism()?.get(),
                 self.salt.unwrap_or(0),
+                super::config::conflict_style(&repo),
             )?;
         } else {
             for p in self.partial_paths.iter() {
@@ -130,6 +138,7 @@ impl Clone {
                     None,
                     1, // std::thread::available_parallelism()?.get(),
                     self.salt.unwrap_or(0),
+                    super::config::conflict_style(&repo),
                 )?;
             }
         }
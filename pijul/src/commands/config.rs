@@ -0,0 +1,50 @@
+/// The diff algorithm chosen on the command line, or else the one set
+/// in the configuration of `repo`.
+pub(crate) fn diff_algorithm(
+    repo: &pijul_repository::Repository,
+    patience: bool,
+    histogram: bool,
+) -> libpijul::Algorithm {
+    use pijul_config::DiffAlgorithm;
+    if patience {
+        libpijul::Algorithm::Patience
+    } else if histogram {
+        libpijul::Algorithm::Histogram
+    } else {
+        match repo.config.diff_algorithm {
+            Some(DiffAlgorithm::Myers) => libpijul::Algorithm::Myers,
+            Some(DiffAlgorithm::Patience) => libpijul::Algorithm::Patience,
+            Some(DiffAlgorithm::Histogram) => libpijul::Algorithm::Histogram,
+            None => libpijul::Algorithm::default(),
+        }
+    }
+}
+
+/// The style of the conflict markers set in the configuration of
+/// `repo`.
+pub(crate) fn conflict_style(
+    repo: &pijul_repository::Repository,
+) -> libpijul::vertex_buffer::ConflictStyle {
+    use libpijul::vertex_buffer::ConflictStyle;
+    match repo.config.conflict_style {
+        Some(pijul_config::ConflictStyle::Pijul) => ConflictStyle::Pijul,
+        Some(pijul_config::ConflictStyle::Git) => ConflictStyle::Git,
+        Some(pijul_config::ConflictStyle::Diff3) => ConflictStyle::Diff3,
+        None => ConflictStyle::default(),
+    }
+}
+
+/// The separators set in the configuration of `repo` for the files
+/// that shouldn't be compared line by line.
+pub(crate) fn diff_separators(
+    repo: &pijul_repository::Repository,
+) -> Result<libpijul::Separators, anyhow::Error> {
+    let mut separators = libpijul::Separators::new();
+    for s in repo.config.diff_separators.iter() {
+        separators.push(
+            regex::bytes::Regex::new(&s.files_regex()?)?,
+            regex::bytes::Regex::new(s.separator_regex())?,
+        )
+    }
+    Ok(separators)
+}
@@ -0,0 +1,317 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use clap::{Parser, ValueHint};
+use libpijul::changestore::ChangeStore;
+use libpijul::vertex_buffer::{END_MARKER, START_MARKER};
+use libpijul::{Base32, Conflict, TxnT};
+use serde_derive::Serialize;
+
+use pijul_repository::Repository;
+
+#[derive(Parser, Debug)]
+pub struct Conflicts {
+    /// Set the repository where this command should run. Defaults to the first ancestor of the current directory that contains a `.pijul` directory.
+    #[clap(long = "repository", value_hint = ValueHint::DirPath)]
+    repo_path: Option<PathBuf>,
+    /// List the conflicts of this channel instead of the current channel
+    #[clap(long = "channel")]
+    channel: Option<String>,
+    /// Output the conflicts in JSON format
+    #[clap(long = "json")]
+    json: bool,
+    /// Record the current contents of the conflicting files in the given paths as the resolution of their conflicts, then list the remaining ones. The files must not contain conflict markers anymore.
+    #[clap(long = "mark-resolved", requires = "paths")]
+    mark_resolved: bool,
+    /// Set the message of the change marking the conflicts as resolved
+    #[clap(short = 'm', long = "message", requires = "mark_resolved")]
+    message: Option<String>,
+    /// Identity to sign the change marking the conflicts as resolved with
+    #[clap(long = "identity", requires = "mark_resolved")]
+    identity: Option<String>,
+    /// Only list the conflicts in these paths (files or directories)
+    #[clap(value_hint = ValueHint::AnyPath)]
+    paths: Vec<PathBuf>,
+}
+
+impl Conflicts {
+    pub async fn run(mut self) -> Result<(), anyhow::Error> {
+        if self.mark_resolved {
+            self.mark_resolved().await?;
+        }
+        let has_repo_path = self.repo_path.is_some();
+        let repo = Repository::find_root(self.repo_path)?;
+        let txn = repo.pristine.arc_txn_begin()?;
+        let channel_name = if let Some(c) = self.channel {
+            c
+        } else {
+            txn.read()
+                .current_channel()
+                .unwrap_or(libpijul::DEFAULT_CHANNEL)
+                .to_string()
+        };
+        let channel = if let Some(channel) = txn.read().load_channel(&channel_name)? {
+            channel
+        } else {
+            bail!("No such channel: {:?}", channel_name)
+        };
+
+        let conflicts = channel_conflicts(&repo, &txn, &channel, &self.paths, has_repo_path)?;
+
+        let identities = pijul_identity::Complete::load_all().unwrap_or_default();
+        let mut entries = Vec::with_capacity(conflicts.len());
+        for c in conflicts.iter() {
+            let (path, line) = match c {
+                Conflict::Name { path, .. }
+                | Conflict::ZombieFile { path, .. }
+                | Conflict::MultipleNames { path, .. } => (path, None),
+                Conflict::Zombie { path, line, .. }
+                | Conflict::Cyclic { path, line, .. }
+                | Conflict::Order { path, line, .. } => (path, Some(*line)),
+            };
+            let mut changes = Vec::new();
+            for h in c.changes() {
+                let header = repo.changes.get_header(h)?;
+                changes.push(ConflictChange {
+                    hash: h.to_base32(),
+                    authors: header
+                        .authors
+                        .iter()
+                        .map(|a| super::identity::author_name(&identities, a))
+                        .collect(),
+                    message: header.message.lines().next().unwrap_or("").to_string(),
+                })
+            }
+            entries.push(ConflictEntry {
+                kind: match c {
+                    Conflict::Name { .. } => "name",
+                    Conflict::ZombieFile { .. } => "zombie_file",
+                    Conflict::MultipleNames { .. } => "multiple_names",
+                    Conflict::Zombie { .. } => "zombie",
+                    Conflict::Cyclic { .. } => "cyclic",
+                    Conflict::Order { .. } => "order",
+                },
+                description: describe_conflict(c),
+                path: path.clone(),
+                line,
+                changes,
+            })
+        }
+
+        let mut stdout = std::io::stdout();
+        if self.json {
+            serde_json::to_writer_pretty(&mut stdout, &entries)?;
+            writeln!(stdout)?;
+        } else {
+            for e in entries.iter() {
+                writeln!(stdout, "{}", e.description)?;
+                for c in e.changes.iter() {
+                    if c.authors.is_empty() {
+                        writeln!(stdout, "  {} {}", c.hash, c.message)?;
+                    } else {
+                        writeln!(
+                            stdout,
+                            "  {} {} ({})",
+                            c.hash,
+                            c.message,
+                            c.authors.join(", ")
+                        )?;
+                    }
+                }
+            }
+        }
+        if !entries.is_empty() {
+            bail!("{} conflict(s) remaining", entries.len())
+        }
+        Ok(())
+    }
+
+    /// Record the conflicting files in `self.paths`, after checking
+    /// that their conflict markers have been removed.
+    async fn mark_resolved(&mut self) -> Result<(), anyhow::Error> {
+        let repo = Repository::find_root(self.repo_path.clone())?;
+        let txn = repo.pristine.arc_txn_begin()?;
+        let channel_name = if let Some(ref c) = self.channel {
+            c.clone()
+        } else {
+            txn.read()
+                .current_channel()
+                .unwrap_or(libpijul::DEFAULT_CHANNEL)
+                .to_string()
+        };
+        let channel = if let Some(channel) = txn.read().load_channel(&channel_name)? {
+            channel
+        } else {
+            bail!("No such channel: {:?}", channel_name)
+        };
+        let conflicts =
+            channel_conflicts(&repo, &txn, &channel, &self.paths, self.repo_path.is_some())?;
+        let mut paths = Vec::new();
+        for c in conflicts.iter() {
+            let path = match c {
+                Conflict::Name { path, .. }
+                | Conflict::ZombieFile { path, .. }
+                | Conflict::MultipleNames { path, .. } => path,
+                Conflict::Zombie { path, .. }
+                | Conflict::Cyclic { path, .. }
+                | Conflict::Order { path, .. } => {
+                    let full_path = repo.path.join(path);
+                    if std::fs::read(&full_path).is_ok_and(|c| has_conflict_markers(&c)) {
+                        bail!(
+                            "{} still contains conflict markers, not marking it as resolved",
+                            path
+                        )
+                    }
+                    path
+                }
+            };
+            let path = repo.path.join(path);
+            if !paths.contains(&path) {
+                paths.push(path)
+            }
+        }
+        if paths.is_empty() {
+            writeln!(std::io::stderr(), "No conflicts to mark as resolved")?;
+            return Ok(());
+        }
+        // Recording needs its own transaction.
+        std::mem::drop(channel);
+        std::mem::drop(txn);
+        std::mem::drop(repo);
+
+        super::Record {
+            all: true,
+            interactive: false,
+            message: Some(
+                self.message
+                    .take()
+                    .unwrap_or_else(|| "Resolve conflicts".to_string()),
+            ),
+            description: None,
+            author: None,
+            channel: self.channel.clone(),
+            repo_path: self.repo_path.clone(),
+            timestamp: None,
+            ignore_missing: false,
+            working_copy: None,
+            amend: None,
+            prefixes: paths,
+            identity: self.identity.take(),
+            patience: false,
+            histogram: false,
+            detect_renames: None,
+        }
+        .run()
+        .await
+    }
+}
+
+/// Whether `contents` has lines starting with a conflict marker, in
+/// any conflict style.
+pub(crate) fn has_conflict_markers(contents: &[u8]) -> bool {
+    contents
+        .split(|c| *c == b'\n')
+        .any(|l| l.starts_with(START_MARKER.as_bytes()) || l.starts_with(END_MARKER.as_bytes()))
+}
+
+#[derive(Serialize)]
+struct ConflictEntry {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip)]
+    description: String,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    changes: Vec<ConflictChange>,
+}
+
+#[derive(Serialize)]
+struct ConflictChange {
+    hash: String,
+    authors: Vec<String>,
+    message: String,
+}
+
+/// A one-line description of a conflict, such as "Order conflict in
+/// "file" starting on line 3".
+pub(crate) fn describe_conflict(c: &Conflict) -> String {
+    match c {
+        Conflict::Name { ref path, .. } => format!("Name conflict on \"{}\"", path),
+        Conflict::ZombieFile { ref path, .. } => format!("Path deletion conflict \"{}\"", path),
+        Conflict::MultipleNames { ref path, .. } => {
+            format!("File has multiple names: \"{}\"", path)
+        }
+        Conflict::Zombie {
+            ref path, ref line, ..
+        } => format!(
+            "Deletion conflict in \"{}\" starting on line {}",
+            path, line
+        ),
+        Conflict::Cyclic {
+            ref path, ref line, ..
+        } => format!("Cycle conflict in \"{}\" starting on line {}", path, line),
+        Conflict::Order {
+            ref path, ref line, ..
+        } => format!("Order conflict in \"{}\" starting on line {}", path, line),
+    }
+}
+
+/// The conflicts of `channel` in `paths`, or in the whole repository
+/// if `paths` is empty, computed without touching the working copy.
+/// The paths are relative to the current directory, or to the root
+/// of the repository if `has_repo_path` is true.
+pub(crate) fn channel_conflicts(
+    repo: &pijul_repository::Repository,
+    txn: &libpijul::ArcTxn<libpijul::pristine::sanakirja::MutTxn<()>>,
+    channel: &libpijul::ChannelRef<libpijul::pristine::sanakirja::MutTxn<()>>,
+    paths: &[std::path::PathBuf],
+    has_repo_path: bool,
+) -> Result<Vec<Conflict>, anyhow::Error> {
+    use path_slash::PathExt;
+    if paths.is_empty() {
+        return Ok(txn.archive(&repo.changes, channel, &mut Discard)?);
+    }
+    let repo_path = canonical_path::CanonicalPathBuf::canonicalize(&repo.path)?;
+    let mut conflicts = Vec::new();
+    for p in paths.iter() {
+        let p = if has_repo_path {
+            repo_path.as_path().join(p)
+        } else {
+            std::env::current_dir()?.join(p)
+        };
+        let p = p.strip_prefix(&repo_path)?.to_slash_lossy();
+        let mut prefix = p.split('/').filter(|c| !c.is_empty() && *c != ".");
+        conflicts.extend(txn.archive_prefix(&repo.changes, channel, &mut prefix, &mut Discard)?);
+    }
+    conflicts.sort();
+    conflicts.dedup();
+    Ok(conflicts)
+}
+
+/// An archive that discards everything, used to compute the conflicts
+/// without touching the working copy.
+struct Discard;
+
+impl libpijul::output::Archive for Discard {
+    type File = std::io::Sink;
+    type Error = std::io::Error;
+    fn create_file(&mut self, _path: &str, _mtime: u64, _perm: u16) -> Self::File {
+        std::io::sink()
+    }
+    fn create_dir(&mut self, _path: &str, _mtime: u64, _perm: u16) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn close_file(&mut self, _f: Self::File) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn create_symlink(
+        &mut self,
+        _path: &str,
+        _mtime: u64,
+        _target: &str,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use clap::{Parser, ValueHint};
+use libpijul::changestore::ChangeStore;
+use libpijul::*;
+use serde_derive::Serialize;
+
+use pijul_repository::Repository;
+
+#[derive(Parser, Debug)]
+pub struct Deps {
+    /// Set the repository where this command should run. Defaults to the first ancestor of the current directory that contains a `.pijul` directory.
+    #[clap(long = "repository", value_hint = ValueHint::DirPath)]
+    repo_path: Option<PathBuf>,
+    /// Output the dependencies of this channel instead of the current channel
+    #[clap(long = "channel")]
+    channel: Option<String>,
+    /// Output format: a Graphviz graph, or a list of changes with their dependencies
+    #[clap(long = "format", value_enum, default_value = "dot")]
+    format: DepsFormat,
+    /// Only output the changes applied after this state
+    #[clap(long = "since", value_name = "STATE")]
+    since: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, clap::ValueEnum)]
+enum DepsFormat {
+    Dot,
+    Json,
+}
+
+#[derive(Serialize)]
+struct Node {
+    hash: String,
+    state: String,
+    message: String,
+    authors: Vec<String>,
+    timestamp: chrono::DateTime<chrono::offset::Utc>,
+    dependencies: Vec<String>,
+}
+
+impl Deps {
+    pub fn run(self) -> Result<(), anyhow::Error> {
+        let repo = Repository::find_root(self.repo_path)?;
+        let txn = repo.pristine.txn_begin()?;
+        let channel_name = if let Some(ref c) = self.channel {
+            c
+        } else {
+            txn.current_channel().unwrap_or(libpijul::DEFAULT_CHANNEL)
+        };
+        let channel = if let Some(channel) = txn.load_channel(channel_name)? {
+            channel
+        } else {
+            bail!("No such channel: {:?}", channel_name)
+        };
+        let channel = channel.read();
+        let from = if let Some(ref since) = self.since {
+            let state = if let Some(state) = Merkle::from_base32(since.as_bytes()) {
+                state
+            } else {
+                bail!("Invalid state: {:?}", since)
+            };
+            if let Some(n) = txn.channel_has_state(txn.states(&*channel), &state.into())? {
+                u64::from(n) + 1
+            } else {
+                bail!("State {} not found in channel {:?}", since, channel_name)
+            }
+        } else {
+            0
+        };
+
+        // Dependencies are only shown between the changes in the
+        // output.
+        let mut ids = Vec::new();
+        let mut shown = HashSet::new();
+        for l in txn.log(&*channel, from)? {
+            let (_, (h, m)) = l?;
+            let id = *txn.get_internal(h)?.unwrap();
+            shown.insert(id);
+            ids.push((id, Hash::from(h), Merkle::from(m)));
+        }
+
+        let identities = pijul_identity::Complete::load_all().unwrap_or_default();
+        let mut nodes = Vec::with_capacity(ids.len());
+        for (id, hash, state) in ids {
+            let header = repo.changes.get_header(&hash)?;
+            let mut dependencies = Vec::new();
+            for d in txn.iter_dep(&id)? {
+                let (id_, dep) = d?;
+                if *id_ > id {
+                    break;
+                } else if *id_ == id && shown.contains(dep) {
+                    let h: Hash = txn.get_external(dep)?.unwrap().into();
+                    dependencies.push(h.to_base32())
+                }
+            }
+            nodes.push(Node {
+                hash: hash.to_base32(),
+                state: state.to_base32(),
+                message: header.message.lines().next().unwrap_or("").to_string(),
+                authors: header
+                    .authors
+                    .iter()
+                    .map(|a| super::identity::author_name(&identities, a))
+                    .collect(),
+                timestamp: header.timestamp,
+                dependencies,
+            })
+        }
+
+        let mut stdout = std::io::stdout();
+        match self.format {
+            DepsFormat::Json => {
+                serde_json::to_writer_pretty(&mut stdout, &nodes)?;
+                writeln!(stdout)?;
+            }
+            DepsFormat::Dot => {
+                writeln!(stdout, "digraph changes {{")?;
+                writeln!(stdout, "  rankdir=BT;")?;
+                writeln!(stdout, "  node [shape=box];")?;
+                for n in nodes.iter() {
+                    let label = [&n.hash[..8], &n.message, &n.authors.join(", ")]
+                        .iter()
+                        .filter(|l| !l.is_empty())
+                        .copied()
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    writeln!(
+                        stdout,
+                        "  \"{}\" [label=\"{}\"];",
+                        n.hash,
+                        dot_escape(&label)
+                    )?;
+                }
+                for n in nodes.iter() {
+                    for d in n.dependencies.iter() {
+                        writeln!(stdout, "  \"{}\" -> \"{}\";", n.hash, d)?;
+                    }
+                }
+                writeln!(stdout, "}}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Escape a string for a quoted Graphviz label.
+fn dot_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
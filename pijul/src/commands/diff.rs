@@ -32,9 +32,23 @@ pub struct Diff {
     pub untracked: bool,
     /// Only diff those paths (files or directories). If missing, diff the entire repository.
     pub prefixes: Vec<PathBuf>,
-    /// Use Patience diff instead of the default Myers diff
-    #[clap(long = "patience")]
+    /// Use Patience diff instead of the configured `diff_algorithm`
+    /// (Myers diff by default)
+    #[clap(long = "patience", conflicts_with = "histogram")]
     pub patience: bool,
+    /// Use Histogram diff instead of the configured `diff_algorithm`
+    /// (Myers diff by default)
+    #[clap(long = "histogram")]
+    pub histogram: bool,
+    /// Detect files moved without `pijul mv`, by comparing the missing files with the new ones. The optional value is the minimal similarity of their contents, in percent.
+    #[clap(
+        long = "detect-renames",
+        value_name = "SIMILARITY",
+        num_args = 0..=1,
+        default_missing_value = "50",
+        value_parser = clap::value_parser!(u8).range(0..=100)
+    )]
+    pub detect_renames: Option<u8>,
 }
 
 impl Diff {
@@ -63,16 +77,25 @@ impl Diff {
             cur.as_str()
         };
         let channel = txn.write().open_or_create_channel(&channel)?;
+        if let Some(threshold) = self.detect_renames {
+            super::renames::detect_renames(
+                &repo,
+                &repo.working_copy,
+                &CanonicalPathBuf::canonicalize(&repo.path)?,
+                &txn,
+                &channel,
+                threshold,
+            )?;
+        }
 
+        let algorithm = super::config::diff_algorithm(&repo, self.patience, self.histogram);
         let mut state = libpijul::RecordBuilder::new();
+        state.separators = super::config::diff_separators(&repo)?;
+        state.conflict_style = super::config::conflict_style(&repo);
         if self.prefixes.is_empty() {
             state.record(
                 txn.clone(),
-                if self.patience {
-                    libpijul::Algorithm::Patience
-                } else {
-                    libpijul::Algorithm::default()
-                },
+                algorithm,
                 self.short,
                 &libpijul::DEFAULT_SEPARATOR,
                 channel.clone(),
@@ -85,11 +108,7 @@ impl Diff {
             self.fill_relative_prefixes()?;
             repo.working_copy.record_prefixes(
                 txn.clone(),
-                if self.patience {
-                    libpijul::Algorithm::Patience
-                } else {
-                    libpijul::Algorithm::default()
-                },
+                algorithm,
                 channel.clone(),
                 &repo.changes,
                 &mut state,
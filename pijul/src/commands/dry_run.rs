@@ -0,0 +1,106 @@
+/// Preview the effect of unrecording `unrecord` and then applying
+/// `apply` to `channel`, by doing it on a fork of `channel`, without
+/// touching the working copy. Prints the changes, the paths they
+/// touch and the conflicts they create. The fork only lives in `txn`,
+/// which must not be committed.
+pub(crate) fn dry_run(
+    repo: &pijul_repository::Repository,
+    txn: &libpijul::ArcTxn<libpijul::pristine::sanakirja::MutTxn<()>>,
+    channel: &libpijul::ChannelRef<libpijul::pristine::sanakirja::MutTxn<()>>,
+    unrecord: &[libpijul::Hash],
+    apply: &[libpijul::Hash],
+) -> Result<(), anyhow::Error> {
+    use libpijul::changestore::ChangeStore;
+    use libpijul::{Base32, DepsTxnT, GraphTxnT, MutTxnT, MutTxnTExt, TxnTExt};
+    use rand::Rng;
+    use std::io::Write;
+    let fork_name: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(20)
+        .map(char::from)
+        .collect();
+    let fork = txn.write().fork(channel, &fork_name)?;
+
+    // Unrecord the most recent changes first.
+    let mut unrecord = unrecord
+        .iter()
+        .map(|h| Ok((txn.read().has_change(&fork, h)?, *h)))
+        .collect::<Result<Vec<_>, anyhow::Error>>()?;
+    unrecord.sort_by_key(|u| std::cmp::Reverse(u.0));
+    for (n, h) in unrecord {
+        if n.is_some() {
+            txn.write().unrecord(&repo.changes, &fork, &h, 0)?;
+        }
+    }
+    let before: std::collections::BTreeSet<_> =
+        super::conflicts::channel_conflicts(repo, txn, &fork, &[], true)?
+            .iter()
+            .map(super::conflicts::describe_conflict)
+            .collect();
+    for h in apply {
+        txn.write()
+            .apply_change_rec(&repo.changes, &mut *fork.write(), h)?;
+    }
+    let after = super::conflicts::channel_conflicts(repo, txn, &fork, &[], true)?;
+
+    let mut paths = std::collections::BTreeSet::new();
+    {
+        let txn = txn.read();
+        for h in apply {
+            let int = if let Some(int) = txn.get_internal(&h.into())? {
+                *int
+            } else {
+                continue;
+            };
+            for inode in txn.iter_rev_touched(&int)? {
+                let (int_, inode) = inode?;
+                if *int_ < int {
+                    continue;
+                } else if *int_ > int {
+                    break;
+                }
+                // Deleted files are only found in the original channel.
+                for c in [&fork, channel] {
+                    if let Some((path, _)) =
+                        libpijul::fs::find_path(&repo.changes, &*txn, &*c.read(), false, *inode)?
+                    {
+                        if !path.is_empty() {
+                            paths.insert(path);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut stdout = std::io::stdout();
+    writeln!(stdout, "Changes:")?;
+    for h in apply {
+        let header = repo.changes.get_header(h)?;
+        writeln!(
+            stdout,
+            "  {} {}",
+            h.to_base32(),
+            header.message.lines().next().unwrap_or("")
+        )?;
+    }
+    writeln!(stdout, "\nPaths touched:")?;
+    for p in paths.iter() {
+        writeln!(stdout, "  {}", p)?;
+    }
+    let new_conflicts: Vec<_> = after
+        .iter()
+        .map(super::conflicts::describe_conflict)
+        .filter(|c| !before.contains(c))
+        .collect();
+    if new_conflicts.is_empty() {
+        writeln!(stdout, "\nNo new conflicts.")?;
+    } else {
+        writeln!(stdout, "\nNew conflicts:")?;
+        for c in new_conflicts.iter() {
+            writeln!(stdout, "  {}", c)?;
+        }
+    }
+    Ok(())
+}
@@ -41,7 +41,7 @@ impl Move {
         let repo_path = CanonicalPathBuf::canonicalize(&repo.path)?;
         for p in self.paths {
             debug!("p = {:?}", p);
-            let source = std::fs::canonicalize(&p.clone())?;
+            let source = canonicalize_no_follow(&p)?.into_path_buf();
             debug!("source = {:?}", source);
             let target = if is_dir {
                 to.join(source.file_name().unwrap())
@@ -55,7 +55,7 @@ impl Move {
                 target: &target,
             };
             std::fs::rename(r.source, r.target)?;
-            let target = std::fs::canonicalize(r.target)?;
+            let target = canonicalize_no_follow(r.target)?.into_path_buf();
             debug!("target = {:?}", target);
             {
                 let source = source.strip_prefix(&repo_path)?;
@@ -73,6 +73,27 @@ impl Move {
     }
 }
 
+/// Canonicalize `path`, but don't follow it if it is a symbolic link,
+/// since links are tracked as links rather than as their target.
+fn canonicalize_no_follow(path: &Path) -> Result<CanonicalPathBuf, std::io::Error> {
+    let name = match path.file_name() {
+        Some(name) if std::fs::symlink_metadata(path)?.file_type().is_symlink() => name,
+        _ => return CanonicalPathBuf::canonicalize(path),
+    };
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let mut canonical = CanonicalPathBuf::canonicalize(parent)?;
+    let file_name = if let Some(parent_name) = canonical.file_name() {
+        Path::new(parent_name).join(name)
+    } else {
+        PathBuf::from(name)
+    };
+    canonical.set_file_name(file_name);
+    Ok(canonical)
+}
+
 struct Rename<'a> {
     source: &'a Path,
     target: &'a Path,
@@ -132,9 +153,9 @@ impl Add {
         let mut stderr = std::io::stderr();
         for path in self.paths.iter() {
             info!("Adding {:?}", path);
-            let path = CanonicalPathBuf::canonicalize(&path)?;
+            let path = canonicalize_no_follow(path)?;
             debug!("{:?}", path);
-            let meta = std::fs::metadata(&path)?;
+            let meta = std::fs::symlink_metadata(&path)?;
             debug!("{:?}", meta);
             if !self.force
                 && !libpijul::working_copy::filesystem::filter_ignore(
@@ -169,7 +190,7 @@ impl Add {
                 let path_str = path.to_slash_lossy();
 
                 // If the user calls `pijul add .`, the entire path will be stripped, so we must account for this edge case
-                if path.is_dir() || path_str == "" {
+                if meta.is_dir() || path_str.is_empty() {
                     let display_str = if path.is_dir() {
                         path_str.clone()
                     } else {
@@ -215,8 +236,8 @@ impl Remove {
                 }
             }
 
-            let path = path.canonicalize()?;
-            let path = if let Ok(path) = path.strip_prefix(&repo_path.as_path()) {
+            let path = canonicalize_no_follow(path)?;
+            let path = if let Ok(path) = path.as_path().strip_prefix(repo_path.as_path()) {
                 path
             } else {
                 continue;
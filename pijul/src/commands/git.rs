@@ -10,7 +10,10 @@ use std::path::{Path, PathBuf};
 use pijul_repository::*;
 
 #[derive(Parser, Debug)]
+#[clap(args_conflicts_with_subcommands = true)]
 pub struct Git {
+    #[clap(subcommand)]
+    subcmd: Option<SubCommand>,
     /// Process this path instead of the current directory, creating a Pijul repository if necessary.
     #[clap(value_hint = ValueHint::DirPath)]
     pub git_path: Option<PathBuf>,
@@ -25,6 +28,59 @@ pub struct Git {
     check: usize,
 }
 
+#[derive(Parser, Debug)]
+pub enum SubCommand {
+    /// Exports a channel to a Git branch, one commit per change.
+    ///
+    /// Exporting again only adds the changes applied since the last
+    /// export, and the same states always give the same commits.
+    #[clap(name = "export")]
+    Export(Export),
+    /// Imports Git branches into channels, along with the tags of the
+    /// imported commits.
+    ///
+    /// The changes of the commits shared by several branches are
+    /// shared by their channels, and importing again only imports the
+    /// new commits.
+    #[clap(name = "import")]
+    Import(Import),
+}
+
+#[derive(Parser, Debug)]
+pub struct Import {
+    /// Set the repository where this command should run, creating a Pijul repository if necessary. Defaults to the first ancestor of the current directory that contains a `.pijul` directory.
+    #[clap(long = "repository", value_hint = ValueHint::DirPath)]
+    repo_path: Option<PathBuf>,
+    /// Import this Git branch (can be repeated, defaults to the current branch)
+    #[clap(long = "branch")]
+    branches: Vec<String>,
+    /// Import the branches into these channels, in the same order as the branches (defaults to the names of the branches)
+    #[clap(long = "to-channel")]
+    to_channels: Vec<String>,
+    /// Import from this Git repository instead of the root of the Pijul repository.
+    #[clap(value_hint = ValueHint::DirPath)]
+    git_path: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Export {
+    /// Set the repository where this command should run. Defaults to the first ancestor of the current directory that contains a `.pijul` directory.
+    #[clap(long = "repository", value_hint = ValueHint::DirPath)]
+    repo_path: Option<PathBuf>,
+    /// Export this channel instead of the current channel
+    #[clap(long = "channel")]
+    channel: Option<String>,
+    /// Name of the Git branch (defaults to the name of the channel)
+    #[clap(long = "branch")]
+    branch: Option<String>,
+    /// Overwrite the branch even if its tip wasn't exported by Pijul
+    #[clap(long = "force")]
+    force: bool,
+    /// Export to this Git repository instead of the root of the Pijul repository, creating a bare repository if necessary.
+    #[clap(value_hint = ValueHint::DirPath)]
+    git_path: Option<PathBuf>,
+}
+
 struct OpenRepo {
     repo: Repository,
     stats: Option<std::fs::File>,
@@ -42,6 +98,11 @@ use ::sanakirja::{Storable, UnsizedStorable};
 
 impl Git {
     pub fn run(self) -> Result<(), anyhow::Error> {
+        match self.subcmd {
+            Some(SubCommand::Export(export)) => return export.run(),
+            Some(SubCommand::Import(import)) => return import.run(),
+            None => {}
+        }
         let repo = if let Ok(repo) = Repository::find_root(self.pijul_path.clone()) {
             repo
         } else {
@@ -77,7 +138,7 @@ impl Git {
         path_git.push("git");
         std::fs::create_dir_all(&path_git)?;
         let mut env_git = ::sanakirja::Env::new(&path_git.join("db"), 1 << 15, 2)?;
-        let dag = Dag::dfs(&git, oid, &mut env_git)?;
+        let dag = Dag::dfs(&git, &[oid], &mut env_git)?;
 
         trace!(target: "dag", "{:?}", dag);
         debug!("Done");
@@ -107,6 +168,7 @@ impl Git {
                     None,
                     std::thread::available_parallelism()?.get(),
                     0,
+                    super::config::conflict_style(&repo.repo),
                 )?;
             }
         }
@@ -114,11 +176,279 @@ impl Git {
     }
 }
 
+impl Import {
+    pub fn run(self) -> Result<(), anyhow::Error> {
+        use libpijul::changestore::ChangeStore;
+        let repo = if let Ok(repo) = Repository::find_root(self.repo_path.clone()) {
+            repo
+        } else {
+            Repository::init(self.repo_path.clone(), None, None)?
+        };
+        let git = git2::Repository::open(self.git_path.as_ref().unwrap_or(&repo.path))?;
+
+        let mut branches = self.branches;
+        if branches.is_empty() {
+            let head = git.head()?;
+            if !head.is_branch() {
+                bail!("Git HEAD is not a branch, please use --branch")
+            }
+            branches.push(head.shorthand().unwrap().to_string())
+        }
+        if !self.to_channels.is_empty() && self.to_channels.len() != branches.len() {
+            bail!("There must be as many channels as branches")
+        }
+        let mut tips = Vec::with_capacity(branches.len());
+        for (i, b) in branches.iter().enumerate() {
+            let oid = git.revparse_single(b)?.peel_to_commit()?.id();
+            let channel = self.to_channels.get(i).unwrap_or(b);
+            tips.push((b.as_str(), oid, channel.as_str()))
+        }
+        let mut tags = Vec::new();
+        for name in git.tag_names(None)?.iter().flatten() {
+            let r = git.find_reference(&format!("refs/tags/{}", name))?;
+            if let Ok(commit) = r.peel_to_commit() {
+                tags.push((name.to_string(), commit.id()))
+            }
+        }
+
+        // The working copy is output again at the end of the import.
+        let previous_channel = {
+            let txn = repo.pristine.arc_txn_begin()?;
+            let current = txn.read().current_channel().ok().map(String::from);
+            let channel = if let Some(ref c) = current {
+                txn.read().load_channel(c)?
+            } else {
+                None
+            };
+            if let Some(channel) = channel {
+                if super::reset::has_unrecorded_changes(txn.clone(), channel, &repo)? {
+                    bail!("Cannot import, as there are unrecorded changes.")
+                }
+                current
+            } else {
+                None
+            }
+        };
+
+        let mut path_git = repo.path.join(libpijul::DOT_DIR);
+        path_git.push("git");
+        std::fs::create_dir_all(&path_git)?;
+        let mut env_git = ::sanakirja::Env::new(path_git.join("db"), 1 << 15, 2)?;
+        let oids: Vec<_> = tips.iter().map(|(_, oid, _)| *oid).collect();
+        let mut dag = Dag::dfs(&git, &oids, &mut env_git)?;
+        // Keep the channels of the branches and tags until they are
+        // copied to their final channels.
+        dag.keep.extend(oids.iter().cloned());
+        dag.keep.extend(tags.iter().map(|(_, oid)| *oid));
+        trace!(target: "dag", "{:?}", dag);
+        let mut repo = OpenRepo {
+            repo,
+            stats: None,
+            n: 0,
+            check: 0,
+            current_commit: None,
+        };
+        import(&git, &mut env_git, &mut repo, &dag)?;
+        let repo = repo.repo;
+
+        let txn = repo.pristine.arc_txn_begin()?;
+        {
+            let txn = txn.read();
+            for (branch, oid, channel) in tips.iter() {
+                let source = txn.load_channel(&oid.to_string())?.unwrap();
+                let target = if let Some(target) = txn.load_channel(channel)? {
+                    target
+                } else {
+                    continue;
+                };
+                for x in txn.log(&*target.read(), 0)? {
+                    let (_, (h, _)) = x?;
+                    let h = Hash::from(h);
+                    if txn.has_change(&source, &h)?.is_none()
+                        && !repo
+                            .changes
+                            .get_change(&h)?
+                            .changes
+                            .iter()
+                            .all(|h| matches!(h, libpijul::change::Hunk::AddRoot { .. }))
+                    {
+                        bail!(
+                            "Channel {:?} has changes that are not in branch {:?}, not importing it",
+                            channel,
+                            branch
+                        )
+                    }
+                }
+            }
+        }
+        let mut stdout = std::io::stdout();
+        for (branch, oid, channel) in tips.iter() {
+            let mut txn = txn.write();
+            let source = txn.load_channel(&oid.to_string())?.unwrap();
+            if let Some(target) = txn.load_channel(channel)? {
+                if txn.current_state(&*target.read())? == txn.current_state(&*source.read())? {
+                    continue;
+                }
+                // Apply the new changes to the existing channel, which
+                // keeps its id and tags.
+                let mut new = Vec::new();
+                for x in txn.log(&*source.read(), 0)? {
+                    let (_, (h, _)) = x?;
+                    let h = Hash::from(h);
+                    if txn.has_change(&target, &h)?.is_none() {
+                        new.push(h)
+                    }
+                }
+                for h in new.iter() {
+                    txn.apply_change_rec(&repo.changes, &mut *target.write(), h)?
+                }
+            } else {
+                txn.fork(&source, channel)?;
+            }
+            writeln!(
+                stdout,
+                "Imported branch {} into channel {}",
+                branch, channel
+            )?;
+        }
+
+        // Tags, taken on the channels of the tagged commits.
+        let imported = imported_states(&env_git)?;
+        for (name, oid) in tags.iter() {
+            let state = if let Some(state) = imported.get(oid) {
+                state
+            } else {
+                continue;
+            };
+            let mut tag_path = repo.changes_dir.clone();
+            libpijul::changestore::filesystem::push_tag_filename(&mut tag_path, state);
+            if !tag_path.exists() {
+                let oid_ = oid.to_string();
+                let has_channel = txn.read().load_channel(&oid_)?.is_some();
+                if !has_channel
+                    && restore_commit_channel(&repo.changes, &txn, &oid_, state)?.is_none()
+                {
+                    writeln!(
+                        std::io::stderr(),
+                        "Cannot import tag {}: commit {} is not in any channel",
+                        name,
+                        oid
+                    )?;
+                    continue;
+                }
+                let header = tag_header(&git, name, oid)?;
+                let mut temp_path = repo.changes_dir.clone();
+                std::fs::create_dir_all(&temp_path)?;
+                temp_path.push("tmp");
+                let mut w = std::fs::File::create(&temp_path)?;
+                libpijul::tag::from_channel(&*txn.read(), &oid_, &header, &mut w)?;
+                std::fs::create_dir_all(tag_path.parent().unwrap())?;
+                std::fs::rename(&temp_path, &tag_path)?;
+                txn.write().drop_channel(&oid_)?;
+                writeln!(stdout, "Imported tag {}: {}", name, state.to_base32())?;
+            }
+            for (_, _, channel) in tips.iter() {
+                let channel = txn.read().load_channel(channel)?.unwrap();
+                let mut txn = txn.write();
+                let n = txn.channel_has_state(txn.states(&*channel.read()), &state.into())?;
+                if let Some(n) = n {
+                    let n = u64::from(n);
+                    if !txn.is_tagged(&channel.read().tags, n)? {
+                        txn.put_tags(&mut channel.write().tags, n, state)?;
+                    }
+                }
+            }
+        }
+        for oid in dag.keep.iter() {
+            txn.write().drop_channel(&oid.to_string())?;
+        }
+
+        let current = previous_channel.as_deref().unwrap_or(tips[0].2);
+        txn.write().set_current_channel(current)?;
+        let channel = txn.read().load_channel(current)?.unwrap();
+        libpijul::output::output_repository_no_pending(
+            &repo.working_copy,
+            &repo.changes,
+            &txn,
+            &channel,
+            "",
+            true,
+            None,
+            std::thread::available_parallelism()?.get(),
+            0,
+            super::config::conflict_style(&repo),
+        )?;
+        txn.commit()?;
+        Ok(())
+    }
+}
+
+/// The states of the imported commits.
+fn imported_states(
+    env_git: &::sanakirja::Env,
+) -> Result<BTreeMap<git2::Oid, libpijul::Merkle>, anyhow::Error> {
+    let txn = ::sanakirja::Env::txn_begin(env_git)?;
+    let mut states = BTreeMap::new();
+    let db = txn.root(0);
+    if db != 0 {
+        let db: ::sanakirja::btree::UDb<Oid, libpijul::pristine::SerializedMerkle> =
+            unsafe { ::sanakirja::btree::UDb::from_page(db) };
+        for x in ::sanakirja::btree::iter(&txn, &db, None)? {
+            let (commit, merk) = x?;
+            states.insert(commit.0, merk.into());
+        }
+    }
+    Ok(states)
+}
+
+/// The header of the Pijul tag corresponding to a Git tag: the
+/// message is the name of the tag, and the description is the message
+/// of annotated tags.
+fn tag_header(
+    git: &git2::Repository,
+    name: &str,
+    oid: &git2::Oid,
+) -> Result<libpijul::change::ChangeHeader, anyhow::Error> {
+    let commit = git.find_commit(*oid)?;
+    let r = git.find_reference(&format!("refs/tags/{}", name))?;
+    let tag = r.peel_to_tag().ok();
+    let signature = tag
+        .as_ref()
+        .and_then(|t| t.tagger())
+        .unwrap_or_else(|| commit.author());
+    let mut author = BTreeMap::new();
+    author.insert(
+        "name".to_string(),
+        signature.name().unwrap_or("").to_string(),
+    );
+    author.insert(
+        "email".to_string(),
+        signature.email().unwrap_or("").to_string(),
+    );
+    Ok(libpijul::change::ChangeHeader {
+        message: name.to_string(),
+        authors: vec![libpijul::change::Author(author)],
+        description: tag
+            .as_ref()
+            .and_then(|t| t.message())
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty()),
+        timestamp: chrono::DateTime::from_naive_utc_and_offset(
+            chrono::NaiveDateTime::from_timestamp_opt(signature.when().seconds(), 0)
+                .expect("seconds and nanos are within bounds"),
+            chrono::Utc,
+        ),
+    })
+}
+
 #[derive(Debug)]
 struct Dag {
     children: BTreeMap<git2::Oid, Vec<git2::Oid>>,
     parents: BTreeMap<git2::Oid, Vec<git2::Oid>>,
     root: Vec<(git2::Oid, Option<libpijul::Merkle>)>,
+    /// Commits whose channels are kept after the import, even if all
+    /// their children have been imported.
+    keep: BTreeSet<git2::Oid>,
 }
 
 impl Dag {
@@ -127,17 +457,22 @@ impl Dag {
     /// its parents.
     fn dfs(
         git: &git2::Repository,
-        oid: git2::Oid,
+        oids: &[git2::Oid],
         env_git: &mut ::sanakirja::Env,
     ) -> Result<Self, anyhow::Error> {
-        let mut stack = vec![git.find_commit(oid)?];
+        let mut stack = Vec::new();
         let mut oids_set = BTreeSet::new();
+        for oid in oids {
+            if oids_set.insert(*oid) {
+                stack.push(git.find_commit(*oid)?)
+            }
+        }
         let mut dag = Dag {
             children: BTreeMap::new(),
             parents: BTreeMap::new(),
             root: Vec::new(),
+            keep: BTreeSet::new(),
         };
-        oids_set.insert(oid.clone());
         let mut txn_git = ::sanakirja::Env::mut_txn_begin(env_git)?;
         let db: ::sanakirja::btree::UDb<Oid, libpijul::pristine::SerializedMerkle> = unsafe {
             if let Some(db) = txn_git.root(0) {
@@ -193,7 +528,7 @@ impl Dag {
             for p in parents {
                 let rc = todo.refs.get_mut(p).unwrap();
                 *rc -= 1;
-                if *rc == 0 {
+                if *rc == 0 && !self.keep.contains(p) {
                     let p_name = format!("{}", p);
                     debug!("dropping channel {:?}", p_name);
                     let mut txn = txn.write();
@@ -278,7 +613,12 @@ fn import(
     for &(oid, merkle) in dag.root.iter() {
         if let Some(merkle) = merkle {
             let oid_ = format!("{}", oid);
-            let channel = if let Some(c) = txn.read().load_channel(&oid_)? {
+            let channel = txn.read().load_channel(&oid_)?;
+            let channel = if let Some(c) = channel {
+                c
+            } else if let Some(c) =
+                restore_commit_channel(&repo.repo.changes, &txn, &oid_, &merkle)?
+            {
                 c
             } else {
                 bail!("Channel not found: {:?}", oid);
@@ -307,7 +647,8 @@ fn import(
             }
         }
     }
-    std::mem::drop(txn);
+    // Commit the channels restored above.
+    txn.commit()?;
     todo.swap_next(Vec::new());
 
     while !todo.is_empty() {
@@ -384,6 +725,50 @@ fn save_state(
     Ok(())
 }
 
+/// Restore the channel of an imported commit, if its state is still
+/// in some channel, by forking that channel and unrecording the changes
+/// applied after the state.
+fn restore_commit_channel<T: TxnTExt + MutTxnTExt + 'static>(
+    changes: &libpijul::changestore::filesystem::FileSystem,
+    txn: &ArcTxn<T>,
+    name: &str,
+    state: &libpijul::Merkle,
+) -> Result<Option<ChannelRef<T>>, anyhow::Error> {
+    let mut source = None;
+    let txn_ = txn.read();
+    for c in txn_.channels("")? {
+        let n = txn_.channel_has_state(txn_.states(&*c.read()), &state.into())?;
+        if let Some(n) = n {
+            source = Some((c, u64::from(n)));
+            break;
+        }
+    }
+    std::mem::drop(txn_);
+    let (source, n) = if let Some(s) = source {
+        s
+    } else {
+        return Ok(None);
+    };
+    debug!(
+        "restoring {:?} from {:?}",
+        name,
+        txn.read().name(&*source.read())
+    );
+    let channel = txn.write().fork(&source, name)?;
+    let mut unrecord = Vec::new();
+    for x in txn.read().reverse_log(&*channel.read(), None)? {
+        let (m, (h, _)) = x?;
+        if m <= n {
+            break;
+        }
+        unrecord.push(libpijul::Hash::from(h))
+    }
+    for h in unrecord.iter() {
+        txn.write().unrecord(changes, &channel, h, 0)?;
+    }
+    Ok(Some(channel))
+}
+
 fn make_apply_plan<T: TxnTExt>(
     repo: &OpenRepo,
     txn: &ArcTxn<T>,
@@ -479,6 +864,7 @@ fn import_commit_parents<T: TxnTExt + MutTxnTExt + GraphIter + Send + Sync + 'st
             None,
             std::thread::available_parallelism()?.get(),
             0,
+            super::config::conflict_style(&repo.repo),
         )?;
         let t = output_time.elapsed();
         if repo.check > 0 && repo.n % repo.check == 0 {
@@ -642,6 +1028,8 @@ impl<'a> libpijul::working_copy::WorkingCopyRead for Commit<'a> {
         let is_dir = entry.kind() == Some(git2::ObjectType::Tree);
         if is_dir {
             Ok(InodeMetadata::new(0o100, true))
+        } else if entry.filemode() == 0o120000 {
+            Ok(InodeMetadata::SYMLINK)
         } else {
             let permissions = entry.filemode();
             debug!(
@@ -997,3 +1385,365 @@ impl Stats {
         Ok(())
     }
 }
+
+/// Root of `.pijul/git/db` mapping the exported states to their Git
+/// commits (the root 0 maps imported commits to states).
+const EXPORT_ROOT: usize = 1;
+
+impl Export {
+    pub fn run(self) -> Result<(), anyhow::Error> {
+        use libpijul::changestore::ChangeStore;
+        let repo = Repository::find_root(self.repo_path)?;
+        let git = if let Some(ref git_path) = self.git_path {
+            match git2::Repository::open(git_path) {
+                Ok(git) => git,
+                Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                    git2::Repository::init_bare(git_path)?
+                }
+                Err(e) => return Err(e.into()),
+            }
+        } else {
+            git2::Repository::open(&repo.path)?
+        };
+        let txn = repo.pristine.arc_txn_begin()?;
+        let channel_name = if let Some(c) = self.channel {
+            c
+        } else {
+            txn.read()
+                .current_channel()
+                .unwrap_or(libpijul::DEFAULT_CHANNEL)
+                .to_string()
+        };
+        let channel = if let Some(channel) = txn.read().load_channel(&channel_name)? {
+            channel
+        } else {
+            bail!("No such channel: {:?}", channel_name)
+        };
+        let branch = format!(
+            "refs/heads/{}",
+            self.branch.as_ref().unwrap_or(&channel_name)
+        );
+
+        let mut log = Vec::new();
+        for l in txn.read().log(&*channel.read(), 0)? {
+            let (_, (h, m)) = l?;
+            log.push((Hash::from(h), Merkle::from(m)))
+        }
+
+        let mut path_git = repo.path.join(libpijul::DOT_DIR);
+        path_git.push("git");
+        std::fs::create_dir_all(&path_git)?;
+        let env_git = ::sanakirja::Env::new(path_git.join("db"), 1 << 15, 2)?;
+        let mut txn_git = ::sanakirja::Env::mut_txn_begin(&env_git)?;
+        let mut db: ::sanakirja::btree::UDb<libpijul::pristine::SerializedMerkle, Oid> = unsafe {
+            if let Some(db) = txn_git.root(EXPORT_ROOT) {
+                ::sanakirja::btree::UDb::from_page(db)
+            } else {
+                ::sanakirja::btree::create_db_(&mut txn_git)?
+            }
+        };
+
+        // Commits added to the branch outside of Pijul would be lost.
+        if !self.force {
+            if let Some(tip) = git.find_reference(&branch).ok().and_then(|r| r.target()) {
+                let mut exported = false;
+                for x in ::sanakirja::btree::iter(&txn_git, &db, None)? {
+                    if x?.1 .0 == tip {
+                        exported = true;
+                        break;
+                    }
+                }
+                if !exported {
+                    bail!(
+                        "The tip of {} ({}) wasn't exported from Pijul, use --force to overwrite it",
+                        branch,
+                        tip
+                    )
+                }
+            }
+        }
+
+        // Find the last state already exported to this Git repository.
+        let mut start = 0;
+        let mut parent = None;
+        for (i, (_, m)) in log.iter().enumerate().rev() {
+            let m = m.into();
+            if let Some((k, oid)) = ::sanakirja::btree::get(&txn_git, &db, &m, None)? {
+                if *k == m && git.find_commit(oid.0).is_ok() {
+                    start = i + 1;
+                    parent = Some(oid.0);
+                    break;
+                }
+            }
+        }
+        debug!("start = {:?}, parent = {:?}", start, parent);
+
+        // Rebuild the state of the parent on a scratch channel, which
+        // only lives in this transaction.
+        let scratch_name = format!("git-export-{}", rand::random::<u64>());
+        let scratch = if start > 0 {
+            let scratch = txn.write().fork(&channel, &scratch_name)?;
+            for (h, _) in log[start..].iter().rev() {
+                txn.write().unrecord(&repo.changes, &scratch, h, 0)?;
+            }
+            scratch
+        } else {
+            txn.write().open_or_create_channel(&scratch_name)?
+        };
+        let mut files = BTreeMap::new();
+        if let Some(parent) = parent {
+            git.find_commit(parent)?.tree()?.walk(
+                git2::TreeWalkMode::PreOrder,
+                |root, entry| {
+                    if entry.kind() != Some(git2::ObjectType::Tree) {
+                        let path = format!("{}{}", root, entry.name().unwrap_or(""));
+                        files.insert(path, (entry.id(), entry.filemode()));
+                    }
+                    git2::TreeWalkResult::Ok
+                },
+            )?;
+        }
+
+        let identities = pijul_identity::Complete::load_all().unwrap_or_default();
+        for (h, m) in log[start..].iter() {
+            let id = *txn.read().get_internal(&h.into())?.unwrap();
+            let mut touched = Vec::new();
+            for x in txn.read().iter_rev_touched_files(&id, None)? {
+                let (id_, pos) = x?;
+                if *id_ > id {
+                    break;
+                }
+                touched.push(*pos)
+            }
+            let mut old = touched_paths(&repo, &txn, &scratch, &touched)?;
+            txn.write()
+                .apply_change(&repo.changes, &mut *scratch.write(), h)?;
+            let new = touched_paths(&repo, &txn, &scratch, &touched)?;
+            debug!("{:?}: old = {:?}, new = {:?}", h, old, new);
+
+            // Only output the paths touched by the change, unless the
+            // root is touched.
+            let new = if old.contains("") || new.contains("") {
+                files.clear();
+                old.clear();
+                vec![String::new()]
+            } else {
+                new.into_iter().collect()
+            };
+            for p in old.iter().chain(new.iter()) {
+                files.retain(|f: &String, _| {
+                    !(f == p || (f.starts_with(p.as_str()) && f.as_bytes()[p.len()] == b'/'))
+                });
+            }
+            let mut tree = GitTree {
+                git: &git,
+                files: &mut files,
+            };
+            for p in new.iter() {
+                let mut prefix = p.split('/').filter(|c| !c.is_empty());
+                txn.archive_prefix(&repo.changes, &scratch, &mut prefix, &mut tree)?;
+            }
+            let tree = git.find_tree(write_tree(&git, &files)?)?;
+
+            let header = repo.changes.get_header(h)?;
+            let signature = git_signature(&identities, &header)?;
+            let message = if let Some(ref d) = header.description {
+                format!("{}\n\n{}", header.message, d)
+            } else {
+                header.message.clone()
+            };
+            let parents = parent.map(|p| git.find_commit(p)).transpose()?;
+            let oid = git.commit(
+                None,
+                &signature,
+                &signature,
+                &message,
+                &tree,
+                &parents.iter().collect::<Vec<_>>(),
+            )?;
+            ::sanakirja::btree::put(&mut txn_git, &mut db, &m.into(), &Oid(oid))?;
+            parent = Some(oid)
+        }
+        txn_git.set_root(EXPORT_ROOT, db.db.into());
+        ::sanakirja::Commit::commit(txn_git)?;
+
+        let mut stdout = std::io::stdout();
+        if let Some(oid) = parent {
+            git.reference(&branch, oid, true, "pijul git export")?;
+            if start == log.len() {
+                writeln!(stdout, "{} is up to date", branch)?;
+            } else {
+                writeln!(
+                    stdout,
+                    "Exported {} change(s) to {} ({})",
+                    log.len() - start,
+                    branch,
+                    oid
+                )?;
+            }
+        } else {
+            writeln!(stdout, "Nothing to export")?;
+        }
+        Ok(())
+    }
+}
+
+/// The current paths of `touched` in `channel`.
+fn touched_paths<T: ChannelTxnT + 'static>(
+    repo: &Repository,
+    txn: &ArcTxn<T>,
+    channel: &ChannelRef<T>,
+    touched: &[Position<ChangeId>],
+) -> Result<BTreeSet<String>, anyhow::Error> {
+    let txn = txn.read();
+    let channel = channel.read();
+    let mut paths = BTreeSet::new();
+    for pos in touched {
+        if let Some((path, _)) =
+            libpijul::fs::find_path(&repo.changes, &*txn, &*channel, false, *pos)?
+        {
+            paths.insert(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// The Git author of a change, looking up keys in the local
+/// identities. Git commits are deterministic, so that exporting the
+/// same channel twice yields the same commits.
+fn git_signature(
+    identities: &[pijul_identity::Complete],
+    header: &libpijul::change::ChangeHeader,
+) -> Result<git2::Signature<'static>, git2::Error> {
+    let (name, email) = if let Some(author) = header.authors.first() {
+        if let Some(key) = author.0.get("key") {
+            if let Some(id) = identities.iter().find(|id| &id.public_key.key == key) {
+                let author = &id.config.author;
+                let name = if author.display_name.is_empty() {
+                    &author.username
+                } else {
+                    &author.display_name
+                };
+                (name.clone(), author.email.clone())
+            } else {
+                (key.clone(), String::new())
+            }
+        } else {
+            (
+                author.0.get("name").cloned().unwrap_or_default(),
+                author.0.get("email").cloned().unwrap_or_default(),
+            )
+        }
+    } else {
+        (String::new(), String::new())
+    };
+    // Git doesn't accept empty names or emails.
+    let name = if name.is_empty() { "Unknown" } else { &name };
+    let email = if email.is_empty() { "unknown" } else { &email };
+    git2::Signature::new(
+        name,
+        email,
+        &git2::Time::new(header.timestamp.timestamp(), 0),
+    )
+}
+
+/// An archive writing the files as Git blobs, and collecting them
+/// with their modes.
+struct GitTree<'a> {
+    git: &'a git2::Repository,
+    files: &'a mut BTreeMap<String, (git2::Oid, i32)>,
+}
+
+struct GitFile {
+    path: String,
+    perm: u16,
+    buf: Vec<u8>,
+}
+
+impl std::io::Write for GitFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buf.write(buf)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> libpijul::output::Archive for GitTree<'a> {
+    type File = GitFile;
+    type Error = git2::Error;
+    fn create_file(&mut self, path: &str, _mtime: u64, perm: u16) -> Self::File {
+        GitFile {
+            path: path.to_string(),
+            perm,
+            buf: Vec::new(),
+        }
+    }
+    fn create_dir(&mut self, _path: &str, _mtime: u64, _perm: u16) -> Result<(), Self::Error> {
+        // Git doesn't store empty directories.
+        Ok(())
+    }
+    fn close_file(&mut self, f: Self::File) -> Result<(), Self::Error> {
+        let mode = if f.perm & 0o100 != 0 {
+            git2::FileMode::BlobExecutable
+        } else {
+            git2::FileMode::Blob
+        };
+        let oid = self.git.blob(&f.buf)?;
+        self.files.insert(f.path, (oid, mode.into()));
+        Ok(())
+    }
+    fn create_symlink(&mut self, path: &str, _mtime: u64, target: &str) -> Result<(), Self::Error> {
+        let oid = self.git.blob(target.as_bytes())?;
+        self.files
+            .insert(path.to_string(), (oid, git2::FileMode::Link.into()));
+        Ok(())
+    }
+}
+
+/// Write the trees of `files`, and return the root tree.
+fn write_tree(
+    git: &git2::Repository,
+    files: &BTreeMap<String, (git2::Oid, i32)>,
+) -> Result<git2::Oid, git2::Error> {
+    let entries: Vec<_> = files
+        .iter()
+        .map(|(p, &(o, m))| (p.as_str(), o, m))
+        .collect();
+    write_subtree(git, &entries)
+}
+
+/// Write the tree of `entries`, which are sorted by path, and
+/// relative to the tree.
+fn write_subtree(
+    git: &git2::Repository,
+    entries: &[(&str, git2::Oid, i32)],
+) -> Result<git2::Oid, git2::Error> {
+    let mut builder = git.treebuilder(None)?;
+    let mut i = 0;
+    while i < entries.len() {
+        let (path, oid, mode) = entries[i];
+        if let Some((dir, _)) = path.split_once('/') {
+            // All the paths starting with `dir/` are contiguous.
+            let mut sub = Vec::new();
+            while i < entries.len() {
+                if let Some(rest) = entries[i]
+                    .0
+                    .strip_prefix(dir)
+                    .and_then(|r| r.strip_prefix('/'))
+                {
+                    sub.push((rest, entries[i].1, entries[i].2));
+                    i += 1
+                } else {
+                    break;
+                }
+            }
+            let sub = write_subtree(git, &sub)?;
+            builder.insert(dir, sub, git2::FileMode::Tree.into())?;
+        } else {
+            builder.insert(path, oid, mode)?;
+            i += 1
+        }
+    }
+    builder.write()
+}
@@ -0,0 +1,22 @@
+/// The current state of `channel`, in base32, as passed to hooks.
+pub(crate) fn hook_state<T: libpijul::ChannelTxnT>(
+    txn: &T,
+    channel: &T::Channel,
+) -> Result<Option<String>, anyhow::Error> {
+    use libpijul::Base32;
+    Ok(Some(
+        libpijul::pristine::current_state(txn, channel)?.to_base32(),
+    ))
+}
+
+/// The hashes of the changes (but not the tags) in `cs`, in base32,
+/// as passed to hooks.
+pub(crate) fn hook_changes(cs: &[pijul_remote::CS]) -> Vec<String> {
+    use libpijul::Base32;
+    cs.iter()
+        .filter_map(|c| match c {
+            pijul_remote::CS::Change(h) => Some(h.to_base32()),
+            pijul_remote::CS::State(_) => None,
+        })
+        .collect()
+}
@@ -463,3 +463,27 @@ impl IdentityCommand {
         Ok(())
     }
 }
+
+/// The name of an author, looking up keys in the local identities.
+pub(crate) fn author_name(
+    identities: &[pijul_identity::Complete],
+    author: &libpijul::change::Author,
+) -> String {
+    if let Some(key) = author.0.get("key") {
+        if let Some(id) = identities.iter().find(|id| &id.public_key.key == key) {
+            if !id.config.author.display_name.is_empty() {
+                return id.config.author.display_name.clone();
+            }
+            return id.config.author.username.clone();
+        }
+        key.clone()
+    } else if let Some(email) = author.0.get("email") {
+        format!(
+            "{} <{}>",
+            author.0.get("name").map(|s| s.as_str()).unwrap_or(""),
+            email
+        )
+    } else {
+        author.0.get("name").cloned().unwrap_or_default()
+    }
+}
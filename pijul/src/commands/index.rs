@@ -0,0 +1,40 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::{Parser, ValueHint};
+
+use pijul_repository::Repository;
+
+#[derive(Parser, Debug)]
+pub struct Index {
+    /// Set the repository where this command should run. Defaults to the first ancestor of the current directory that contains a `.pijul` directory.
+    #[clap(long = "repository", value_hint = ValueHint::DirPath)]
+    repo_path: Option<PathBuf>,
+    #[clap(subcommand)]
+    subcmd: SubCommand,
+}
+
+#[derive(Parser, Debug)]
+pub enum SubCommand {
+    /// Build the index from the change files, replacing the existing
+    /// index if any.
+    #[clap(name = "build")]
+    Build,
+    /// Delete the index.
+    #[clap(name = "remove")]
+    Remove,
+}
+
+impl Index {
+    pub fn run(self) -> Result<(), anyhow::Error> {
+        let mut repo = Repository::find_root(self.repo_path)?;
+        match self.subcmd {
+            SubCommand::Build => {
+                let n = repo.changes.build_index()?;
+                writeln!(std::io::stdout(), "Indexed {} changes", n)?;
+            }
+            SubCommand::Remove => repo.changes.remove_index()?,
+        }
+        Ok(())
+    }
+}
@@ -8,7 +8,7 @@ use anyhow::bail;
 use clap::{Parser, ValueHint};
 use libpijul::changestore::*;
 use libpijul::pristine::{
-    sanakirja::Txn, ChannelRef, DepsTxnT, GraphTxnT, TreeErr, TreeTxnT, TxnErr,
+    sanakirja::Txn, ChannelRef, ChannelTxnT, DepsTxnT, GraphTxnT, TreeErr, TreeTxnT, TxnErr,
 };
 use libpijul::{Base32, TxnT, TxnTExt};
 use log::*;
@@ -38,6 +38,9 @@ pub struct Log {
     /// Include files changed in the output
     #[clap(long = "files")]
     files: bool,
+    /// Check the signature of each change against the known identities
+    #[clap(long = "verify", conflicts_with = "hash_only")]
+    verify: bool,
     /// Start after this many changes
     #[clap(long = "offset")]
     offset: Option<usize>,
@@ -46,6 +49,9 @@ pub struct Log {
     limit: Option<usize>,
     #[clap(long = "output-format", value_enum)]
     output_format: Option<OutputFormat>,
+    /// Draw the dependencies between changes as an ASCII graph next to the log
+    #[clap(long = "graph", conflicts_with_all = ["output_format", "filters"])]
+    graph: bool,
     /// Filter log output, showing only log entries that touched the specified
     /// files. Accepted as a list of paths relative to your current directory.
     /// Currently, filters can only be applied when logging the channel that's
@@ -198,8 +204,16 @@ enum LogEntry {
         description: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         paths: Option<Vec<String>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        signature: Option<pijul_identity::ChangeSignature>,
     },
     Hash(libpijul::Hash),
+    /// An entry drawn next to its rows of the dependency graph.
+    #[serde(skip)]
+    Graph {
+        rows: GraphRows,
+        entry: Box<LogEntry>,
+    },
 }
 
 /// The standard pretty-print
@@ -214,6 +228,7 @@ impl std::fmt::Display for LogEntry {
                 message,
                 description,
                 paths,
+                signature,
             } => {
                 if let Some(ref h) = hash {
                     writeln!(f, "Change {}", h)?;
@@ -233,6 +248,13 @@ impl std::fmt::Display for LogEntry {
                     writeln!(f)?;
                 }
 
+                if let Some(ref signature) = signature {
+                    writeln!(
+                        f,
+                        "Signature: {}",
+                        super::signatures::SignatureDisplay(signature)
+                    )?;
+                }
                 if let Some(ref timestamp) = timestamp {
                     writeln!(f, "Date: {}", timestamp.to_rfc2822())?;
                 }
@@ -256,11 +278,137 @@ impl std::fmt::Display for LogEntry {
             LogEntry::Hash(h) => {
                 writeln!(f, "{}", h.to_base32())?;
             }
+            LogEntry::Graph { rows, entry } => {
+                let entry = entry.to_string();
+                let mut lines = entry.lines();
+                if let Some(ref merge) = rows.merge {
+                    writeln!(f, "{}", merge.trim_end())?;
+                }
+                writeln!(f, "{}{}", rows.node, lines.next().unwrap_or(""))?;
+                let mut fork = rows.fork.as_ref();
+                for l in lines {
+                    let row = fork.take().unwrap_or(&rows.tail);
+                    writeln!(f, "{}", format!("{}{}", row, l).trim_end())?;
+                }
+                if let Some(fork) = fork {
+                    writeln!(f, "{}", fork.trim_end())?;
+                }
+            }
         }
         Ok(())
     }
 }
 
+/// The rows of the dependency graph drawn for one change.
+struct GraphRows {
+    /// Columns of several dependents joining before the change.
+    merge: Option<String>,
+    /// The row of the change itself.
+    node: String,
+    /// New columns started by the dependencies of the change.
+    fork: Option<String>,
+    /// The row drawn next to the other lines of the entry.
+    tail: String,
+}
+
+/// The columns of an ASCII graph of the dependencies between
+/// changes, drawn from the most recent change. Each column is waiting
+/// for a dependency of a change that was already drawn.
+#[derive(Default)]
+struct Graph {
+    columns: Vec<Option<libpijul::ChangeId>>,
+}
+
+impl Graph {
+    /// Draw change `id`, whose dependencies in the channel are `deps`,
+    /// most recent first.
+    fn next(&mut self, id: libpijul::ChangeId, deps: &[libpijul::ChangeId]) -> GraphRows {
+        let joining: Vec<usize> = (0..self.columns.len())
+            .filter(|&i| self.columns[i] == Some(id))
+            .collect();
+        let col = if let Some(&col) = joining.first() {
+            col
+        } else if let Some(col) = self.columns.iter().position(|c| c.is_none()) {
+            col
+        } else {
+            self.columns.push(None);
+            self.columns.len() - 1
+        };
+
+        let merge = if joining.len() > 1 {
+            let row = self.row(|i| {
+                if i != col && joining.contains(&i) {
+                    Some('/')
+                } else {
+                    None
+                }
+            });
+            for &i in &joining[1..] {
+                self.columns[i] = None
+            }
+            Some(row)
+        } else {
+            None
+        };
+        self.columns[col] = Some(id);
+        let node = self.row(|i| if i == col { Some('*') } else { None });
+
+        self.columns[col] = deps.first().cloned();
+        let mut forked = Vec::new();
+        for dep in deps.iter().skip(1) {
+            let i = if let Some(i) = self.columns.iter().position(|c| c.is_none()) {
+                i
+            } else {
+                self.columns.push(None);
+                self.columns.len() - 1
+            };
+            self.columns[i] = Some(*dep);
+            forked.push(i)
+        }
+        let fork = if forked.is_empty() {
+            None
+        } else {
+            Some(self.row(|i| {
+                if forked.contains(&i) {
+                    Some('\\')
+                } else {
+                    None
+                }
+            }))
+        };
+        while let Some(None) = self.columns.last() {
+            self.columns.pop();
+        }
+        let tail = self.row(|_| None);
+
+        // Pad all the rows to the same width, so that the entry
+        // stays aligned.
+        let width = [&merge, &Some(node.clone()), &fork, &Some(tail.clone())]
+            .iter()
+            .filter_map(|r| r.as_ref().map(|r| r.len()))
+            .max()
+            .unwrap_or(0);
+        let pad = |r: String| format!("{:width$}", r, width = width);
+        GraphRows {
+            merge: merge.map(pad),
+            node: pad(node),
+            fork: fork.map(pad),
+            tail: pad(tail),
+        }
+    }
+
+    /// Draw a row, with `f` overriding the character of some columns,
+    /// and `|` for the other occupied columns.
+    fn row<F: Fn(usize) -> Option<char>>(&self, f: F) -> String {
+        let mut row = String::new();
+        for (i, c) in self.columns.iter().enumerate() {
+            row.push(f(i).unwrap_or(if c.is_some() { '|' } else { ' ' }));
+            row.push(' ');
+        }
+        row
+    }
+}
+
 /// Contains state needed to produce the sequence of [`LogEntry`] items
 /// that are to be logged. The implementation of `TryFrom<Log>` provides
 /// a fallible way of creating one of these from the CLI's [`Log`] structure.
@@ -330,10 +478,23 @@ impl LogIterator {
 
         let mut id_path = self.id_path.clone();
 
+        let mut verifier = if self.cmd.verify {
+            Some(pijul_identity::Verifier::new(&self.repo.path))
+        } else {
+            None
+        };
+
         let inodes = get_inodes(&self.txn, &self.repo.path, &self.cmd.filters)?;
         let mut offset = self.offset;
         let mut limit = self.limit;
 
+        let mut graph = if self.cmd.graph {
+            Some(Graph::default())
+        } else {
+            None
+        };
+        let channel = self.channel_ref.read();
+
         let mut reverse_log = self
             .txn
             .reverse_log(&*self.channel_ref.read(), None)?
@@ -349,6 +510,11 @@ impl LogIterator {
         for pr in reverse_log {
             let (_, (h, mrk)) = pr?;
             let cid = self.txn.get_internal(h)?.unwrap();
+            let rows = if let Some(ref mut graph) = graph {
+                Some(graph.next(*cid, &self.dependencies(&channel, cid)?))
+            } else {
+                None
+            };
             let mut is_in_filters = inodes.is_empty();
             for (_, position) in inodes.iter() {
                 if let Some(position) = position {
@@ -362,8 +528,21 @@ impl LogIterator {
                 if offset == 0 && limit > 0 {
                     // If there were no path filters applied, OR is this was one of the hashes
                     // marked by the file filters that were applied
-                    let entry =
-                        self.mk_log_entry(&mut authors, &mut id_path, h.into(), Some(mrk.into()))?;
+                    let entry = self.mk_log_entry(
+                        &mut authors,
+                        &mut id_path,
+                        verifier.as_mut(),
+                        h.into(),
+                        Some(mrk.into()),
+                    )?;
+                    let entry = if let Some(rows) = rows {
+                        LogEntry::Graph {
+                            rows,
+                            entry: Box::new(entry),
+                        }
+                    } else {
+                        entry
+                    };
                     f(entry).map_err(Error::E)?;
                     limit -= 1
                 } else if limit > 0 {
@@ -377,6 +556,30 @@ impl LogIterator {
         Ok(())
     }
 
+    /// The direct dependencies of `cid` in the channel, most recent
+    /// first.
+    fn dependencies(
+        &self,
+        channel: &libpijul::pristine::sanakirja::Channel,
+        cid: &libpijul::ChangeId,
+    ) -> Result<Vec<libpijul::ChangeId>, TxnErr<libpijul::pristine::sanakirja::SanakirjaError>>
+    {
+        let mut deps = Vec::new();
+        for d in self.txn.iter_dep(cid)? {
+            let (id, dep) = d?;
+            if id > cid {
+                break;
+            } else if id < cid {
+                continue;
+            }
+            if let Some(n) = self.txn.get_changeset(self.txn.changes(channel), dep)? {
+                deps.push((u64::from(*n), *dep))
+            }
+        }
+        deps.sort_by_key(|d| std::cmp::Reverse(d.0));
+        Ok(deps.into_iter().map(|(_, d)| d).collect())
+    }
+
     /// Create a [`LogEntry`] for a given hash.
     ///
     /// Most of this is just getting the right key information from either the cache
@@ -385,6 +588,7 @@ impl LogIterator {
         &self,
         author_kvs: &'x mut HashMap<String, String>,
         id_path: &mut PathBuf,
+        verifier: Option<&mut pijul_identity::Verifier>,
         h: libpijul::Hash,
         m: Option<libpijul::Merkle>,
     ) -> Result<LogEntry, Error<E>> {
@@ -405,6 +609,17 @@ impl LogIterator {
             None
         };
 
+        let signature = if let Some(verifier) = verifier {
+            let change = self.repo.changes.get_change(&h)?;
+            if super::signatures::is_root_change(&change) {
+                None
+            } else {
+                Some(verifier.verify(&change, &h))
+            }
+        } else {
+            None
+        };
+
         let header = self.repo.changes.get_header(&h.into())?;
         let authors = header
             .authors
@@ -484,6 +699,7 @@ impl LogIterator {
                 None
             },
             paths,
+            signature,
         })
     }
 }
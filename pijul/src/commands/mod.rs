@@ -24,9 +24,15 @@ pub use change::Change;
 mod dependents;
 pub use dependents::Dependents;
 
+mod deps;
+pub use deps::Deps;
+
 mod protocol;
 pub use protocol::Protocol;
 
+mod serve;
+pub use serve::Serve;
+
 #[cfg(feature = "git")]
 mod git;
 #[cfg(feature = "git")]
@@ -44,12 +50,33 @@ pub use fork::*;
 mod unrecord;
 pub use unrecord::*;
 
+mod revert;
+pub use revert::*;
+
+mod conflicts;
+pub use conflicts::*;
+
+mod resolve;
+pub use resolve::*;
+
+mod stash;
+pub use stash::Stash;
+
+mod bisect;
+pub use bisect::Bisect;
+
+mod index;
+pub use index::Index;
+
 mod file_operations;
 pub use file_operations::*;
 
 mod apply;
 pub use apply::*;
 
+mod cherry_pick;
+pub use cherry_pick::*;
+
 mod archive;
 pub use archive::*;
 
@@ -71,6 +98,13 @@ pub use client::*;
 mod completions;
 pub use completions::*;
 
+mod config;
+mod dry_run;
+mod hooks;
+mod protected;
+mod renames;
+mod signatures;
+
 /// Record the pending change (i.e. any unrecorded modifications in
 /// the working copy), returning its hash.
 fn pending<T: libpijul::MutTxnTExt + libpijul::TxnT + Send + Sync + 'static>(
@@ -81,6 +115,8 @@ fn pending<T: libpijul::MutTxnTExt + libpijul::TxnT + Send + Sync + 'static>(
     use libpijul::changestore::ChangeStore;
 
     let mut builder = libpijul::record::Builder::new();
+    builder.separators = config::diff_separators(repo)?;
+    builder.conflict_style = config::conflict_style(repo);
     builder.record(
         txn.clone(),
         libpijul::Algorithm::default(),
@@ -332,36 +368,7 @@ fn print_conflicts(conflicts: &[Conflict]) -> Result<(), std::io::Error> {
     writeln!(w, "\nThere were conflicts:\n")?;
     w.set_color(ColorSpec::new().set_fg(None))?;
     for c in conflicts.iter() {
-        match c {
-            Conflict::Name { ref path, .. } => writeln!(w, "  - Name conflict on \"{}\"", path)?,
-            Conflict::ZombieFile { ref path, .. } => {
-                writeln!(w, "  - Path deletion conflict \"{}\"", path)?
-            }
-            Conflict::MultipleNames { ref path, .. } => {
-                writeln!(w, "  - File has multiple names: \"{}\"", path)?
-            }
-            Conflict::Zombie {
-                ref path, ref line, ..
-            } => writeln!(
-                w,
-                "  - Deletion conflict in \"{}\" starting on line {}",
-                path, line
-            )?,
-            Conflict::Cyclic {
-                ref path, ref line, ..
-            } => writeln!(
-                w,
-                "  - Cycle conflict in \"{}\" starting on line {}",
-                path, line
-            )?,
-            Conflict::Order {
-                ref path, ref line, ..
-            } => writeln!(
-                w,
-                "  - Order conflict in \"{}\" starting on line {}",
-                path, line
-            )?,
-        }
+        writeln!(w, "  - {}", conflicts::describe_conflict(c))?
     }
     Ok(())
 }
@@ -0,0 +1,27 @@
+use anyhow::bail;
+
+/// Check that the user can perform `op` on `channel`, according to the
+/// protected channels of `repo`. Since the user has full access to the
+/// repository, this is only advisory: having any of their identities
+/// listed is enough.
+pub(crate) fn check_channel_permission(
+    repo: &pijul_repository::Repository,
+    channel: &str,
+    op: pijul_config::ChannelOperation,
+) -> Result<(), anyhow::Error> {
+    if repo.config.allowed_keys(channel, op).is_none() {
+        return Ok(());
+    }
+    let ids = pijul_identity::Complete::load_all().unwrap_or_default();
+    if !repo
+        .config
+        .is_allowed(channel, op, ids.iter().map(|id| id.public_key.key.as_str()))
+    {
+        bail!(
+            "Channel {:?} is protected, none of your identities can {} it",
+            channel,
+            op
+        )
+    }
+    Ok(())
+}
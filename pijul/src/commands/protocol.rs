@@ -7,8 +7,10 @@ use anyhow::bail;
 use byteorder::{BigEndian, WriteBytesExt};
 use clap::Parser;
 use lazy_static::lazy_static;
+use libpijul::output::{ArchiveFormat, ArchiveWriter};
 use libpijul::*;
 use log::{debug, error, warn};
+use pijul_config::ChannelOperation;
 use pijul_repository::Repository;
 use regex::Regex;
 
@@ -35,11 +37,13 @@ lazy_static! {
     static ref TAGUP: Regex = Regex::new(r#"^tagup\s+(\S+)\s+(\S+)\s+([0-9]+)\s+"#).unwrap();
     static ref APPLY: Regex = Regex::new(r#"apply\s+(\S+)\s+([^ ]*) ([0-9]+)\s+"#).unwrap();
     static ref CHANNEL: Regex = Regex::new(r#"channel\s+(\S+)\s+"#).unwrap();
+    static ref CHALLENGE: Regex = Regex::new(r#"^challenge\s+(.+)\s+"#).unwrap();
+    static ref PROVE: Regex = Regex::new(r#"^prove\s+(\S+)\s+"#).unwrap();
     static ref ARCHIVE: Regex =
-        Regex::new(r#"archive\s+(\S+)\s*(( ([^:]+))*)( :(.*))?\n"#).unwrap();
+        Regex::new(r#"archive\s+(format=(\S+)\s+)?(\S+)\s*(( ([^:]+))*)( :(.*))?\n"#).unwrap();
 }
 
-fn load_channel<T: MutTxnTExt>(txn: &T, name: &str) -> Result<ChannelRef<T>, anyhow::Error> {
+fn load_channel<T: TxnT>(txn: &T, name: &str) -> Result<ChannelRef<T>, anyhow::Error> {
     if let Some(c) = txn.load_channel(name)? {
         Ok(c)
     } else {
@@ -49,9 +53,11 @@ fn load_channel<T: MutTxnTExt>(txn: &T, name: &str) -> Result<ChannelRef<T>, any
 
 const PARTIAL_CHANGE_SIZE: u64 = 1 << 20;
 
+type ProtocolTxn = ArcTxn<libpijul::pristine::sanakirja::MutTxn<()>>;
+
 impl Protocol {
     pub fn run(self) -> Result<(), anyhow::Error> {
-        let mut repo = Repository::find_root(self.repo_path)?;
+        let repo = Repository::find_root(self.repo_path)?;
         let txn = repo.pristine.arc_txn_begin()?;
         let mut ws = libpijul::ApplyWorkspace::new();
         let mut buf = String::new();
@@ -61,169 +67,64 @@ impl Protocol {
         let o = std::io::stdout();
         let mut o = BufWriter::new(o.lock());
         let mut applied = HashMap::new();
+        let mut written = Vec::new();
+        let mut challenge = None;
+        let mut proven: Option<String> = None;
 
         debug!("reading");
         while s.read_line(&mut buf)? > 0 {
             debug!("{:?}", buf);
-            if let Some(cap) = ID.captures(&buf) {
-                let channel = load_channel(&*txn.read(), &cap[1])?;
-                let c = channel.read();
-                writeln!(o, "{}", c.id)?;
+            if let Some(cap) = CHALLENGE.captures(&buf) {
+                let key: libpijul::key::PublicKey = serde_json::from_str(&cap[1])?;
+                let c = new_challenge();
+                writeln!(o, "{}", c)?;
+                o.flush()?;
+                challenge = Some((key, c));
+            } else if let Some(cap) = PROVE.captures(&buf) {
+                let (key, c) = if let Some(c) = challenge.take() {
+                    c
+                } else {
+                    bail!("Protocol error")
+                };
+                key.load()?
+                    .verify(c.as_bytes(), &cap[1], &chrono::Utc::now())
+                    .map_err(|_| anyhow::anyhow!("Invalid proof"))?;
+                debug!("proven {:?}", key.key);
+                proven = Some(key.key);
+            } else if let Some(cap) = ID.captures(&buf) {
+                writeln!(o, "{}", channel_id(&*txn.read(), &cap[1])?)?;
                 o.flush()?;
             } else if let Some(cap) = STATE.captures(&buf) {
-                let channel = load_channel(&*txn.read(), &cap[1])?;
                 let init = if let Some(u) = cap.get(3) {
                     u.as_str().parse().ok()
                 } else {
                     None
                 };
-                if let Some(pos) = init {
-                    let txn = txn.read();
-                    for x in txn.log(&*channel.read(), pos)? {
-                        let (n, (_, m)) = x?;
-                        match n.cmp(&pos) {
-                            std::cmp::Ordering::Less => continue,
-                            std::cmp::Ordering::Greater => {
-                                writeln!(o, "-")?;
-                                break;
-                            }
-                            std::cmp::Ordering::Equal => {
-                                let m: libpijul::Merkle = m.into();
-                                let m2 = if let Some(x) = txn
-                                    .rev_iter_tags(txn.tags(&*channel.read()), Some(n))?
-                                    .next()
-                                {
-                                    x?.1.b.into()
-                                } else {
-                                    Merkle::zero()
-                                };
-                                writeln!(o, "{} {} {}", n, m.to_base32(), m2.to_base32())?;
-                                break;
-                            }
-                        }
-                    }
-                } else {
-                    let txn = txn.read();
-                    if let Some(x) = txn.reverse_log(&*channel.read(), None)?.next() {
-                        let (n, (_, m)) = x?;
-                        let m: Merkle = m.into();
-                        let m2 = if let Some(x) = txn
-                            .rev_iter_tags(txn.tags(&*channel.read()), Some(n))?
-                            .next()
-                        {
-                            x?.1.b.into()
-                        } else {
-                            Merkle::zero()
-                        };
-                        writeln!(o, "{} {} {}", n, m.to_base32(), m2.to_base32())?
-                    } else {
-                        writeln!(o, "-")?;
-                    }
-                }
+                write_state(&*txn.read(), &cap[1], init, &mut o)?;
                 o.flush()?;
             } else if let Some(cap) = CHANGELIST.captures(&buf) {
-                let channel = load_channel(&*txn.read(), &cap[1])?;
                 let from: u64 = cap[2].parse().unwrap();
-                let mut paths = HashSet::new();
                 debug!("cap[3] = {:?}", &cap[3]);
-                let txn = txn.read();
-                for r in CHANGELIST_PATHS.captures_iter(&cap[3]) {
-                    let s: String = r[1].replace("\\\"", "\"");
-                    if let Ok((p, ambiguous)) = txn.follow_oldest_path(&repo.changes, &channel, &s)
-                    {
-                        if ambiguous {
-                            bail!("Ambiguous path")
-                        }
-                        let h: libpijul::Hash = txn.get_external(&p.change)?.unwrap().into();
-                        writeln!(o, "{}.{}", h.to_base32(), p.pos.0)?;
-                        paths.insert(p);
-                        paths.extend(
-                            libpijul::fs::iter_graph_descendants(&*txn, &channel.read(), p)?
-                                .map(|x| x.unwrap()),
-                        );
-                    } else {
-                        debug!("protocol line: {:?}", buf);
-                        bail!("Protocol error")
-                    }
-                }
-                debug!("paths = {:?}", paths);
-                let tags: Vec<u64> = txn
-                    .iter_tags(txn.tags(&*channel.read()), from)?
-                    .map(|k| (*k.unwrap().0).into())
+                let paths: Vec<String> = CHANGELIST_PATHS
+                    .captures_iter(&cap[3])
+                    .map(|r| r[1].replace("\\\"", "\""))
                     .collect();
-                let mut tagsi = 0;
-                for x in txn.log(&*channel.read(), from)? {
-                    let (n, (h, m)) = x?;
-                    let h_int = txn.get_internal(h)?.unwrap();
-                    if paths.is_empty()
-                        || paths.iter().any(|x| {
-                            x.change == *h_int
-                                || txn.get_touched_files(x, Some(h_int)).unwrap().is_some()
-                        })
-                    {
-                        let h: Hash = h.into();
-                        let m: Merkle = m.into();
-                        if paths.is_empty() && tags.get(tagsi) == Some(&n) {
-                            writeln!(o, "{}.{}.{}.", n, h.to_base32(), m.to_base32())?;
-                            tagsi += 1;
-                        } else {
-                            writeln!(o, "{}.{}.{}", n, h.to_base32(), m.to_base32())?;
-                        }
-                    }
-                }
-                writeln!(o)?;
+                write_changelist(&repo, &*txn.read(), &cap[1], from, &paths, &mut o)?;
                 o.flush()?;
             } else if let Some(cap) = TAG.captures(&buf) {
                 if let Some(state) = Merkle::from_base32(cap[1].as_bytes()) {
-                    let mut tag_path = repo.changes_dir.clone();
-                    libpijul::changestore::filesystem::push_tag_filename(&mut tag_path, &state);
-                    let mut tag = libpijul::tag::OpenTagFile::open(&tag_path, &state)?;
-                    let mut buf = Vec::new();
-                    tag.short(&mut buf)?;
+                    let buf = short_tag(&repo, &state)?;
                     o.write_u64::<BigEndian>(buf.len() as u64)?;
                     o.write_all(&buf)?;
                     o.flush()?;
                 }
             } else if let Some(cap) = TAGUP.captures(&buf) {
                 if let Some(state) = Merkle::from_base32(cap[1].as_bytes()) {
-                    let channel = load_channel(&*txn.read(), &cap[2])?;
-                    let m = libpijul::pristine::current_state(&*txn.read(), &*channel.read())?;
-                    if m == state {
-                        let mut tag_path = repo.changes_dir.clone();
-                        libpijul::changestore::filesystem::push_tag_filename(&mut tag_path, &m);
-                        if std::fs::metadata(&tag_path).is_ok() {
-                            bail!("Tag for state {} already exists", m.to_base32());
-                        }
-
-                        let last_t = if let Some(n) =
-                            txn.read().reverse_log(&*channel.read(), None)?.next()
-                        {
-                            n?.0.into()
-                        } else {
-                            bail!("Channel {} is empty", &cap[2]);
-                        };
-                        if txn.read().is_tagged(&channel.read().tags, last_t)? {
-                            bail!("Current state is already tagged")
-                        }
-
-                        let size: usize = cap[3].parse().unwrap();
-                        let mut buf = vec![0; size];
-                        s.read_exact(&mut buf)?;
-
-                        let header = libpijul::tag::read_short(std::io::Cursor::new(&buf[..]), &m)?;
-
-                        let temp_path = tag_path.with_extension("tmp");
-
-                        std::fs::create_dir_all(temp_path.parent().unwrap())?;
-                        let mut w = std::fs::File::create(&temp_path)?;
-                        libpijul::tag::from_channel(&*txn.read(), &cap[2], &header, &mut w)?;
-
-                        std::fs::rename(&temp_path, &tag_path)?;
-                        txn.write()
-                            .put_tags(&mut channel.write().tags, last_t.into(), &m)?;
-                    } else {
-                        bail!("Wrong state, cannot tag")
-                    }
+                    let size: usize = cap[3].parse().unwrap();
+                    let mut buf = vec![0; size];
+                    s.read_exact(&mut buf)?;
+                    check_permission(&repo, &cap[2], ChannelOperation::Tag, proven.as_deref())?;
+                    tagup(&repo, &txn, &cap[2], &state, &buf)?;
                 }
             } else if let Some(cap) = CHANGE.captures(&buf) {
                 let h_ = &cap[4];
@@ -233,15 +134,7 @@ impl Protocol {
                     debug!("protocol error: {:?}", buf);
                     bail!("Protocol error")
                 };
-                libpijul::changestore::filesystem::push_filename(&mut repo.changes_dir, &h);
-                debug!("repo = {:?}", repo.changes_dir);
-                let mut f = std::fs::File::open(&repo.changes_dir)?;
-                let size = std::fs::metadata(&repo.changes_dir)?.len();
-                let size = if &cap[1] == "change" || size <= PARTIAL_CHANGE_SIZE {
-                    size
-                } else {
-                    libpijul::change::Change::size_no_contents(&mut f)?
-                };
+                let (mut f, size) = open_change(&repo, &h, &cap[1] == "partial")?;
                 o.write_u64::<BigEndian>(size)?;
                 let mut size = size as usize;
                 while size > 0 {
@@ -256,7 +149,6 @@ impl Protocol {
                     o.write_all(&buf2[..n])?;
                 }
                 o.flush()?;
-                libpijul::changestore::filesystem::pop_filename(&mut repo.changes_dir);
             } else if let Some(cap) = APPLY.captures(&buf) {
                 let h = if let Some(h) = Hash::from_base32(cap[2].as_bytes()) {
                     h
@@ -264,66 +156,50 @@ impl Protocol {
                     debug!("protocol error {:?}", buf);
                     bail!("Protocol error");
                 };
-                let mut path = repo.changes_dir.clone();
-                libpijul::changestore::filesystem::push_filename(&mut path, &h);
-                std::fs::create_dir_all(path.parent().unwrap())?;
                 let size: usize = cap[3].parse().unwrap();
                 buf2.resize(size, 0);
                 s.read_exact(&mut buf2)?;
-                std::fs::write(&path, &buf2)?;
-                libpijul::change::Change::deserialize(&path.to_string_lossy(), Some(&h))?;
-                let channel = load_channel(&*txn.read(), &cap[1])?;
-                {
-                    let mut channel_ = channel.write();
-                    txn.write()
-                        .apply_change_ws(&repo.changes, &mut channel_, &h, &mut ws)?;
-                }
-                applied.insert(cap[1].to_string(), channel);
+                check_permission(&repo, &cap[1], ChannelOperation::Push, proven.as_deref())?;
+                let channel = apply(&repo, &txn, &mut ws, &cap[1], &h, &buf2, &mut written)?;
+                applied
+                    .entry(cap[1].to_string())
+                    .or_insert_with(|| (channel, Vec::new()))
+                    .1
+                    .push(h);
             } else if let Some(cap) = ARCHIVE.captures(&buf) {
-                let mut w = Vec::new();
-                let mut tarball = libpijul::output::Tarball::new(
-                    &mut w,
-                    cap.get(6).map(|x| x.as_str().to_string()),
-                    0,
-                );
-                let channel = load_channel(&*txn.read(), &cap[1])?;
-                let conflicts = if let Some(caps) = cap.get(2) {
-                    debug!("caps = {:?}", caps.as_str());
-                    let mut hashes = caps.as_str().split(' ').filter(|x| !x.is_empty());
-                    let state: libpijul::Merkle = hashes.next().unwrap().parse().unwrap();
-                    let extra: Vec<libpijul::Hash> = hashes.map(|x| x.parse().unwrap()).collect();
-                    debug!("state = {:?}, extra = {:?}", state, extra);
-                    if txn.read().current_state(&*channel.read())? == state && extra.is_empty() {
-                        txn.archive(&repo.changes, &channel, &mut tarball)?
-                    } else {
-                        use rand::Rng;
-                        let fork_name: String = rand::thread_rng()
-                            .sample_iter(&rand::distributions::Alphanumeric)
-                            .take(30)
-                            .map(|x| x as char)
-                            .collect();
-                        let mut fork = {
-                            let mut txn = txn.write();
-                            txn.fork(&channel, &fork_name)?
-                        };
-                        let conflicts = txn.archive_with_state(
-                            &repo.changes,
-                            &mut fork,
-                            &state,
-                            &extra,
-                            &mut tarball,
-                            0,
-                        )?;
-                        txn.write().drop_channel(&fork_name)?;
-                        conflicts
-                    }
+                let format = if let Some(f) = cap.get(2) {
+                    f.as_str().parse().map_err(|e: String| anyhow::anyhow!(e))?
+                } else {
+                    ArchiveFormat::TarGz
+                };
+                let mut w = std::io::Cursor::new(Vec::new());
+                let mut hashes = cap
+                    .get(4)
+                    .map(|caps| caps.as_str())
+                    .unwrap_or("")
+                    .split(' ')
+                    .filter(|x| !x.is_empty());
+                debug!("caps = {:?}", cap.get(4));
+                let state = if let Some(state) = hashes.next() {
+                    let state: Merkle = state.parse()?;
+                    let extra = hashes.map(|x| x.parse()).collect::<Result<Vec<_>, _>>()?;
+                    Some((state, extra))
                 } else {
-                    txn.archive(&repo.changes, &channel, &mut tarball)?
+                    None
                 };
-                std::mem::drop(tarball);
+                let conflicts = archive(
+                    &repo,
+                    &txn,
+                    &cap[3],
+                    state,
+                    format,
+                    cap.get(8).map(|x| x.as_str().to_string()),
+                    &mut w,
+                )?;
+                let w = w.into_inner();
                 let mut o = std::io::stdout();
                 o.write_u64::<BigEndian>(w.len() as u64)?;
-                o.write_u64::<BigEndian>(conflicts.len() as u64)?;
+                o.write_u64::<BigEndian>(conflicts as u64)?;
                 o.write_all(&w)?;
                 o.flush()?;
             } else if let Some(cap) = IDENTITIES.captures(&buf) {
@@ -332,22 +208,20 @@ impl Protocol {
                 } else {
                     0
                 };
-                let mut id_dir = repo.path.clone();
-                id_dir.push(DOT_DIR);
-                id_dir.push("identities");
-                let r = if let Ok(r) = std::fs::read_dir(&id_dir) {
-                    r
+                let ids = if let Some(ids) = identities(&repo, last_touched)? {
+                    ids
                 } else {
                     writeln!(o)?;
                     o.flush()?;
+                    buf.clear();
                     continue;
                 };
-                let mut at_least_one = false;
-                for id in r {
-                    at_least_one |= output_id(id, last_touched, &mut o).unwrap_or(false);
+                for (_, id) in ids.iter() {
+                    serde_json::to_writer(&mut o, id)?;
+                    writeln!(o)?;
                 }
-                debug!("at least one {:?}", at_least_one);
-                if !at_least_one {
+                debug!("at least one {:?}", !ids.is_empty());
+                if ids.is_empty() {
                     writeln!(o)?;
                 }
                 writeln!(o)?;
@@ -357,32 +231,412 @@ impl Protocol {
             }
             buf.clear();
         }
-        let applied_nonempty = !applied.is_empty();
-        for (_, channel) in applied {
-            libpijul::output::output_repository_no_pending(
-                &repo.working_copy,
-                &repo.changes,
-                &txn,
-                &channel,
-                "",
-                true,
-                None,
-                std::thread::available_parallelism()?.get(),
-                0,
-            )?;
-        }
-        if applied_nonempty {
+        if !applied.is_empty() {
+            let mut hooks = Vec::with_capacity(applied.len());
+            for (name, (channel, changes)) in applied.iter() {
+                let mut hook = apply_hook_context(&txn, name, channel, changes)?;
+                if let Err(e) =
+                    pijul_config::run_hooks(&repo.config.hooks.pre_apply, &repo.path, &hook)
+                {
+                    discard(&written);
+                    bail!("Push to channel {:?} rejected: {}", name, e)
+                }
+                hook.hook = "post_apply";
+                hooks.push(hook);
+            }
+            output_applied(&repo, &txn, applied.into_values().map(|(c, _)| c))?;
             txn.commit()?;
+            for hook in hooks {
+                pijul_config::run_post_hooks(&repo.config.hooks.post_apply, &repo.path, &hook);
+            }
         }
         Ok(())
     }
 }
 
-fn output_id<W: Write>(
+/// A random string for clients to sign in order to prove their identity.
+pub(crate) fn new_challenge() -> String {
+    use rand::Rng;
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(32)
+        .map(|x| x as char)
+        .collect()
+}
+
+/// Check that the protected channels of the repository allow `key`,
+/// the public key proven by the client if any, to perform `op` on
+/// channel `name`.
+pub(crate) fn check_permission(
+    repo: &Repository,
+    name: &str,
+    op: ChannelOperation,
+    key: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    if repo.config.is_allowed(name, op, key) {
+        Ok(())
+    } else if key.is_none() {
+        bail!(
+            "Channel {:?} is protected, an identity must be proven to {} it",
+            name,
+            op
+        )
+    } else {
+        bail!("Not allowed to {} channel {:?}", op, name)
+    }
+}
+
+/// Identifier of channel `name`, as sent to clients in answer to `id`.
+pub(crate) fn channel_id<T: TxnT>(
+    txn: &T,
+    name: &str,
+) -> Result<libpijul::pristine::RemoteId, anyhow::Error> {
+    let channel = load_channel(txn, name)?;
+    let c = channel.read();
+    if let Some(id) = txn.id(&c) {
+        Ok(*id)
+    } else {
+        bail!("Channel {:?} has no id", name)
+    }
+}
+
+/// Write the position, state and last tagged state of channel `name`,
+/// either at position `init` or at the end of the log.
+pub(crate) fn write_state<T: TxnTExt, W: Write>(
+    txn: &T,
+    name: &str,
+    init: Option<u64>,
+    mut o: W,
+) -> Result<(), anyhow::Error> {
+    let channel = load_channel(txn, name)?;
+    if let Some(pos) = init {
+        for x in txn.log(&*channel.read(), pos)? {
+            let (n, (_, m)) = x?;
+            match n.cmp(&pos) {
+                std::cmp::Ordering::Less => continue,
+                std::cmp::Ordering::Greater => {
+                    writeln!(o, "-")?;
+                    break;
+                }
+                std::cmp::Ordering::Equal => {
+                    let m: libpijul::Merkle = m.into();
+                    let m2 = if let Some(x) = txn
+                        .rev_iter_tags(txn.tags(&*channel.read()), Some(n))?
+                        .next()
+                    {
+                        x?.1.b.into()
+                    } else {
+                        Merkle::zero()
+                    };
+                    writeln!(o, "{} {} {}", n, m.to_base32(), m2.to_base32())?;
+                    break;
+                }
+            }
+        }
+    } else if let Some(x) = txn.reverse_log(&*channel.read(), None)?.next() {
+        let (n, (_, m)) = x?;
+        let m: Merkle = m.into();
+        let m2 = if let Some(x) = txn
+            .rev_iter_tags(txn.tags(&*channel.read()), Some(n))?
+            .next()
+        {
+            x?.1.b.into()
+        } else {
+            Merkle::zero()
+        };
+        writeln!(o, "{} {} {}", n, m.to_base32(), m2.to_base32())?
+    } else {
+        writeln!(o, "-")?;
+    }
+    Ok(())
+}
+
+/// Write the log of channel `name` starting at position `from`,
+/// restricted to the changes touching `paths` if that list is not
+/// empty. The list is terminated by an empty line.
+pub(crate) fn write_changelist<T: TxnTExt, W: Write>(
+    repo: &Repository,
+    txn: &T,
+    name: &str,
+    from: u64,
+    paths_: &[String],
+    mut o: W,
+) -> Result<(), anyhow::Error> {
+    let channel = load_channel(txn, name)?;
+    let mut paths = HashSet::new();
+    for s in paths_ {
+        if let Ok((p, ambiguous)) = txn.follow_oldest_path(&repo.changes, &channel, s) {
+            if ambiguous {
+                bail!("Ambiguous path")
+            }
+            let h: libpijul::Hash = txn.get_external(&p.change)?.unwrap().into();
+            writeln!(o, "{}.{}", h.to_base32(), p.pos.0)?;
+            paths.insert(p);
+            paths.extend(
+                libpijul::fs::iter_graph_descendants(txn, txn.graph(&channel.read()), p)?
+                    .map(|x| x.unwrap()),
+            );
+        } else {
+            debug!("protocol path: {:?}", s);
+            bail!("Protocol error")
+        }
+    }
+    debug!("paths = {:?}", paths);
+    let tags: Vec<u64> = txn
+        .iter_tags(txn.tags(&*channel.read()), from)?
+        .map(|k| (*k.unwrap().0).into())
+        .collect();
+    let mut tagsi = 0;
+    for x in txn.log(&*channel.read(), from)? {
+        let (n, (h, m)) = x?;
+        let h_int = txn.get_internal(h)?.unwrap();
+        if paths.is_empty()
+            || paths.iter().any(|x| {
+                x.change == *h_int || txn.get_touched_files(x, Some(h_int)).unwrap().is_some()
+            })
+        {
+            let h: Hash = h.into();
+            let m: Merkle = m.into();
+            if paths.is_empty() && tags.get(tagsi) == Some(&n) {
+                writeln!(o, "{}.{}.{}.", n, h.to_base32(), m.to_base32())?;
+                tagsi += 1;
+            } else {
+                writeln!(o, "{}.{}.{}", n, h.to_base32(), m.to_base32())?;
+            }
+        }
+    }
+    writeln!(o)?;
+    Ok(())
+}
+
+/// The short version of the tag file for `state`.
+pub(crate) fn short_tag(repo: &Repository, state: &Merkle) -> Result<Vec<u8>, anyhow::Error> {
+    let mut tag_path = repo.changes_dir.clone();
+    libpijul::changestore::filesystem::push_tag_filename(&mut tag_path, state);
+    let mut tag = libpijul::tag::OpenTagFile::open(&tag_path, state)?;
+    let mut buf = Vec::new();
+    tag.short(&mut buf)?;
+    Ok(buf)
+}
+
+/// Tag the current state of channel `name`, which must be `state`,
+/// from the short tag file `contents`.
+pub(crate) fn tagup(
+    repo: &Repository,
+    txn: &ProtocolTxn,
+    name: &str,
+    state: &Merkle,
+    contents: &[u8],
+) -> Result<(), anyhow::Error> {
+    let channel = load_channel(&*txn.read(), name)?;
+    let m = libpijul::pristine::current_state(&*txn.read(), &*channel.read())?;
+    if m != *state {
+        bail!("Wrong state, cannot tag")
+    }
+    let mut tag_path = repo.changes_dir.clone();
+    libpijul::changestore::filesystem::push_tag_filename(&mut tag_path, &m);
+    if std::fs::metadata(&tag_path).is_ok() {
+        bail!("Tag for state {} already exists", m.to_base32());
+    }
+
+    let last_t = if let Some(n) = txn.read().reverse_log(&*channel.read(), None)?.next() {
+        n?.0.into()
+    } else {
+        bail!("Channel {} is empty", name);
+    };
+    if txn.read().is_tagged(&channel.read().tags, last_t)? {
+        bail!("Current state is already tagged")
+    }
+
+    let header = libpijul::tag::read_short(std::io::Cursor::new(contents), &m)?;
+
+    let temp_path = tag_path.with_extension("tmp");
+
+    std::fs::create_dir_all(temp_path.parent().unwrap())?;
+    let mut w = std::fs::File::create(&temp_path)?;
+    libpijul::tag::from_channel(&*txn.read(), name, &header, &mut w)?;
+
+    std::fs::rename(&temp_path, &tag_path)?;
+    txn.write()
+        .put_tags(&mut channel.write().tags, last_t.into(), &m)?;
+    Ok(())
+}
+
+/// Open the file of change `h`, returning it along with the number of
+/// bytes to send: the whole file, or only the part without the
+/// contents of the change if `partial` is set and the change is large.
+pub(crate) fn open_change(
+    repo: &Repository,
+    h: &Hash,
+    partial: bool,
+) -> Result<(std::fs::File, u64), anyhow::Error> {
+    let mut path = repo.changes_dir.clone();
+    libpijul::changestore::filesystem::push_filename(&mut path, h);
+    debug!("repo = {:?}", path);
+    let mut f = std::fs::File::open(&path)?;
+    let size = std::fs::metadata(&path)?.len();
+    let size = if !partial || size <= PARTIAL_CHANGE_SIZE {
+        size
+    } else {
+        libpijul::change::Change::size_no_contents(&mut f)?
+    };
+    Ok((f, size))
+}
+
+/// Save the change `h`, received as `contents`, to the change store,
+/// and apply it to channel `name`. If the change wasn't in the store
+/// yet, its path is pushed to `written`, so that it can be removed with
+/// [`discard`] if the push is rejected.
+pub(crate) fn apply(
+    repo: &Repository,
+    txn: &ProtocolTxn,
+    ws: &mut libpijul::ApplyWorkspace,
+    name: &str,
+    h: &Hash,
+    contents: &[u8],
+    written: &mut Vec<PathBuf>,
+) -> Result<ChannelRef<libpijul::pristine::sanakirja::MutTxn<()>>, anyhow::Error> {
+    let mut path = repo.changes_dir.clone();
+    libpijul::changestore::filesystem::push_filename(&mut path, h);
+    if !path.exists() {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(&path, contents)?;
+        written.push(path.clone());
+    }
+    libpijul::change::Change::deserialize(&path.to_string_lossy(), Some(h))?;
+    let channel = load_channel(&*txn.read(), name)?;
+    {
+        let mut channel_ = channel.write();
+        txn.write()
+            .apply_change_ws(&repo.changes, &mut channel_, h, ws)?;
+    }
+    Ok(channel)
+}
+
+/// Remove the changes written by [`apply`] from the change store, after
+/// a rejected push. The transaction must not be committed.
+pub(crate) fn discard(written: &[PathBuf]) {
+    for path in written {
+        std::fs::remove_file(path).unwrap_or(());
+        // Only succeeds if the directory is now empty.
+        std::fs::remove_dir(path.parent().unwrap()).unwrap_or(());
+    }
+}
+
+/// Context of the `pre_apply` hooks for `changes`, pushed to channel
+/// `name`. The state is the one the channel will be in if the push is
+/// accepted.
+pub(crate) fn apply_hook_context(
+    txn: &ProtocolTxn,
+    name: &str,
+    channel: &ChannelRef<libpijul::pristine::sanakirja::MutTxn<()>>,
+    changes: &[Hash],
+) -> Result<pijul_config::HookContext, anyhow::Error> {
+    Ok(pijul_config::HookContext {
+        hook: "pre_apply",
+        channel: Some(name.to_string()),
+        changes: changes.iter().map(|h| h.to_base32()).collect(),
+        state: super::hooks::hook_state(&*txn.read(), &*channel.read())?,
+        ..Default::default()
+    })
+}
+
+/// Output the channels changes were applied to, before committing.
+pub(crate) fn output_applied<
+    I: Iterator<Item = ChannelRef<libpijul::pristine::sanakirja::MutTxn<()>>>,
+>(
+    repo: &Repository,
+    txn: &ProtocolTxn,
+    applied: I,
+) -> Result<(), anyhow::Error> {
+    for channel in applied {
+        libpijul::output::output_repository_no_pending(
+            &repo.working_copy,
+            &repo.changes,
+            txn,
+            &channel,
+            "",
+            true,
+            None,
+            std::thread::available_parallelism()?.get(),
+            0,
+            super::config::conflict_style(repo),
+        )?;
+    }
+    Ok(())
+}
+
+/// Write an archive of channel `name` to `w`, optionally at `state`
+/// with extra changes applied, returning the number of conflicts.
+pub(crate) fn archive<W: Write + std::io::Seek>(
+    repo: &Repository,
+    txn: &ProtocolTxn,
+    name: &str,
+    state: Option<(Merkle, Vec<Hash>)>,
+    format: ArchiveFormat,
+    prefix: Option<String>,
+    w: W,
+) -> Result<usize, anyhow::Error> {
+    let mut arch = ArchiveWriter::new(format, w, prefix, 0)?;
+    let channel = load_channel(&*txn.read(), name)?;
+    let conflicts = if let Some((state, extra)) = state {
+        debug!("state = {:?}, extra = {:?}", state, extra);
+        if txn.read().current_state(&*channel.read())? == state && extra.is_empty() {
+            txn.archive(&repo.changes, &channel, &mut arch)?
+        } else {
+            use rand::Rng;
+            let fork_name: String = rand::thread_rng()
+                .sample_iter(&rand::distributions::Alphanumeric)
+                .take(30)
+                .map(|x| x as char)
+                .collect();
+            let mut fork = {
+                let mut txn = txn.write();
+                txn.fork(&channel, &fork_name)?
+            };
+            let conflicts =
+                txn.archive_with_state(&repo.changes, &mut fork, &state, &extra, &mut arch, 0)?;
+            txn.write().drop_channel(&fork_name)?;
+            conflicts
+        }
+    } else {
+        txn.archive(&repo.changes, &channel, &mut arch)?
+    };
+    arch.finish()?;
+    Ok(conflicts.len())
+}
+
+/// The identities of the repository modified after `last_touched`
+/// (in seconds since the Unix epoch), along with their modification
+/// times, or `None` if the repository has no identities directory.
+pub(crate) fn identities(
+    repo: &Repository,
+    last_touched: u64,
+) -> Result<Option<Vec<(u64, pijul_identity::Complete)>>, anyhow::Error> {
+    let mut id_dir = repo.path.clone();
+    id_dir.push(DOT_DIR);
+    id_dir.push("identities");
+    let r = if let Ok(r) = std::fs::read_dir(&id_dir) {
+        r
+    } else {
+        return Ok(None);
+    };
+    let mut done = HashSet::new();
+    let mut ids = Vec::new();
+    for id in r {
+        if let Some((mod_ts, id)) = load_id(id, last_touched).unwrap_or(None) {
+            if done.insert(id.public_key.key.clone()) {
+                ids.push((mod_ts, id))
+            }
+        }
+    }
+    Ok(Some(ids))
+}
+
+fn load_id(
     id: Result<std::fs::DirEntry, std::io::Error>,
     last_touched: u64,
-    mut o: W,
-) -> Result<bool, anyhow::Error> {
+) -> Result<Option<(u64, pijul_identity::Complete)>, anyhow::Error> {
     let id = id?;
     let m = id.metadata()?;
     let p = id.path();
@@ -393,26 +647,20 @@ fn output_id<W: Write>(
         .unwrap()
         .as_secs();
     if mod_ts >= last_touched {
-        let mut done = HashSet::new();
         if p.file_name() == Some("publickey.json".as_ref()) {
             warn!("Skipping serializing old public key format.");
-            return Ok(false);
+            return Ok(None);
         } else {
             let mut idf = if let Ok(f) = std::fs::File::open(&p) {
                 f
             } else {
-                return Ok(false);
+                return Ok(None);
             };
             let id: Result<pijul_identity::Complete, _> = serde_json::from_reader(&mut idf);
             if let Ok(id) = id {
-                if !done.insert(id.public_key.key.clone()) {
-                    return Ok(false);
-                }
-                serde_json::to_writer(&mut o, &id.as_portable()).unwrap();
-                writeln!(o)?;
-                return Ok(true);
+                return Ok(Some((mod_ts, id.as_portable())));
             }
         }
     }
-    Ok(false)
+    Ok(None)
 }
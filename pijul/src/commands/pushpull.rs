@@ -98,6 +98,13 @@ pub struct Push {
     /// Push to this remote channel instead of the remote's default channel
     #[clap(long = "to-channel")]
     to_channel: Option<String>,
+    /// Prove this identity to the remote before pushing, in order to
+    /// push to its protected channels (SSH remotes only)
+    #[clap(long = "identity")]
+    identity: Option<String>,
+    /// Show the changes that would be pushed, the paths they touch and the conflicts they would create in the remote channel, without pushing. The state of the remote channel is estimated locally, as the local channel without the changes to push: changes of the remote that are unknown locally are not taken into account.
+    #[clap(long = "dry-run")]
+    dry_run: bool,
     /// Push only these changes
     #[clap(last = true)]
     changes: Vec<String>,
@@ -132,6 +139,9 @@ pub struct Pull {
     /// Pull from this remote channel
     #[clap(long = "from-channel")]
     from_channel: Option<String>,
+    /// Show the paths that would be touched and the conflicts that would result, without modifying the channel
+    #[clap(long = "dry-run")]
+    dry_run: bool,
     /// Pull changes from the local repository, not necessarily from a channel
     #[clap(last = true)]
     changes: Vec<String>, // For local changes only, can't be symmetric.
@@ -245,6 +255,9 @@ impl Push {
         notify_remote_unrecords(&repo, remote_unrecs.as_slice());
         notify_unknown_changes(unknown_changes.as_slice());
 
+        // All the changes missing from the remote, before the user
+        // selects which ones to push.
+        let outgoing = changes_of(&to_upload);
         let to_upload = if !self.changes.is_empty() {
             let mut u: Vec<CS> = Vec::new();
             let mut not_found = Vec::new();
@@ -311,6 +324,29 @@ impl Push {
             return Ok(());
         }
 
+        if self.dry_run {
+            // The remote channel is approximated by the local channel
+            // without the outgoing changes.
+            super::dry_run::dry_run(&repo, &txn, &channel, &outgoing, &changes_of(&to_upload))?;
+            remote.finish().await?;
+            return Ok(());
+        }
+
+        let mut hook = pijul_config::HookContext {
+            hook: "pre_push",
+            channel: Some(channel_name.to_string()),
+            remote: Some(remote_name.to_string()),
+            changes: super::hooks::hook_changes(&to_upload),
+            state: super::hooks::hook_state(&*txn.read(), &*channel.read())?,
+            ..Default::default()
+        };
+        pijul_config::run_hooks(&repo.config.hooks.pre_push, &repo.path, &hook)?;
+
+        if let Some(ref identity) = self.identity {
+            let (key, _) = pijul_identity::Complete::load(identity)?.decrypt()?;
+            remote.prove(key).await?;
+        }
+
         remote
             .upload_changes(
                 &mut *txn.write(),
@@ -322,6 +358,8 @@ impl Push {
         txn.commit()?;
 
         remote.finish().await?;
+        hook.hook = "post_push";
+        pijul_config::run_post_hooks(&repo.config.hooks.post_push, &repo.path, &hook);
         Ok(())
     }
 }
@@ -383,6 +421,11 @@ impl Pull {
             cur.as_str()
         };
         let is_current_channel = channel_name == cur;
+        super::protected::check_channel_permission(
+            &repo,
+            channel_name,
+            pijul_config::ChannelOperation::Push,
+        )?;
         let mut channel = txn.write().open_or_create_channel(&channel_name)?;
         debug!("{:?}", repo.config);
         let remote_name = if let Some(ref rem) = self.from {
@@ -408,6 +451,7 @@ impl Pull {
         )
         .await?;
         debug!("downloading");
+        let hook_remote = remote_name.to_string();
 
         let RemoteDelta {
             inodes,
@@ -453,6 +497,32 @@ impl Pull {
             to_download = complete_deps(&repo.changes, None, &to_download)?;
         }
 
+        super::signatures::check_signatures(
+            &repo,
+            to_download.iter().filter_map(|h| match h {
+                CS::Change(h) => Some(h),
+                CS::State(_) => None,
+            }),
+        )?;
+        if self.dry_run {
+            let changes: Vec<_> = changes_of(&to_download).into_iter().rev().collect();
+            super::dry_run::dry_run(&repo, &txn, &channel, &[], &changes)?;
+            if let Some(h) = hash {
+                repo.changes.del_change(&h)?;
+            }
+            remote.finish().await?;
+            return Ok(());
+        }
+
+        let mut hook = pijul_config::HookContext {
+            hook: "pre_pull",
+            channel: Some(channel_name.to_string()),
+            remote: Some(hook_remote),
+            changes: super::hooks::hook_changes(&to_download),
+            ..Default::default()
+        };
+        pijul_config::run_hooks(&repo.config.hooks.pre_pull, &repo.path, &hook)?;
+
         {
             // Now that .pull is always given `false` for `do_apply`...
             let mut ws = libpijul::ApplyWorkspace::new();
@@ -568,6 +638,7 @@ impl Pull {
                         None,
                         std::thread::available_parallelism()?.get(),
                         0,
+                        super::config::conflict_style(&repo),
                     )?
                     .into_iter(),
                 );
@@ -581,11 +652,24 @@ impl Pull {
             repo.changes.del_change(&h)?;
         }
 
+        hook.hook = "post_pull";
+        hook.state = super::hooks::hook_state(&*txn.read(), &*channel.read())?;
         txn.commit()?;
+        pijul_config::run_post_hooks(&repo.config.hooks.post_pull, &repo.path, &hook);
         Ok(())
     }
 }
 
+/// The changes in `cs`, without the tags.
+fn changes_of(cs: &[CS]) -> Vec<libpijul::Hash> {
+    cs.iter()
+        .filter_map(|c| match c {
+            CS::Change(h) => Some(*h),
+            CS::State(_) => None,
+        })
+        .collect()
+}
+
 fn complete_deps<C: ChangeStore>(
     c: &C,
     original: Option<&[CS]>,
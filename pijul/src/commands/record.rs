@@ -14,6 +14,7 @@ use libpijul::{
 use libpijul::{HashMap, HashSet};
 use log::debug;
 
+use pijul_interaction::Input;
 use pijul_repository::*;
 
 #[derive(Parser, Debug)]
@@ -21,6 +22,9 @@ pub struct Record {
     /// Record all paths that have changed
     #[clap(short = 'a', long = "all")]
     pub all: bool,
+    /// Choose the hunks to record one by one in the terminal, instead of editing the change
+    #[clap(short = 'i', long = "interactive", conflicts_with = "all")]
+    pub interactive: bool,
     /// Set the change message
     #[clap(short = 'm', long = "message")]
     pub message: Option<String>,
@@ -53,9 +57,23 @@ pub struct Record {
     /// Identity to sign changes with
     #[clap(long = "identity")]
     pub identity: Option<String>,
-    /// Use Patience diff instead of the default Myers diff
-    #[clap(long = "patience")]
+    /// Use Patience diff instead of the configured `diff_algorithm`
+    /// (Myers diff by default)
+    #[clap(long = "patience", conflicts_with = "histogram")]
     pub patience: bool,
+    /// Use Histogram diff instead of the configured `diff_algorithm`
+    /// (Myers diff by default)
+    #[clap(long = "histogram")]
+    pub histogram: bool,
+    /// Detect files moved without `pijul mv`, by comparing the missing files with the new ones. The optional value is the minimal similarity of their contents, in percent.
+    #[clap(
+        long = "detect-renames",
+        value_name = "SIMILARITY",
+        num_args = 0..=1,
+        default_missing_value = "50",
+        value_parser = clap::value_parser!(u8).range(0..=100)
+    )]
+    pub detect_renames: Option<u8>,
 }
 
 pub(crate) fn parse_datetime_rfc2822(s: &str) -> Result<i64, &'static str> {
@@ -88,11 +106,24 @@ impl Record {
         } else {
             cur.as_str()
         };
+        let channel_name = channel.to_string();
         let mut channel = if let Some(channel) = txn.read().load_channel(&channel)? {
             channel
         } else {
             bail!("Channel {:?} not found", channel);
         };
+        super::protected::check_channel_permission(
+            &repo,
+            &channel_name,
+            pijul_config::ChannelOperation::Push,
+        )?;
+        if self.amend.is_some() {
+            super::protected::check_channel_permission(
+                &repo,
+                &channel_name,
+                pijul_config::ChannelOperation::Unrecord,
+            )?;
+        }
 
         let mut extra = Vec::new();
         for h in repo.config.extra_dependencies.iter() {
@@ -157,14 +188,31 @@ impl Record {
         txn.write()
             .apply_root_change_if_needed(&repo.changes, &channel, rand::thread_rng())?;
 
+        let working_copy = working_copy.as_ref().unwrap_or(&repo.working_copy);
+        if let Some(threshold) = self.detect_renames {
+            super::renames::detect_renames(
+                &repo,
+                working_copy,
+                &repo_path,
+                &txn,
+                &channel,
+                threshold,
+            )?;
+        }
+
+        let algorithm = super::config::diff_algorithm(&repo, self.patience, self.histogram);
+        let separators = super::config::diff_separators(&repo)?;
         let result = self.record(
             txn,
             channel.clone(),
-            working_copy.as_ref().unwrap_or(&repo.working_copy),
+            working_copy,
             &repo.changes,
             repo_path,
             header,
             &extra,
+            algorithm,
+            separators,
+            super::config::conflict_style(&repo),
         )?;
         match result {
             Either::A((txn, mut change, updates, oldest)) => {
@@ -201,7 +249,15 @@ impl Record {
                     txn_.touch_channel(&mut *channel.write(), Some((oldest / 1000) * 1000));
                 }
                 std::mem::drop(txn_);
+                let hook = pijul_config::HookContext {
+                    hook: "post_record",
+                    channel: Some(channel_name),
+                    changes: vec![hash.to_base32()],
+                    state: super::hooks::hook_state(&*txn.read(), &*channel.read())?,
+                    ..Default::default()
+                };
                 txn.commit()?;
+                pijul_config::run_post_hooks(&repo.config.hooks.post_record, &repo.path, &hook);
             }
             Either::B(txn) => {
                 if no_prefixes {
@@ -297,6 +353,9 @@ impl Record {
         repo_path: CanonicalPathBuf,
         header: ChangeHeader,
         extra_deps: &[libpijul::Hash],
+        algorithm: libpijul::Algorithm,
+        separators: libpijul::Separators,
+        conflict_style: libpijul::vertex_buffer::ConflictStyle,
     ) -> Result<
         Either<
             (
@@ -310,6 +369,8 @@ impl Record {
         anyhow::Error,
     > {
         let mut state = libpijul::RecordBuilder::new();
+        state.separators = separators;
+        state.conflict_style = conflict_style;
         if self.ignore_missing {
             state.ignore_missing = true;
         }
@@ -322,11 +383,7 @@ impl Record {
                         let p = p.as_path().strip_prefix(&repo_path).unwrap();
                         state.record(
                             txn.clone(),
-                            if self.patience {
-                                libpijul::Algorithm::Patience
-                            } else {
-                                libpijul::Algorithm::default()
-                            },
+                            algorithm,
                             false,
                             &libpijul::DEFAULT_SEPARATOR,
                             channel.clone(),
@@ -340,11 +397,7 @@ impl Record {
             } else {
                 state.record(
                     txn.clone(),
-                    if self.patience {
-                        libpijul::Algorithm::Patience
-                    } else {
-                        libpijul::Algorithm::default()
-                    },
+                    algorithm,
                     false,
                     &libpijul::DEFAULT_SEPARATOR,
                     channel.clone(),
@@ -358,11 +411,7 @@ impl Record {
             self.fill_relative_prefixes()?;
             working_copy.record_prefixes(
                 txn.clone(),
-                if self.patience {
-                    libpijul::Algorithm::Patience
-                } else {
-                    libpijul::Algorithm::default()
-                },
+                algorithm,
                 channel.clone(),
                 changes,
                 &mut state,
@@ -408,6 +457,37 @@ impl Record {
         debug!("has_binary = {:?}", rec.has_binary_files);
         let mut change = if self.all {
             change
+        } else if self.interactive {
+            let (hunks, mut updatables) = select_hunks(
+                changes,
+                &mut change,
+                std::mem::take(&mut rec.updatables),
+                "Record",
+            )?;
+            if hunks.is_empty() {
+                bail!("No hunk selected")
+            }
+            let mut header = change.hashed.header.clone();
+            if header.message.trim().is_empty() {
+                header.message = Input::new()?.with_prompt("Change message").interact()?;
+            }
+            let contents = std::mem::take(&mut change.contents);
+            let change =
+                LocalChange::make_change(&*txn_, &channel, hunks, contents, header, Vec::new())?;
+            // Go through the text format, as when editing the change,
+            // in order to drop the contents of the hunks that weren't
+            // selected, and update the positions of the new inodes.
+            let mut o = Vec::new();
+            change.write(changes, None, true, &mut o)?;
+            let change = if let Ok(change) =
+                Change::read_and_deps(&mut &o[..], &mut updatables, &*txn_, &channel)
+            {
+                change
+            } else {
+                bail!("The selected hunks depend on hunks that were not selected")
+            };
+            rec.updatables = updatables;
+            change
         } else {
             let mut o = Vec::new();
             debug!("write change");
@@ -466,3 +546,93 @@ const SYNTAX_ERROR: &str = "# Syntax errors, please try again.
 # Alternatively, you may delete the entire file (including this
 # comment) to abort.
 ";
+
+type GlobalHunk = libpijul::change::Hunk<Option<libpijul::Hash>, libpijul::change::Local>;
+
+/// Ask the user which hunks of `change` to keep, like `git add -p`,
+/// with prompts starting with `verb`. Returns the selected hunks, and
+/// the updates of the inodes they add or delete, renumbered
+/// accordingly.
+pub(crate) fn select_hunks<C: libpijul::changestore::ChangeStore>(
+    changes: &C,
+    change: &mut libpijul::change::Change,
+    mut updatables: libpijul::HashMap<usize, libpijul::InodeUpdate>,
+    verb: &str,
+) -> Result<
+    (
+        Vec<GlobalHunk>,
+        libpijul::HashMap<usize, libpijul::InodeUpdate>,
+    ),
+    anyhow::Error,
+> {
+    use libpijul::change::{join_lines, split_lines};
+    use pijul_interaction::Input;
+    use std::io::Write;
+    let mut stdout = std::io::stdout();
+    let mut stderr = std::io::stderr();
+    let n = change.changes.len();
+    // For each hunk of the change, the hunks offered to the user
+    // (the hunk itself or its parts, if it was split), and whether
+    // they were selected.
+    let mut offered: Vec<Vec<(GlobalHunk, bool)>> = vec![Vec::new(); n];
+    let mut split = vec![false; n];
+    let mut queue: std::collections::VecDeque<_> =
+        change.changes.iter().cloned().enumerate().collect();
+    'outer: while let Some((i, hunk)) = queue.pop_front() {
+        writeln!(stdout)?;
+        change.write_hunk(changes, &hunk, &mut stdout)?;
+        writeln!(stdout)?;
+        loop {
+            let answer = Input::new()?
+                .with_prompt(format!("({}/{}) {} this hunk [y,n,s,q,?]", i + 1, n, verb))
+                .interact()?;
+            match answer.trim() {
+                "y" => {
+                    offered[i].push((hunk, true));
+                    break;
+                }
+                "n" => {
+                    offered[i].push((hunk, false));
+                    break;
+                }
+                "s" => {
+                    if let Some(parts) = split_lines(changes, &hunk, &change.contents)? {
+                        split[i] = true;
+                        for part in parts.into_iter().rev() {
+                            queue.push_front((i, part))
+                        }
+                        break;
+                    }
+                    writeln!(stderr, "This hunk cannot be split")?
+                }
+                "q" => break 'outer,
+                _ => {
+                    let verb = verb.to_lowercase();
+                    writeln!(
+                        stderr,
+                        "y - {verb} this hunk\n\
+                         n - do not {verb} this hunk\n\
+                         s - split this hunk into lines\n\
+                         q - quit, keeping only the hunks selected so far"
+                    )?
+                }
+            }
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let mut new_updatables = libpijul::HashMap::default();
+    for (i, offered) in offered.into_iter().enumerate() {
+        if split[i] {
+            let (parts, selected): (Vec<_>, Vec<_>) = offered.into_iter().unzip();
+            hunks.extend(join_lines(parts, &selected, &mut change.contents))
+        } else if let Some((hunk, true)) = offered.into_iter().next() {
+            hunks.push(hunk);
+            // Hunks and inode updates are numbered from 1.
+            if let Some(update) = updatables.remove(&(i + 1)) {
+                new_updatables.insert(hunks.len(), update);
+            }
+        }
+    }
+    Ok((hunks, new_updatables))
+}
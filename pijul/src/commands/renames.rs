@@ -0,0 +1,42 @@
+/// Detect the files moved without `pijul mv`, by comparing the
+/// tracked files missing from `working_copy` with the new and
+/// untracked ones, move them in the tree, and tell the user.
+pub(crate) fn detect_renames(
+    repo: &pijul_repository::Repository,
+    working_copy: &libpijul::working_copy::FileSystem,
+    repo_path: &canonical_path::CanonicalPathBuf,
+    txn: &libpijul::ArcTxn<libpijul::pristine::sanakirja::MutTxn<()>>,
+    channel: &libpijul::ChannelRef<libpijul::pristine::sanakirja::MutTxn<()>>,
+    threshold: u8,
+) -> Result<(), anyhow::Error> {
+    use libpijul::TxnTExt;
+    use path_slash::PathExt;
+    use std::io::Write;
+    let mut untracked = Vec::new();
+    for p in working_copy.iterate_prefix_rec(
+        repo_path.clone(),
+        repo_path.clone(),
+        false,
+        std::thread::available_parallelism()?.get(),
+        |_, _| true,
+    )? {
+        let (path, is_dir) = p?;
+        let path = path.to_slash_lossy();
+        if !is_dir && !path.is_empty() && !txn.read().is_tracked(&path)? {
+            untracked.push(path.to_string())
+        }
+    }
+    let renames = libpijul::rename::detect_renames(
+        txn,
+        channel,
+        working_copy,
+        &repo.changes,
+        &untracked,
+        threshold,
+    )?;
+    let mut stderr = std::io::stderr();
+    for r in renames {
+        writeln!(stderr, "Moved {} -> {} ({}%)", r.from, r.to, r.similarity)?;
+    }
+    Ok(())
+}
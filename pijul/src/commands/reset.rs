@@ -128,6 +128,42 @@ impl Reset {
             }
         }
 
+        let switching = self.channel.is_some() && self.channel.as_deref() != Some(&current_channel);
+        let (pre_hooks, post_hooks) = if switching {
+            (
+                &repo.config.hooks.pre_channel_switch,
+                &repo.config.hooks.post_channel_switch,
+            )
+        } else {
+            (&repo.config.hooks.pre_reset, &repo.config.hooks.post_reset)
+        };
+        let mut hook = pijul_config::HookContext {
+            hook: if switching {
+                "pre_channel_switch"
+            } else {
+                "pre_reset"
+            },
+            channel: Some(channel_name.to_string()),
+            from_channel: if switching {
+                Some(current_channel.clone())
+            } else {
+                None
+            },
+            state: super::hooks::hook_state(&*txn.read(), &*channel.read())?,
+            paths: self
+                .files
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect(),
+            ..Default::default()
+        };
+        pijul_config::run_hooks(pre_hooks, &repo.path, &hook)?;
+        hook.hook = if switching {
+            "post_channel_switch"
+        } else {
+            "post_reset"
+        };
+
         let now = std::time::Instant::now();
         let mut conflicts = Vec::new();
         if self.files.is_empty() {
@@ -157,11 +193,13 @@ impl Reset {
                     Some(last_modified),
                     1, // std::thread::available_parallelism()?.get(),
                     0,
+                    super::config::conflict_style(&repo),
                 )?;
                 txn.write().touch_channel(&mut *channel.write(), None);
                 txn.commit()?;
 
                 writeln!(stderr, "Reset repository to last recorded change")?;
+                pijul_config::run_post_hooks(post_hooks, &repo.path, &hook);
                 return Ok(());
             }
             let mut inodes = HashSet::new();
@@ -218,6 +256,7 @@ impl Reset {
                         None,
                         std::thread::available_parallelism()?.get(),
                         0,
+                        super::config::conflict_style(&repo),
                     )?
                     .into_iter(),
                 );
@@ -242,6 +281,7 @@ impl Reset {
                         None,
                         std::thread::available_parallelism()?.get(),
                         0,
+                        super::config::conflict_style(&repo),
                     )?
                     .into_iter(),
                 );
@@ -257,6 +297,7 @@ impl Reset {
         );
 
         writeln!(stderr, "Reset given paths to last recorded change")?;
+        pijul_config::run_post_hooks(post_hooks, &repo.path, &hook);
 
         Ok(())
     }
@@ -296,12 +337,14 @@ fn last_modified<T: ChannelTxnT>(txn: &T, channel: &T::Channel) -> std::time::Sy
     std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(txn.last_modified(channel))
 }
 
-fn has_unrecorded_changes(
+pub(super) fn has_unrecorded_changes(
     txn: ArcTxn<MutTxn<()>>,
     channel: ChannelRef<MutTxn<()>>,
     repo: &Repository,
 ) -> Result<bool, anyhow::Error> {
     let mut state = libpijul::RecordBuilder::new();
+    state.separators = super::config::diff_separators(repo)?;
+    state.conflict_style = super::config::conflict_style(repo);
     state.record(
         txn,
         libpijul::Algorithm::default(),
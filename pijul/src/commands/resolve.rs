@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use clap::{Parser, ValueHint};
+use libpijul::vertex_buffer::SidesWriter;
+use libpijul::{Conflict, TxnT};
+use log::debug;
+
+use pijul_repository::Repository;
+
+#[derive(Parser, Debug)]
+pub struct Resolve {
+    /// Set the repository where this command should run. Defaults to the first ancestor of the current directory that contains a `.pijul` directory.
+    #[clap(long = "repository", value_hint = ValueHint::DirPath)]
+    repo_path: Option<PathBuf>,
+    /// Merge tool to use: "meld", "kdiff3", "vimdiff", or a command run by the shell with the files in $BASE, $LOCAL, $REMOTE and $MERGED. Defaults to `merge_tool` in the configuration.
+    #[clap(long = "tool")]
+    tool: Option<String>,
+    /// Record the resolution once all the files have been merged
+    #[clap(long = "record")]
+    record: bool,
+    /// Set the message of the recorded resolution
+    #[clap(short = 'm', long = "message", requires = "record")]
+    message: Option<String>,
+    /// Identity to sign the recorded resolution with
+    #[clap(long = "identity", requires = "record")]
+    identity: Option<String>,
+    /// Only resolve the conflicts in these paths (files or directories)
+    #[clap(value_hint = ValueHint::AnyPath)]
+    paths: Vec<PathBuf>,
+}
+
+impl Resolve {
+    pub async fn run(self) -> Result<(), anyhow::Error> {
+        let has_repo_path = self.repo_path.is_some();
+        let repo = Repository::find_root(self.repo_path.clone())?;
+        let tool = if let Some(ref tool) = self.tool {
+            tool.clone()
+        } else if let Some(ref tool) = repo.config.merge_tool {
+            tool.clone()
+        } else if let Some(tool) = pijul_config::Global::load()
+            .ok()
+            .and_then(|(global, _)| global.merge_tool)
+        {
+            tool
+        } else {
+            bail!("No merge tool configured, use --tool or set `merge_tool` in the configuration")
+        };
+        let txn = repo.pristine.arc_txn_begin()?;
+        let channel_name = txn
+            .read()
+            .current_channel()
+            .unwrap_or(libpijul::DEFAULT_CHANNEL)
+            .to_string();
+        let channel = if let Some(channel) = txn.read().load_channel(&channel_name)? {
+            channel
+        } else {
+            bail!("No such channel: {:?}", channel_name)
+        };
+
+        // Only conflicts inside files can be merged, other conflicts
+        // are resolved by moving or deleting files.
+        let mut files = BTreeMap::new();
+        let mut stderr = std::io::stderr();
+        for c in
+            super::conflicts::channel_conflicts(&repo, &txn, &channel, &self.paths, has_repo_path)?
+        {
+            match c {
+                Conflict::Order {
+                    path, inode_vertex, ..
+                }
+                | Conflict::Zombie {
+                    path, inode_vertex, ..
+                }
+                | Conflict::Cyclic {
+                    path, inode_vertex, ..
+                } => {
+                    files.insert(path, inode_vertex[0]);
+                }
+                c => writeln!(
+                    stderr,
+                    "Skipping {}",
+                    super::conflicts::describe_conflict(&c)
+                )?,
+            }
+        }
+        if files.is_empty() {
+            writeln!(stderr, "No conflicts to resolve")?;
+            return Ok(());
+        }
+
+        let mut resolved = Vec::new();
+        for (path, pos) in files {
+            let mut sides = SidesWriter::new();
+            libpijul::output::output_file(&repo.changes, &txn, &channel, pos, &mut sides)?;
+            if sides.conflicts == 0 {
+                continue;
+            }
+            let full_path = repo.path.join(&path);
+            if merge_file(&tool, &full_path, &sides)? {
+                writeln!(stderr, "Resolved {}", path)?;
+                resolved.push(full_path)
+            } else {
+                writeln!(
+                    stderr,
+                    "Merge tool failed on {}, leaving it unchanged",
+                    path
+                )?;
+            }
+        }
+        // Recording needs its own transaction.
+        std::mem::drop(channel);
+        std::mem::drop(txn);
+        std::mem::drop(repo);
+
+        if self.record && !resolved.is_empty() {
+            super::Record {
+                all: true,
+                interactive: false,
+                message: Some(
+                    self.message
+                        .unwrap_or_else(|| "Resolve conflicts".to_string()),
+                ),
+                description: None,
+                author: None,
+                channel: None,
+                repo_path: self.repo_path,
+                timestamp: None,
+                ignore_missing: false,
+                working_copy: None,
+                amend: None,
+                prefixes: resolved,
+                identity: self.identity,
+                patience: false,
+                histogram: false,
+                detect_renames: None,
+            }
+            .run()
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the sides of the conflicts of `path` to temporary files, run
+/// the merge tool on them, and copy the merged file back to `path`
+/// if the tool succeeds. The merged file initially contains the
+/// current version of `path`, with its conflict markers.
+fn merge_file(tool: &str, path: &Path, sides: &SidesWriter) -> Result<bool, anyhow::Error> {
+    let dir = tempfile::tempdir()?;
+    // Keep the extension, for syntax highlighting in the merge tool.
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = if let Some(ext) = path.extension() {
+        format!(".{}", ext.to_string_lossy())
+    } else {
+        String::new()
+    };
+    let temp = |side: &str| dir.path().join(format!("{}.{}{}", stem, side, ext));
+    let (base, local, remote, merged) =
+        (temp("BASE"), temp("LOCAL"), temp("REMOTE"), temp("MERGED"));
+    std::fs::write(&base, &sides.base)?;
+    std::fs::write(&local, &sides.local)?;
+    std::fs::write(&remote, &sides.remote)?;
+    std::fs::copy(path, &merged)?;
+
+    let cmd = pijul_config::merge_tool_command(tool);
+    debug!("merge tool: {:?}", cmd);
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", cmd]);
+        command
+    } else {
+        let mut command =
+            std::process::Command::new(std::env::var("SHELL").unwrap_or("sh".to_string()));
+        command.arg("-c").arg(cmd);
+        command
+    };
+    let status = command
+        .env("BASE", &base)
+        .env("LOCAL", &local)
+        .env("REMOTE", &remote)
+        .env("MERGED", &merged)
+        .status()?;
+    if !status.success() {
+        return Ok(false);
+    }
+    let result = std::fs::read(&merged)?;
+    if super::conflicts::has_conflict_markers(&result) {
+        writeln!(
+            std::io::stderr(),
+            "Warning: {} still contains conflict markers",
+            path.display()
+        )?;
+    }
+    std::fs::write(path, result)?;
+    Ok(true)
+}
@@ -0,0 +1,195 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use canonical_path::CanonicalPathBuf;
+use clap::{Parser, ValueHint};
+use libpijul::change::*;
+use libpijul::changestore::*;
+use libpijul::*;
+use log::debug;
+
+use pijul_repository::Repository;
+
+#[derive(Parser, Debug)]
+pub struct Revert {
+    /// Set the repository where this command should run. Defaults to the first ancestor of the current directory that contains a `.pijul` directory.
+    #[clap(long = "repository", value_hint = ValueHint::DirPath)]
+    repo_path: Option<PathBuf>,
+    /// Revert the change in this channel instead of the current channel
+    #[clap(long = "channel")]
+    channel: Option<String>,
+    /// Set the change message. Defaults to "Revert" followed by the message of the reverted change.
+    #[clap(short = 'm', long = "message")]
+    message: Option<String>,
+    /// Identity to sign the change with
+    #[clap(long = "identity")]
+    identity: Option<String>,
+    /// Choose the hunks to revert one by one in the terminal
+    #[clap(short = 'i', long = "interactive")]
+    interactive: bool,
+    /// The hash of the change to revert (unambiguous prefixes are accepted)
+    change: String,
+    /// Only revert the hunks touching these paths
+    #[clap(value_hint = ValueHint::AnyPath)]
+    paths: Vec<PathBuf>,
+}
+
+impl Revert {
+    pub async fn run(self) -> Result<(), anyhow::Error> {
+        let has_repo_path = self.repo_path.is_some();
+        let mut repo = Repository::find_root(self.repo_path)?;
+        // A revert records a new change.
+        for h in repo.config.hooks.record.iter() {
+            h.run(repo.path.clone())?
+        }
+        let txn = repo.pristine.arc_txn_begin()?;
+        let cur = txn
+            .read()
+            .current_channel()
+            .unwrap_or(libpijul::DEFAULT_CHANNEL)
+            .to_string();
+        let channel_name = if let Some(ref c) = self.channel {
+            c
+        } else {
+            cur.as_str()
+        };
+        let is_current_channel = cur == channel_name;
+        let channel = if let Some(channel) = txn.read().load_channel(channel_name)? {
+            channel
+        } else {
+            bail!("No such channel: {:?}", channel_name);
+        };
+        super::protected::check_channel_permission(
+            &repo,
+            channel_name,
+            pijul_config::ChannelOperation::Push,
+        )?;
+        let (hash, change_id) = txn.read().hash_from_prefix(&self.change)?;
+        {
+            let txn = txn.read();
+            if txn
+                .get_changeset(txn.changes(&*channel.read()), &change_id)?
+                .is_none()
+            {
+                bail!("Change not in channel: {}", hash.to_base32())
+            }
+        }
+
+        let repo_path = CanonicalPathBuf::canonicalize(&repo.path)?;
+        let mut prefixes = Vec::new();
+        for p in self.paths.iter() {
+            // The paths may have been deleted by the change, don't
+            // canonicalize them.
+            let p = if has_repo_path {
+                repo_path.as_path().join(p)
+            } else {
+                std::env::current_dir()?.join(p)
+            };
+            use path_slash::PathExt;
+            prefixes.push(p.strip_prefix(&repo_path)?.to_slash_lossy().to_string());
+        }
+
+        let reverted = repo.changes.get_change(&hash)?;
+        let mut inverse = reverted.inverse(&hash, ChangeHeader::default(), Vec::new());
+        inverse.changes.retain(|hunk| {
+            prefixes.is_empty()
+                || prefixes.iter().any(|p| {
+                    p.is_empty()
+                        || hunk.path() == p
+                        || (hunk.path().starts_with(p.as_str())
+                            && hunk.path().as_bytes().get(p.len()) == Some(&b'/'))
+                })
+        });
+        let hunks = if self.interactive {
+            super::record::select_hunks(&repo.changes, &mut inverse, HashMap::default(), "Revert")?
+                .0
+        } else {
+            std::mem::take(&mut inverse.changes)
+        };
+        if hunks.is_empty() {
+            bail!("Nothing to revert")
+        }
+
+        let identity_name = if let Some(ref identity) = self.identity {
+            identity.clone()
+        } else {
+            pijul_identity::choose_identity_name().await?
+        };
+        let mut author = std::collections::BTreeMap::new();
+        author.insert(
+            "key".to_string(),
+            pijul_identity::public_key(&identity_name)?.key,
+        );
+        let message = if let Some(message) = self.message {
+            message
+        } else {
+            format!(
+                "Revert \"{}\"",
+                reverted.header.message.lines().next().unwrap_or("")
+            )
+        };
+        let header = ChangeHeader {
+            message,
+            authors: vec![Author(author)],
+            description: Some(format!("This reverts change {}.", hash.to_base32())),
+            timestamp: chrono::Utc::now(),
+        };
+        let (secret, _) = pijul_identity::Complete::load(&identity_name)?.decrypt()?;
+
+        let mut change = {
+            let txn = txn.read();
+            LocalChange::make_change(&*txn, &channel, hunks, Vec::new(), header, Vec::new())?
+        };
+        // Preserve the unrecorded changes, as in `pijul unrecord --reset`.
+        let pending_hash = if is_current_channel {
+            super::pending(txn.clone(), &channel, &mut repo)?
+        } else {
+            None
+        };
+        let revert_hash = repo.changes.save_change(&mut change, |change, hash| {
+            change.unhashed = Some(serde_json::json!({
+                "signature": secret.sign_raw(&hash.to_bytes()).unwrap(),
+            }));
+            Ok::<_, anyhow::Error>(())
+        })?;
+        debug!("revert_hash = {:?}", revert_hash);
+        txn.write()
+            .apply_change(&repo.changes, &mut *channel.write(), &revert_hash)?;
+
+        if is_current_channel {
+            let conflicts = libpijul::output::output_repository_no_pending(
+                &repo.working_copy,
+                &repo.changes,
+                &txn,
+                &channel,
+                "",
+                true,
+                None,
+                std::thread::available_parallelism()?.get(),
+                0,
+                super::config::conflict_style(&repo),
+            )?
+            .into_iter()
+            .collect::<Vec<_>>();
+            super::print_conflicts(&conflicts)?;
+        }
+        if let Some(h) = pending_hash {
+            txn.write().unrecord(&repo.changes, &channel, &h, 0)?;
+            if !cfg!(feature = "keep-changes") {
+                repo.changes.del_change(&h)?;
+            }
+        }
+        let hook = pijul_config::HookContext {
+            hook: "post_record",
+            channel: Some(channel_name.to_string()),
+            changes: vec![revert_hash.to_base32()],
+            state: super::hooks::hook_state(&*txn.read(), &*channel.read())?,
+            ..Default::default()
+        };
+        txn.commit()?;
+        pijul_config::run_post_hooks(&repo.config.hooks.post_record, &repo.path, &hook);
+        writeln!(std::io::stdout(), "Hash: {}", revert_hash.to_base32())?;
+        Ok(())
+    }
+}
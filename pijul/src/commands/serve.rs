@@ -0,0 +1,433 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::bail;
+use byteorder::{BigEndian, WriteBytesExt};
+use clap::{Parser, ValueHint};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use libpijul::{Base32, Hash, Merkle, TxnT, DOT_DIR};
+use log::debug;
+use pijul_config::ChannelOperation;
+use pijul_repository::Repository;
+
+use super::protocol;
+
+#[derive(Parser, Debug)]
+pub struct Serve {
+    /// Repositories to serve, each one under the name of its root
+    /// directory. Defaults to the first ancestor of the current
+    /// directory that contains a `.pijul` directory. When a single
+    /// repository is served, it is also available at the root URL.
+    #[clap(value_hint = ValueHint::DirPath)]
+    repositories: Vec<PathBuf>,
+    /// Listen on this address
+    #[clap(long = "address", default_value = "127.0.0.1:8080")]
+    address: SocketAddr,
+    /// Accept changes and tags pushed by clients. Repositories are read-only otherwise.
+    #[clap(long = "allow-push")]
+    allow_push: bool,
+    /// Only accept pushes sent with an `Authorization: Bearer <token>`
+    /// header, where `<token>` is one of the lines of this file
+    #[clap(long = "tokens", requires = "allow_push", value_hint = ValueHint::FilePath)]
+    tokens: Option<PathBuf>,
+}
+
+/// Maximal number of challenges waiting for a proof.
+const MAX_CHALLENGES: usize = 1024;
+
+struct State {
+    repos: HashMap<String, Arc<Mutex<Repository>>>,
+    allow_push: bool,
+    tokens: Option<HashSet<String>>,
+    /// Challenges sent to clients, as pairs of a public key and the
+    /// string to be signed by the corresponding secret key.
+    challenges: Mutex<Vec<(String, String)>>,
+}
+
+/// A request to the server, once its repository has been found.
+struct Query {
+    method: Method,
+    args: Vec<(String, String)>,
+    authorization: Option<String>,
+    body: hyper::body::Bytes,
+}
+
+impl Query {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.args
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Archives are requested either with a state in `archive`, or
+    /// with only a channel (and archive options), for the current
+    /// state of the channel.
+    fn is_archive(&self) -> bool {
+        self.get("archive").is_some()
+            || (self.get("channel").is_some()
+                && self
+                    .args
+                    .iter()
+                    .all(|(k, _)| ["channel", "format", "outputPrefix"].contains(&k.as_str())))
+    }
+
+    /// The channel named in the request, or else the current channel
+    /// of the served repository.
+    fn channel<'a, T: TxnT>(&'a self, txn: &'a T) -> &'a str {
+        self.get("to_channel")
+            .or_else(|| self.get("channel"))
+            .or_else(|| txn.current_channel().ok())
+            .unwrap_or(libpijul::DEFAULT_CHANNEL)
+    }
+}
+
+impl Serve {
+    pub async fn run(self) -> Result<(), anyhow::Error> {
+        let mut repos = HashMap::new();
+        if self.repositories.is_empty() {
+            let repo = Repository::find_root(None)?;
+            repos.insert(String::new(), Arc::new(Mutex::new(repo)));
+        } else {
+            for path in self.repositories.iter() {
+                let repo = Repository::find_root(Some(path.clone()))?;
+                let name = if let Some(name) = repo.path.file_name() {
+                    name.to_string_lossy().to_string()
+                } else {
+                    bail!("Could not infer repository name from {:?}", path)
+                };
+                if repos
+                    .insert(name.clone(), Arc::new(Mutex::new(repo)))
+                    .is_some()
+                {
+                    bail!("Two repositories are named {:?}", name)
+                }
+            }
+            if repos.len() == 1 {
+                let repo = repos.values().next().unwrap().clone();
+                repos.insert(String::new(), repo);
+            }
+        }
+        let tokens = if let Some(ref tokens) = self.tokens {
+            Some(
+                std::fs::read_to_string(tokens)?
+                    .lines()
+                    .map(|l| l.trim())
+                    .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                    .map(|l| l.to_string())
+                    .collect(),
+            )
+        } else {
+            None
+        };
+        let state = Arc::new(State {
+            repos,
+            allow_push: self.allow_push,
+            tokens,
+            challenges: Mutex::new(Vec::new()),
+        });
+
+        let make_service = make_service_fn(move |_conn| {
+            let state = state.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req))) }
+        });
+        let server = Server::try_bind(&self.address)?.serve(make_service);
+        eprintln!("Listening on http://{}", server.local_addr());
+        server.await?;
+        Ok(())
+    }
+}
+
+async fn handle(state: Arc<State>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    debug!("serve {:?} {:?}", req.method(), req.uri());
+    let path = req.uri().path().trim_end_matches('/');
+    let name = path
+        .strip_suffix(DOT_DIR)
+        .unwrap_or(path)
+        .trim_matches('/')
+        .to_string();
+    let repo = if let Some(repo) = state.repos.get(&name) {
+        repo.clone()
+    } else {
+        return Ok(remote_error(
+            StatusCode::NOT_FOUND,
+            &libpijul::RemoteError::RepositoryNotFound { url: name },
+        ));
+    };
+    let args = url::form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes())
+        .into_owned()
+        .collect();
+    let method = req.method().clone();
+    let authorization = req
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .map(|x| x.to_string());
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(body) => body,
+        Err(e) => return Ok(error(StatusCode::BAD_REQUEST, &e)),
+    };
+    let query = Query {
+        method,
+        args,
+        authorization,
+        body,
+    };
+    let resp = tokio::task::spawn_blocking(move || {
+        let repo = repo.lock().unwrap();
+        state.respond(&repo, query)
+    })
+    .await;
+    match resp {
+        Ok(Ok(resp)) => Ok(resp),
+        Ok(Err(e)) => {
+            debug!("serve error {:?}", e);
+            Ok(error(StatusCode::INTERNAL_SERVER_ERROR, &e))
+        }
+        Err(e) => Ok(error(StatusCode::INTERNAL_SERVER_ERROR, &e)),
+    }
+}
+
+fn error<E: std::fmt::Display + ?Sized>(status: StatusCode, e: &E) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(e.to_string()))
+        .unwrap()
+}
+
+fn remote_error(status: StatusCode, e: &libpijul::RemoteError) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::to_vec(e).unwrap()))
+        .unwrap()
+}
+
+fn ok<B: Into<Body>>(body: B) -> Result<Response<Body>, anyhow::Error> {
+    Ok(Response::builder().body(body.into())?)
+}
+
+impl State {
+    fn respond(&self, repo: &Repository, q: Query) -> Result<Response<Body>, anyhow::Error> {
+        if q.method == Method::POST {
+            if let Some(resp) = self.check_push(&q) {
+                return Ok(resp);
+            }
+            let txn = repo.pristine.arc_txn_begin()?;
+            let channel = q.channel(&*txn.read()).to_string();
+            let channel = channel.as_str();
+            if let Some(h) = q.get("apply") {
+                let h = if let Some(h) = Hash::from_base32(h.as_bytes()) {
+                    h
+                } else {
+                    bail!("Invalid hash: {:?}", h)
+                };
+                // HTTP requests are not authenticated by identities,
+                // protected channels can only be written to over SSH.
+                if let Err(e) =
+                    protocol::check_permission(repo, channel, ChannelOperation::Push, None)
+                {
+                    return Ok(error(StatusCode::FORBIDDEN, &e));
+                }
+                let mut ws = libpijul::ApplyWorkspace::new();
+                let name = channel;
+                let mut written = Vec::new();
+                let channel =
+                    protocol::apply(repo, &txn, &mut ws, name, &h, &q.body, &mut written)?;
+                let mut hook = protocol::apply_hook_context(&txn, name, &channel, &[h])?;
+                if let Err(e) =
+                    pijul_config::run_hooks(&repo.config.hooks.pre_apply, &repo.path, &hook)
+                {
+                    protocol::discard(&written);
+                    return Ok(error(StatusCode::FORBIDDEN, &e));
+                }
+                protocol::output_applied(repo, &txn, std::iter::once(channel))?;
+                txn.commit()?;
+                hook.hook = "post_apply";
+                pijul_config::run_post_hooks(&repo.config.hooks.post_apply, &repo.path, &hook);
+                return ok(Body::empty());
+            } else if let Some(m) = q.get("tagup") {
+                let m = if let Some(m) = Merkle::from_base32(m.as_bytes()) {
+                    m
+                } else {
+                    bail!("Invalid state: {:?}", m)
+                };
+                if let Err(e) =
+                    protocol::check_permission(repo, channel, ChannelOperation::Tag, None)
+                {
+                    return Ok(error(StatusCode::FORBIDDEN, &e));
+                }
+                protocol::tagup(repo, &txn, channel, &m, &q.body)?;
+                txn.commit()?;
+                return ok(Body::empty());
+            }
+            return Ok(error(StatusCode::BAD_REQUEST, "Unknown request"));
+        } else if q.method != Method::GET {
+            return Ok(error(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed"));
+        }
+
+        if let Some(challenge) = q.get("challenge") {
+            return self.challenge(challenge);
+        } else if let Some(sig) = q.get("prove") {
+            return self.prove(repo, sig);
+        } else if let Some(last_touched) = q.get("identities") {
+            let ids = protocol::identities(repo, last_touched.parse().unwrap_or(0))?;
+            let resp = ids.map(|ids| {
+                serde_json::json!({
+                    "rev": ids.iter().map(|(t, _)| *t).max().unwrap_or(0),
+                    "id": ids.into_iter().map(|(_, id)| id).collect::<Vec<_>>(),
+                })
+            });
+            return ok(serde_json::to_vec(&resp)?);
+        } else if let Some(m) = q.get("tag") {
+            let m = if let Some(m) = Merkle::from_base32(m.as_bytes()) {
+                m
+            } else {
+                bail!("Invalid state: {:?}", m)
+            };
+            return ok(protocol::short_tag(repo, &m)?);
+        } else if let (Some(h), false) = (q.get("change"), q.is_archive()) {
+            let h = if let Some(h) = Hash::from_base32(h.as_bytes()) {
+                h
+            } else {
+                bail!("Invalid hash: {:?}", h)
+            };
+            let (f, size) = match protocol::open_change(repo, &h, false) {
+                Ok(x) => x,
+                Err(_) => {
+                    return Ok(remote_error(
+                        StatusCode::NOT_FOUND,
+                        &libpijul::RemoteError::ChangeNotFound {
+                            change: h.to_base32(),
+                        },
+                    ))
+                }
+            };
+            let mut o = Vec::with_capacity(size as usize);
+            f.take(size).read_to_end(&mut o)?;
+            return ok(o);
+        }
+
+        let txn = repo.pristine.txn_begin()?;
+        let channel = q.channel(&txn).to_string();
+        let channel = channel.as_str();
+        if txn.load_channel(channel)?.is_none() {
+            return Ok(remote_error(
+                StatusCode::NOT_FOUND,
+                &libpijul::RemoteError::ChannelNotFound {
+                    channel: channel.to_string(),
+                    url: repo.path.to_string_lossy().to_string(),
+                },
+            ));
+        }
+        if let Some(from) = q.get("changelist") {
+            let from = from.parse()?;
+            let paths: Vec<String> = q.get_all("path").map(|x| x.to_string()).collect();
+            let mut o = Vec::new();
+            protocol::write_changelist(repo, &txn, channel, from, &paths, &mut o)?;
+            ok(o)
+        } else if let Some(init) = q.get("state") {
+            let mut o = Vec::new();
+            protocol::write_state(&txn, channel, init.parse().ok(), &mut o)?;
+            ok(o)
+        } else if q.get("id").is_some() {
+            let id = protocol::channel_id(&txn, channel)?;
+            ok(id.as_bytes().to_vec())
+        } else if q.is_archive() {
+            let state = if let Some(state) = q.get("archive") {
+                let extra = q
+                    .get_all("change")
+                    .map(|x| x.parse())
+                    .collect::<Result<Vec<Hash>, _>>()?;
+                Some((state.parse()?, extra))
+            } else {
+                None
+            };
+            let format = if let Some(f) = q.get("format") {
+                f.parse().map_err(|e: String| anyhow::anyhow!(e))?
+            } else {
+                libpijul::output::ArchiveFormat::TarGz
+            };
+            let prefix = q.get("outputPrefix").map(|x| x.to_string());
+            // Zip archives are written with absolute offsets, so the
+            // archive is written separately from the header.
+            let mut w = std::io::Cursor::new(Vec::new());
+            // Archiving a past state forks the channel, in a
+            // transaction that is never committed.
+            std::mem::drop(txn);
+            let txn = repo.pristine.arc_txn_begin()?;
+            let conflicts = protocol::archive(repo, &txn, channel, state, format, prefix, &mut w)?;
+            let mut o = Vec::with_capacity(8 + w.get_ref().len());
+            o.write_u64::<BigEndian>(conflicts as u64)?;
+            o.extend_from_slice(w.get_ref());
+            ok(o)
+        } else {
+            Ok(error(StatusCode::BAD_REQUEST, "Unknown request"))
+        }
+    }
+
+    /// Check that pushes are allowed, and that the request carries
+    /// one of the accepted tokens, returning an error response if not.
+    fn check_push(&self, q: &Query) -> Option<Response<Body>> {
+        if !self.allow_push {
+            return Some(error(StatusCode::FORBIDDEN, "This repository is read-only"));
+        }
+        if let Some(ref tokens) = self.tokens {
+            let token = q
+                .authorization
+                .as_deref()
+                .and_then(|x| x.strip_prefix("Bearer "))
+                .map(|x| x.trim());
+            if !token.map(|t| tokens.contains(t)).unwrap_or(false) {
+                return Some(error(StatusCode::UNAUTHORIZED, "Invalid or missing token"));
+            }
+        }
+        None
+    }
+
+    fn challenge(&self, key: &str) -> Result<Response<Body>, anyhow::Error> {
+        let challenge = protocol::new_challenge();
+        let mut challenges = self.challenges.lock().unwrap();
+        if challenges.len() >= MAX_CHALLENGES {
+            challenges.remove(0);
+        }
+        challenges.push((key.to_string(), challenge.clone()));
+        ok(challenge)
+    }
+
+    /// Check that `sig` is a signature of one of the pending
+    /// challenges, by a key known to this repository.
+    fn prove(&self, repo: &Repository, sig: &str) -> Result<Response<Body>, anyhow::Error> {
+        let ids = protocol::identities(repo, 0)?.unwrap_or_default();
+        let mut challenges = self.challenges.lock().unwrap();
+        let now = chrono::Utc::now();
+        let proven = challenges.iter().position(|(key, challenge)| {
+            ids.iter().any(|(_, id)| {
+                &id.public_key.key == key
+                    && id
+                        .public_key
+                        .load()
+                        .and_then(|k| k.verify(challenge.as_bytes(), sig, &now))
+                        .is_ok()
+            })
+        });
+        if let Some(i) = proven {
+            challenges.remove(i);
+            ok(Body::empty())
+        } else {
+            Ok(error(StatusCode::FORBIDDEN, "Invalid proof"))
+        }
+    }
+}
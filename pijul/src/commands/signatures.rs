@@ -0,0 +1,70 @@
+use anyhow::bail;
+
+/// Check the signatures of `changes` against the identities known to
+/// `repo`, according to the `require_signatures` setting of the
+/// repository, or of the global configuration if the repository
+/// doesn't set it. Failures are reported, and are errors if that
+/// setting is `"deny"`. Root changes are never signed, and are
+/// skipped.
+pub(crate) fn check_signatures<'a, I: IntoIterator<Item = &'a libpijul::Hash>>(
+    repo: &pijul_repository::Repository,
+    changes: I,
+) -> Result<(), anyhow::Error> {
+    use libpijul::changestore::ChangeStore;
+    use libpijul::Base32;
+    use pijul_config::RequireSignatures;
+    use std::io::Write;
+
+    let require = if let Some(r) = repo.config.require_signatures {
+        r
+    } else if let Some(r) = pijul_config::Global::load()
+        .ok()
+        .and_then(|(cfg, _)| cfg.require_signatures)
+    {
+        r
+    } else {
+        return Ok(());
+    };
+    let mut verifier = pijul_identity::Verifier::new(&repo.path);
+    let mut stderr = std::io::stderr();
+    let mut failed = 0;
+    for h in changes {
+        let change = repo.changes.get_change(h)?;
+        if is_root_change(&change) {
+            continue;
+        }
+        let sig = verifier.verify(&change, h);
+        if !sig.is_valid() {
+            failed += 1;
+            writeln!(stderr, "Change {}: {}", h.to_base32(), sig.status)?;
+        }
+    }
+    if failed > 0 && require == RequireSignatures::Deny {
+        bail!("{} change(s) failed signature verification", failed)
+    }
+    Ok(())
+}
+
+/// Whether `change` is a root change, which is never signed.
+pub(crate) fn is_root_change(change: &libpijul::change::Change) -> bool {
+    change
+        .changes
+        .iter()
+        .all(|c| matches!(c, libpijul::change::Hunk::AddRoot { .. }))
+}
+
+/// Displays a change signature as its status, followed by the identity
+/// of the signer, or its key if that identity is unknown.
+pub(crate) struct SignatureDisplay<'a>(pub(crate) &'a pijul_identity::ChangeSignature);
+
+impl<'a> std::fmt::Display for SignatureDisplay<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0.status)?;
+        if let Some(ref identity) = self.0.identity {
+            write!(f, " ({})", identity)?;
+        } else if let Some(ref key) = self.0.key {
+            write!(f, " ({})", key)?;
+        }
+        Ok(())
+    }
+}
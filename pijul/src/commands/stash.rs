@@ -0,0 +1,284 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use clap::{Parser, ValueHint};
+use libpijul::changestore::ChangeStore;
+use libpijul::working_copy::WorkingCopy;
+use libpijul::*;
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+
+use pijul_repository::Repository;
+
+#[derive(Parser, Debug)]
+pub struct Stash {
+    /// Set the repository where this command should run. Defaults to the first ancestor of the current directory that contains a `.pijul` directory.
+    #[clap(long = "repository", value_hint = ValueHint::DirPath)]
+    repo_path: Option<PathBuf>,
+    #[clap(subcommand)]
+    subcmd: Option<SubCommand>,
+}
+
+#[derive(Parser, Debug)]
+pub enum SubCommand {
+    /// Save the unrecorded changes, and reset the working copy to the
+    /// current channel. This is the default.
+    #[clap(name = "push")]
+    Push {
+        /// Describe the stashed changes
+        #[clap(short = 'm', long = "message")]
+        message: Option<String>,
+    },
+    /// List the stashed changes, most recent first.
+    #[clap(name = "list")]
+    List,
+    /// Show a stashed change (defaults to the most recent one).
+    #[clap(name = "show")]
+    Show { index: Option<usize> },
+    /// Apply a stashed change to the working copy, and remove it from
+    /// the stash. If this creates conflicts, the change is kept in the
+    /// stash and the command fails.
+    #[clap(name = "pop")]
+    Pop { index: Option<usize> },
+    /// Remove a stashed change without applying it.
+    #[clap(name = "drop")]
+    Drop { index: Option<usize> },
+}
+
+/// An entry of the stash, saved in `.pijul/stash`. The change itself
+/// is in the change store.
+#[derive(Serialize, Deserialize)]
+struct StashEntry {
+    hash: String,
+    channel: String,
+    message: String,
+    timestamp: chrono::DateTime<chrono::offset::Utc>,
+}
+
+const STASH_FILE: &str = "stash";
+
+fn stash_path(repo: &Repository) -> PathBuf {
+    repo.path.join(libpijul::DOT_DIR).join(STASH_FILE)
+}
+
+fn load_stash(path: &Path) -> Result<Vec<StashEntry>, anyhow::Error> {
+    match std::fs::read(path) {
+        Ok(s) => Ok(serde_json::from_slice(&s)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save_stash(path: &Path, stash: &[StashEntry]) -> Result<(), anyhow::Error> {
+    if stash.is_empty() {
+        if path.exists() {
+            std::fs::remove_file(path)?
+        }
+        return Ok(());
+    }
+    std::fs::write(path, serde_json::to_vec_pretty(stash)?)?;
+    Ok(())
+}
+
+/// The entry at position `index` (counted from the most recent one),
+/// and its position in the stash file.
+fn get_entry(stash: &[StashEntry], index: Option<usize>) -> Result<usize, anyhow::Error> {
+    let index = index.unwrap_or(0);
+    if index >= stash.len() {
+        if stash.is_empty() {
+            bail!("No stashed changes")
+        }
+        bail!("No stashed change at index {}", index)
+    }
+    Ok(stash.len() - 1 - index)
+}
+
+fn entry_hash(entry: &StashEntry) -> Result<Hash, anyhow::Error> {
+    if let Some(h) = Hash::from_base32(entry.hash.as_bytes()) {
+        Ok(h)
+    } else {
+        bail!("Invalid hash in the stash: {:?}", entry.hash)
+    }
+}
+
+impl Stash {
+    pub fn run(self) -> Result<(), anyhow::Error> {
+        let mut repo = Repository::find_root(self.repo_path)?;
+        let path = stash_path(&repo);
+        let mut stash = load_stash(&path)?;
+        let mut stdout = std::io::stdout();
+        match self.subcmd.unwrap_or(SubCommand::Push { message: None }) {
+            SubCommand::Push { message } => {
+                let txn = repo.pristine.arc_txn_begin()?;
+                let channel_name = txn
+                    .read()
+                    .current_channel()
+                    .unwrap_or(libpijul::DEFAULT_CHANNEL)
+                    .to_string();
+                let channel = if let Some(channel) = txn.read().load_channel(&channel_name)? {
+                    channel
+                } else {
+                    bail!("No such channel: {:?}", channel_name)
+                };
+                let hash = if let Some(hash) = super::pending(txn.clone(), &channel, &mut repo)? {
+                    hash
+                } else {
+                    writeln!(std::io::stderr(), "No unrecorded changes to stash")?;
+                    return Ok(());
+                };
+                txn.write().unrecord(&repo.changes, &channel, &hash, 0)?;
+                libpijul::output::output_repository_no_pending(
+                    &repo.working_copy,
+                    &repo.changes,
+                    &txn,
+                    &channel,
+                    "",
+                    true,
+                    None,
+                    std::thread::available_parallelism()?.get(),
+                    0,
+                    super::config::conflict_style(&repo),
+                )?;
+                // The files added by the stashed change are not in the
+                // channel anymore, and would otherwise be left in the
+                // working copy. Directories are only removed if empty.
+                let change = repo.changes.get_change(&hash)?;
+                let mut added: Vec<_> = change
+                    .changes
+                    .iter()
+                    .filter_map(|h| match h {
+                        libpijul::change::Hunk::FileAdd { path, .. } => Some(path.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                added.sort_unstable();
+                for p in added.iter().rev() {
+                    repo.working_copy.remove_path(p, false)?
+                }
+                stash.push(StashEntry {
+                    hash: hash.to_base32(),
+                    channel: channel_name.clone(),
+                    message: message.unwrap_or_else(|| format!("WIP on {}", channel_name)),
+                    timestamp: chrono::Utc::now(),
+                });
+                save_stash(&path, &stash)?;
+                txn.commit()?;
+                writeln!(stdout, "Stashed: {}", hash.to_base32())?;
+            }
+            SubCommand::List => {
+                for (i, entry) in stash.iter().rev().enumerate() {
+                    writeln!(
+                        stdout,
+                        "{}: {} On {}: {} ({})",
+                        i,
+                        &entry.hash[..8],
+                        entry.channel,
+                        entry.message,
+                        entry.timestamp.to_rfc2822()
+                    )?;
+                }
+            }
+            SubCommand::Show { index } => {
+                let entry = &stash[get_entry(&stash, index)?];
+                let hash = entry_hash(entry)?;
+                let change = repo.changes.get_change(&hash)?;
+                let colors = super::diff::is_colored(repo.config.pager.as_ref());
+                change.write(
+                    &repo.changes,
+                    Some(hash),
+                    true,
+                    super::diff::Colored {
+                        w: termcolor::StandardStream::stdout(termcolor::ColorChoice::Auto),
+                        colors,
+                    },
+                )?;
+            }
+            SubCommand::Drop { index } => {
+                let entry = stash.remove(get_entry(&stash, index)?);
+                if !cfg!(feature = "keep-changes") {
+                    repo.changes.del_change(&entry_hash(&entry)?)?;
+                }
+                save_stash(&path, &stash)?;
+                writeln!(stdout, "Dropped: {}", entry.hash)?;
+            }
+            SubCommand::Pop { index } => {
+                let i = get_entry(&stash, index)?;
+                let hash = entry_hash(&stash[i])?;
+                let conflicts = pop(&mut repo, &hash)?;
+                super::print_conflicts(&conflicts)?;
+                if conflicts.is_empty() {
+                    let entry = stash.remove(i);
+                    if !cfg!(feature = "keep-changes") {
+                        repo.changes.del_change(&hash)?;
+                    }
+                    save_stash(&path, &stash)?;
+                    writeln!(stdout, "Applied and dropped: {}", entry.hash)?;
+                } else {
+                    bail!(
+                        "Applied {} with {} conflict(s), keeping it in the stash",
+                        stash[i].hash,
+                        conflicts.len()
+                    )
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Apply `hash` to the current channel, output it, and unrecord it
+/// (along with the other unrecorded changes of the working copy), so
+/// that the working copy has the stashed changes as unrecorded
+/// changes.
+fn pop(repo: &mut Repository, hash: &Hash) -> Result<Vec<Conflict>, anyhow::Error> {
+    let txn = repo.pristine.arc_txn_begin()?;
+    let channel_name = txn
+        .read()
+        .current_channel()
+        .unwrap_or(libpijul::DEFAULT_CHANNEL)
+        .to_string();
+    let channel = if let Some(channel) = txn.read().load_channel(&channel_name)? {
+        channel
+    } else {
+        bail!("No such channel: {:?}", channel_name)
+    };
+    // If the channel lost some of the dependencies of the stashed
+    // change since it was stashed, applying it would bring them back.
+    for dep in repo.changes.get_dependencies(hash)? {
+        if txn.read().has_change(&channel, &dep)?.is_none() {
+            bail!(
+                "The stashed change depends on {}, which is not in channel {:?} anymore",
+                dep.to_base32(),
+                channel_name
+            )
+        }
+    }
+    let pending_hash = super::pending(txn.clone(), &channel, repo)?;
+    debug!("pending = {:?}", pending_hash);
+    txn.write()
+        .apply_change(&repo.changes, &mut *channel.write(), hash)?;
+    let conflicts = libpijul::output::output_repository_no_pending(
+        &repo.working_copy,
+        &repo.changes,
+        &txn,
+        &channel,
+        "",
+        true,
+        None,
+        std::thread::available_parallelism()?.get(),
+        0,
+        super::config::conflict_style(repo),
+    )?
+    .into_iter()
+    .collect();
+    txn.write().unrecord(&repo.changes, &channel, hash, 0)?;
+    if let Some(h) = pending_hash {
+        txn.write().unrecord(&repo.changes, &channel, &h, 0)?;
+        if !cfg!(feature = "keep-changes") {
+            repo.changes.del_change(&h)?;
+        }
+    }
+    txn.commit()?;
+    Ok(conflicts)
+}
@@ -104,6 +104,11 @@ impl Tag {
                         .to_string()
                 };
                 debug!("channel_name = {:?}", channel_name);
+                super::protected::check_channel_permission(
+                    &repo,
+                    &channel_name,
+                    pijul_config::ChannelOperation::Tag,
+                )?;
                 try_record(&mut repo, txn.clone(), &channel_name)?;
                 let channel = txn.read().load_channel(&channel_name)?.unwrap();
                 let last_t = if let Some(n) = txn.read().reverse_log(&*channel.read(), None)?.next()
@@ -116,6 +121,13 @@ impl Tag {
                 if txn.read().is_tagged(&channel.read().tags, last_t)? {
                     bail!("Current state is already tagged")
                 }
+                let mut hook = pijul_config::HookContext {
+                    hook: "pre_tag",
+                    channel: Some(channel_name.clone()),
+                    state: super::hooks::hook_state(&*txn.read(), &*channel.read())?,
+                    ..Default::default()
+                };
+                pijul_config::run_hooks(&repo.config.hooks.pre_tag, &repo.path, &hook)?;
                 let mut tag_path = repo.changes_dir.clone();
                 std::fs::create_dir_all(&tag_path)?;
 
@@ -134,6 +146,9 @@ impl Tag {
                     .put_tags(&mut channel.write().tags, last_t.into(), &h)?;
                 txn.commit()?;
                 writeln!(stdout, "{}", h.to_base32())?;
+                hook.hook = "post_tag";
+                hook.state = Some(h.to_base32());
+                pijul_config::run_post_hooks(&repo.config.hooks.post_tag, &repo.path, &hook);
             }
             Some(SubCommand::Checkout {
                 repo_path,
@@ -192,6 +207,7 @@ impl Tag {
                     None,
                     std::thread::available_parallelism()?.get(),
                     0,
+                    super::config::conflict_style(&repo),
                 )?;
                 if let Ok(txn) = std::sync::Arc::try_unwrap(txn.0) {
                     txn.into_inner().txn.commit()?
@@ -319,6 +335,8 @@ fn try_record<T: ChannelMutTxnT + TxnT + Send + Sync + 'static>(
         bail!("Channel not found: {}", channel)
     };
     let mut state = libpijul::RecordBuilder::new();
+    state.separators = super::config::diff_separators(repo)?;
+    state.conflict_style = super::config::conflict_style(repo);
     state.record(
         txn,
         libpijul::Algorithm::default(),
@@ -51,6 +51,11 @@ impl Unrecord {
         } else {
             bail!("No such channel: {:?}", channel_name);
         };
+        super::protected::check_channel_permission(
+            &repo,
+            channel_name,
+            pijul_config::ChannelOperation::Unrecord,
+        )?;
         let mut hashes = Vec::new();
 
         if self.change_id.is_empty() {
@@ -106,6 +111,13 @@ impl Unrecord {
         }
         debug!("changes: {:?}", changes);
         std::mem::drop(channel_);
+        let mut hook = pijul_config::HookContext {
+            hook: "pre_unrecord",
+            channel: Some(channel_name.to_string()),
+            changes: changes.iter().map(|(h, _, _)| h.to_base32()).collect(),
+            ..Default::default()
+        };
+        pijul_config::run_hooks(&repo.config.hooks.pre_unrecord, &repo.path, &hook)?;
         let pending_hash = if self.reset {
             super::pending(txn.clone(), &channel, &mut repo)?
         } else {
@@ -154,6 +166,7 @@ impl Unrecord {
                 None,
                 std::thread::available_parallelism()?.get(),
                 0,
+                super::config::conflict_style(&repo),
             )?;
         }
         if let Some(h) = pending_hash {
@@ -162,7 +175,10 @@ impl Unrecord {
                 repo.changes.del_change(&h)?;
             }
         }
+        hook.hook = "post_unrecord";
+        hook.state = super::hooks::hook_state(&*txn.read(), &*channel.read())?;
         txn.commit()?;
+        pijul_config::run_post_hooks(&repo.config.hooks.post_unrecord, &repo.path, &hook);
         Ok(())
     }
 }
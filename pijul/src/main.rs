@@ -48,12 +48,18 @@ pub enum SubCommand {
     /// Lists the transitive closure of the reverse dependency relation
     Dependents(Dependents),
 
+    /// Outputs the dependency graph of the changes of a channel, in Graphviz or JSON format
+    Deps(Deps),
+
     /// Manages different channels
     Channel(Channel),
 
     #[clap(hide = true)]
     Protocol(Protocol),
 
+    /// Serves repositories over HTTP
+    Serve(Serve),
+
     #[cfg(feature = "git")]
     /// Imports a git repository into pijul
     Git(Git),
@@ -106,9 +112,55 @@ pub enum SubCommand {
     /// with the latter taking precedence.
     Unrecord(Unrecord),
 
+    /// Records a new change undoing a change, or some of its hunks.
+    ///
+    /// Unlike `pijul unrecord`, this doesn't remove the change from
+    /// the channel, and can therefore be used to back out a change
+    /// that others may already have.
+    Revert(Revert),
+
+    /// Lists the conflicts of a channel, with the changes involved.
+    ///
+    /// Exits with a non-zero status if there are conflicts. Conflicts
+    /// are resolved by editing the conflicting files and recording the
+    /// result, which `--mark-resolved` does for the given paths.
+    Conflicts(Conflicts),
+
+    /// Resolves the conflicts inside files with a three-way merge tool.
+    ///
+    /// The sides of the conflicts of each file are written to temporary
+    /// files, and the merged file is copied back to the working copy.
+    Resolve(Resolve),
+
+    /// Shelves the unrecorded changes, to apply them back later.
+    ///
+    /// The changes are saved in the change store, and the working copy
+    /// is reset to the current channel.
+    Stash(Stash),
+
+    /// Finds the change of a channel's log that introduced a bug.
+    ///
+    /// The states of the channel are output to the working copy one
+    /// at a time, in a scratch channel, until the first bad change is
+    /// found. `pijul bisect run` automates this with a test command.
+    Bisect(Bisect),
+
+    /// Manages the index of the change store.
+    ///
+    /// The index caches the headers and dependencies of changes, so
+    /// that commands such as `pijul log` don't need to read the change
+    /// files. Once built, it is kept up to date automatically.
+    Index(Index),
+
     /// Applies changes to a channel
     Apply(Apply),
 
+    /// Applies changes from another channel, along with their missing dependencies.
+    ///
+    /// The dependencies that will come along and the conflicts that
+    /// would result are shown before asking for confirmation.
+    CherryPick(CherryPick),
+
     /// Manages remote repositories
     Remote(Remote),
 
@@ -257,8 +309,10 @@ async fn run(opts: Opts) -> Result<(), anyhow::Error> {
         SubCommand::Pull(pull) => pull.run().await,
         SubCommand::Change(change) => change.run(),
         SubCommand::Dependents(deps) => deps.run(),
+        SubCommand::Deps(deps) => deps.run(),
         SubCommand::Channel(channel) => channel.run(),
         SubCommand::Protocol(protocol) => protocol.run(),
+        SubCommand::Serve(serve) => serve.run().await,
         #[cfg(feature = "git")]
         SubCommand::Git(git) => git.run(),
         SubCommand::Move(move_cmd) => move_cmd.run(),
@@ -269,7 +323,14 @@ async fn run(opts: Opts) -> Result<(), anyhow::Error> {
         SubCommand::Debug(debug) => debug.run(),
         SubCommand::Fork(fork) => fork.run(),
         SubCommand::Unrecord(unrecord) => unrecord.run(),
+        SubCommand::Revert(revert) => revert.run().await,
+        SubCommand::Conflicts(conflicts) => conflicts.run().await,
+        SubCommand::Resolve(resolve) => resolve.run().await,
+        SubCommand::Stash(stash) => stash.run(),
+        SubCommand::Bisect(bisect) => bisect.run(),
+        SubCommand::Index(index) => index.run(),
         SubCommand::Apply(apply) => apply.run(),
+        SubCommand::CherryPick(cherry_pick) => cherry_pick.run(),
         SubCommand::Remote(remote) => remote.run(),
         SubCommand::Archive(archive) => archive.run().await,
         SubCommand::Credit(credit) => credit.run(),
@@ -0,0 +1,28 @@
+mod repo;
+
+use anyhow::Error;
+use repo::Env;
+
+#[test]
+fn apply_new_file() -> Result<(), Error> {
+    let env = Env::new()?;
+    let a = env.init("a")?;
+    env.record(&a, "a")?;
+    env.run(&env.path(""), &["clone", &a.to_string_lossy(), "b"])?;
+    let b = env.path("b");
+
+    std::fs::write(a.join("new"), "new\n")?;
+    env.run(&a, &["add", "new"])?;
+    let h = env.record(&a, "new")?;
+    let mut change = a.join(".pijul/changes");
+    change.push(&h[..2]);
+    change.push(&h[2..]);
+    change.set_extension("change");
+
+    // The new file isn't in the channel before the apply, but must
+    // still be output.
+    env.run(&b, &["apply", &change.to_string_lossy()])?;
+    assert_eq!(env.log(&b)?[0], h);
+    assert_eq!(std::fs::read_to_string(b.join("new"))?, "new\n");
+    Ok(())
+}
@@ -0,0 +1,107 @@
+mod repo;
+
+use std::path::Path;
+
+use anyhow::Error;
+use repo::Env;
+
+/// A repository where `a` gets one more line per change, returning
+/// the hashes of the changes.
+fn history(env: &Env, repo: &Path, n: usize) -> Result<Vec<String>, Error> {
+    let mut contents = String::new();
+    let mut hashes = Vec::new();
+    for i in 0..n {
+        contents.push_str(&format!("{}\n", i));
+        std::fs::write(repo.join("a"), &contents)?;
+        hashes.push(env.record(repo, &i.to_string())?);
+    }
+    Ok(hashes)
+}
+
+/// The channels of `repo`, sorted by name.
+fn channels(env: &Env, repo: &Path) -> Result<Vec<String>, Error> {
+    let mut channels: Vec<_> = env
+        .run(repo, &["channel"])?
+        .lines()
+        .map(|l| l.trim_start_matches("* ").trim().to_string())
+        .collect();
+    channels.sort();
+    Ok(channels)
+}
+
+#[test]
+fn unrecorded_changes() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("repo")?;
+    history(&env, &repo, 2)?;
+    std::fs::write(repo.join("a"), "unrecorded\n")?;
+    let output = env.output(&repo, &["bisect", "start"])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unrecorded changes"));
+    assert!(!repo.join(".pijul/bisect").exists());
+    Ok(())
+}
+
+#[test]
+fn existing_channels() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("repo")?;
+    let hashes = history(&env, &repo, 6)?;
+    // A channel with the name scratch channels used to have.
+    env.run(&repo, &["fork", "main-bisect-3"])?;
+    let before = channels(&env, &repo)?;
+
+    env.run(&repo, &["bisect", "start"])?;
+    env.run(&repo, &["bisect", "good", &hashes[0]])?;
+    env.run(&repo, &["bisect", "bad"])?;
+    let scratch: Vec<_> = channels(&env, &repo)?
+        .into_iter()
+        .filter(|c| !before.contains(c))
+        .collect();
+    assert_eq!(scratch.len(), 1);
+    assert!(!scratch[0].starts_with("main-bisect-"));
+
+    // The bisection doesn't overwrite channels created meanwhile.
+    let (prefix, current) = scratch[0].rsplit_once('-').unwrap();
+    let taken: Vec<String> = (0..=hashes.len() + 1)
+        .filter(|n| n.to_string() != current)
+        .map(|n| format!("{}-{}", prefix, n))
+        .collect();
+    for t in taken.iter() {
+        env.run(&repo, &["fork", "--channel", "main", t])?;
+    }
+    let output = env.output(&repo, &["bisect", "good"])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already exists"));
+    for t in taken.iter() {
+        env.run(&repo, &["channel", "delete", t])?;
+    }
+
+    env.run(&repo, &["bisect", "reset"])?;
+    assert_eq!(channels(&env, &repo)?, before);
+    assert_eq!(env.log(&repo)?.len(), hashes.len() + 1);
+    Ok(())
+}
+
+#[test]
+fn corrupted_state() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("repo")?;
+    let hashes = history(&env, &repo, 4)?;
+    env.run(&repo, &["bisect", "start"])?;
+    env.run(&repo, &["bisect", "good", &hashes[0]])?;
+
+    let path = repo.join(".pijul/bisect");
+    let mut state: serde_json::Value = serde_json::from_slice(&std::fs::read(&path)?)?;
+    for h in state["changes"].as_array_mut().unwrap().iter_mut().skip(1) {
+        *h = "NOTAHASH".into();
+    }
+    std::fs::write(&path, serde_json::to_vec(&state)?)?;
+
+    let output = env.output(&repo, &["bisect", "bad"])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid hash \"NOTAHASH\""));
+    assert!(!stderr.contains("panicked"));
+    Ok(())
+}
@@ -0,0 +1,82 @@
+mod repo;
+
+use anyhow::Error;
+use repo::Env;
+
+/// A repository with channels `main` and `other`, where `other` has
+/// three more changes than `main`: `b` edits file `a`, `c` adds file
+/// `c`, and `d` edits `a` again, depending on `b`. Returns the
+/// repository and the hashes of `b`, `c` and `d`.
+fn setup(env: &Env) -> Result<(std::path::PathBuf, [String; 3]), Error> {
+    let repo = env.init("repo")?;
+    env.record(&repo, "a")?;
+    env.run(&repo, &["fork", "other"])?;
+    env.run(&repo, &["channel", "switch", "other"])?;
+    std::fs::write(repo.join("a"), "a\nb\n")?;
+    let b = env.record(&repo, "b")?;
+    std::fs::write(repo.join("c"), "c\n")?;
+    env.run(&repo, &["add", "c"])?;
+    let c = env.record(&repo, "c")?;
+    std::fs::write(repo.join("a"), "a\nb\nd\n")?;
+    let d = env.record(&repo, "d")?;
+    env.run(&repo, &["channel", "switch", "main"])?;
+    Ok((repo, [b, c, d]))
+}
+
+fn channels(env: &Env, repo: &std::path::Path) -> Result<Vec<String>, Error> {
+    Ok(env
+        .run(repo, &["channel"])?
+        .lines()
+        .map(|l| l.trim_start_matches('*').trim().to_string())
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect())
+}
+
+#[test]
+fn cherry_pick() -> Result<(), Error> {
+    let env = Env::new()?;
+    let (repo, [b, c, d]) = setup(&env)?;
+    let log = env.log(&repo)?;
+
+    let out = env.run(&repo, &["cherry-pick", "-y", "--from-channel", "other", &c])?;
+    assert!(out.contains("No new conflicts"));
+    assert_eq!(std::fs::read_to_string(repo.join("c"))?, "c\n");
+    assert_eq!(std::fs::read_to_string(repo.join("a"))?, "a\n");
+    assert_eq!(
+        env.log(&repo)?,
+        [c.clone()]
+            .iter()
+            .chain(log.iter())
+            .cloned()
+            .collect::<Vec<_>>()
+    );
+
+    // Picking `d` also applies its dependency `b`.
+    let out = env.run(&repo, &["cherry-pick", "-y", "--from-channel", "other", &d])?;
+    assert!(out.contains("Dependencies that will also be applied"));
+    assert!(out.contains(&b));
+    assert_eq!(std::fs::read_to_string(repo.join("a"))?, "a\nb\nd\n");
+    assert_eq!(env.log(&repo)?.len(), log.len() + 3);
+
+    assert_eq!(channels(&env, &repo)?, ["main", "other"]);
+    Ok(())
+}
+
+#[test]
+fn cherry_pick_declined() -> Result<(), Error> {
+    let env = Env::new()?;
+    let (repo, [_, c, _]) = setup(&env)?;
+    let log = env.log(&repo)?;
+
+    // Without `-y` and with no terminal to confirm, nothing is applied,
+    // and the scratch channel used to find conflicts is gone.
+    assert!(!env
+        .output(&repo, &["cherry-pick", "--from-channel", "other", &c])?
+        .status
+        .success());
+    assert_eq!(env.run(&repo, &["ls"])?.trim(), "a");
+    assert_eq!(env.log(&repo)?, log);
+    assert_eq!(channels(&env, &repo)?, ["main", "other"]);
+    Ok(())
+}
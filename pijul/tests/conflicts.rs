@@ -0,0 +1,78 @@
+mod repo;
+
+use anyhow::Error;
+use repo::Env;
+
+#[test]
+fn list_conflicts() -> Result<(), Error> {
+    let env = Env::new()?;
+    let a = env.init("a")?;
+    env.record(&a, "a")?;
+    env.run(&env.path(""), &["clone", &a.to_string_lossy(), "b"])?;
+    let b = env.path("b");
+    std::fs::write(a.join("a"), "a\nx\n")?;
+    let h0 = env.record(&a, "x")?;
+    std::fs::write(b.join("a"), "a\ny\n")?;
+    let h1 = env.record(&b, "y")?;
+    assert!(env.output(&a, &["conflicts"])?.status.success());
+
+    env.run(&a, &["pull", "-a", &b.to_string_lossy()])?;
+    let output = env.output(&a, &["conflicts", "--json"])?;
+    assert!(!output.status.success());
+    let conflicts: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let conflicts = conflicts.as_array().unwrap();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0]["type"], "order");
+    assert_eq!(conflicts[0]["path"], "a");
+    let mut hashes: Vec<_> = conflicts[0]["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["hash"].as_str().unwrap().to_string())
+        .collect();
+    hashes.sort();
+    let mut expected = vec![h0, h1];
+    expected.sort();
+    assert_eq!(hashes, expected);
+
+    // Recording the edited file resolves the conflict.
+    std::fs::write(a.join("a"), "a\nx\ny\n")?;
+    env.record(&a, "resolve")?;
+    assert!(env.output(&a, &["conflicts"])?.status.success());
+    Ok(())
+}
+
+#[test]
+fn mark_resolved() -> Result<(), Error> {
+    let env = Env::new()?;
+    let a = env.init("a")?;
+    env.record(&a, "a")?;
+    env.run(&env.path(""), &["clone", &a.to_string_lossy(), "b"])?;
+    let b = env.path("b");
+    std::fs::write(a.join("a"), "a\nx\n")?;
+    env.record(&a, "x")?;
+    std::fs::write(b.join("a"), "a\ny\n")?;
+    env.record(&b, "y")?;
+    env.run(&a, &["pull", "-a", &b.to_string_lossy()])?;
+    let log = env.log(&a)?;
+
+    // The markers must be removed first.
+    let args = [
+        "conflicts",
+        "--mark-resolved",
+        "a",
+        "-m",
+        "resolve",
+        "--identity",
+        repo::IDENTITY,
+    ];
+    assert!(!env.output(&a, &args)?.status.success());
+    assert_eq!(env.log(&a)?, log);
+
+    std::fs::write(a.join("a"), "a\ny\nx\n")?;
+    env.run(&a, &args)?;
+    assert_eq!(env.log(&a)?.len(), log.len() + 1);
+    assert!(env.output(&a, &["conflicts"])?.status.success());
+    assert_eq!(std::fs::read_to_string(a.join("a"))?, "a\ny\nx\n");
+    Ok(())
+}
@@ -0,0 +1,147 @@
+mod repo;
+
+use anyhow::Error;
+use repo::Env;
+
+/// A repository where change `d` edits the files added by changes `b`
+/// and `c`. Returns the hashes of the root change and of changes `a`
+/// to `d`, in the order they were applied.
+fn setup(env: &Env) -> Result<(std::path::PathBuf, [String; 5]), Error> {
+    let repo = env.init("repo")?;
+    let a = env.record(&repo, "a")?;
+    std::fs::write(repo.join("b"), "b\n")?;
+    env.run(&repo, &["add", "b"])?;
+    let b = env.record(&repo, "b")?;
+    std::fs::write(repo.join("c"), "c\n")?;
+    env.run(&repo, &["add", "c"])?;
+    let c = env.record(&repo, "c")?;
+    std::fs::write(repo.join("b"), "b\nd\n")?;
+    std::fs::write(repo.join("c"), "c\nd\n")?;
+    let d = env.record(&repo, "d")?;
+    let root = env.log(&repo)?.pop().unwrap();
+    Ok((repo, [root, a, b, c, d]))
+}
+
+/// The output of `pijul deps --format json`, as pairs of a change and
+/// its sorted dependencies.
+fn deps(
+    env: &Env,
+    repo: &std::path::Path,
+    args: &[&str],
+) -> Result<Vec<(String, Vec<String>)>, Error> {
+    let mut cmd = vec!["deps", "--format", "json"];
+    cmd.extend(args);
+    let nodes: serde_json::Value = serde_json::from_str(&env.run(repo, &cmd)?)?;
+    Ok(nodes
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|n| {
+            let mut deps: Vec<String> = n["dependencies"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|d| d.as_str().unwrap().to_string())
+                .collect();
+            deps.sort();
+            (n["hash"].as_str().unwrap().to_string(), deps)
+        })
+        .collect())
+}
+
+fn sorted(mut v: Vec<String>) -> Vec<String> {
+    v.sort();
+    v
+}
+
+#[test]
+fn deps_json() -> Result<(), Error> {
+    let env = Env::new()?;
+    let (repo, [root, a, b, c, d]) = setup(&env)?;
+    assert_eq!(
+        deps(&env, &repo, &[])?,
+        [
+            (root.clone(), vec![]),
+            (a, vec![root.clone()]),
+            (b.clone(), vec![root.clone()]),
+            (c.clone(), vec![root]),
+            (d, sorted(vec![b, c])),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn deps_since() -> Result<(), Error> {
+    let env = Env::new()?;
+    let (repo, [_, _, _, c, d]) = setup(&env)?;
+    // The state after change `b`.
+    let state = env.run(&repo, &["log", "--state", "--limit", "1", "--offset", "2"])?;
+    let state = state
+        .lines()
+        .find_map(|l| l.strip_prefix("State: "))
+        .unwrap()
+        .to_string();
+    // Dependencies on changes applied before the state are left out.
+    assert_eq!(
+        deps(&env, &repo, &["--since", &state])?,
+        [(c.clone(), vec![]), (d, vec![c])]
+    );
+    assert!(!env
+        .output(&repo, &["deps", "--since", "NOTASTATE"])?
+        .status
+        .success());
+    Ok(())
+}
+
+#[test]
+fn deps_dot() -> Result<(), Error> {
+    let env = Env::new()?;
+    let (repo, [root, _, b, c, d]) = setup(&env)?;
+    let dot = env.run(&repo, &["deps"])?;
+    assert!(dot.starts_with("digraph changes {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert!(dot.contains(&format!(
+        "  \"{}\" [label=\"{}\\nd\\nTest\"];\n",
+        d,
+        &d[..8]
+    )));
+    assert!(dot.contains(&format!("  \"{}\" [label=\"{}\"];\n", root, &root[..8])));
+    for (from, to) in [(&d, &b), (&d, &c), (&b, &root), (&c, &root)] {
+        assert!(dot.contains(&format!("  \"{}\" -> \"{}\";\n", from, to)));
+    }
+    assert_eq!(dot.matches(" -> ").count(), 5);
+    Ok(())
+}
+
+#[test]
+fn log_graph() -> Result<(), Error> {
+    let env = Env::new()?;
+    let (repo, [root, a, b, c, d]) = setup(&env)?;
+    assert_eq!(
+        env.run(&repo, &["log", "--graph", "--hash-only"])?,
+        format!(
+            "*   {}\n\
+             | \\\n\
+             * | {}\n\
+             | * {}\n\
+             | | * {}\n\
+             | / /\n\
+             *     {}\n",
+            d, c, b, a, root
+        )
+    );
+
+    // The rows continue next to the other lines of each entry.
+    let log = env.run(&repo, &["log", "--graph"])?;
+    let mut lines = log.lines();
+    assert_eq!(lines.next(), Some(format!("*   Change {}", d).as_str()));
+    assert_eq!(lines.next(), Some("| \\ Author: Test ()"));
+    assert!(lines.next().unwrap().starts_with("| | Date: "));
+
+    assert!(!env
+        .output(&repo, &["log", "--graph", "--output-format", "json"])?
+        .status
+        .success());
+    Ok(())
+}
@@ -0,0 +1,73 @@
+mod repo;
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use repo::Env;
+
+/// Repositories `a` and `b`, where `a` and `b` have recorded
+/// conflicting changes to file `a` since `b` was cloned from `a`.
+/// Returns the hash of the change of `a`.
+fn diverge(env: &Env) -> Result<(PathBuf, PathBuf, String), Error> {
+    let a = env.init("a")?;
+    env.record(&a, "a")?;
+    env.run(&env.path(""), &["clone", &a.to_string_lossy(), "b"])?;
+    let b = env.path("b");
+    std::fs::write(a.join("a"), "a\nx\n")?;
+    let h = env.record(&a, "x")?;
+    std::fs::write(b.join("a"), "a\ny\n")?;
+    env.record(&b, "y")?;
+    Ok((a, b, h))
+}
+
+/// Check that the dry run `args` in `repo` announces the change `h`
+/// and a conflict, and leaves the log of `target` and its working
+/// copy unchanged.
+fn check_dry_run(
+    env: &Env,
+    repo: &Path,
+    args: &[&str],
+    target: &Path,
+    h: &str,
+) -> Result<(), Error> {
+    let log = env.log(target)?;
+    let contents = std::fs::read_to_string(target.join("a"))?;
+    let out = env.run(repo, args)?;
+    assert!(out.contains(h));
+    assert!(out.contains("New conflicts:"));
+    assert_eq!(env.log(target)?, log);
+    assert_eq!(std::fs::read_to_string(target.join("a"))?, contents);
+    assert!(env.output(target, &["diff", "--short"])?.stdout.is_empty());
+    Ok(())
+}
+
+#[test]
+fn pull_dry_run() -> Result<(), Error> {
+    let env = Env::new()?;
+    let (a, b, h) = diverge(&env)?;
+    let a = a.to_string_lossy();
+    check_dry_run(&env, &b, &["pull", "-a", "--dry-run", &a], &b, &h)
+}
+
+#[test]
+fn push_dry_run() -> Result<(), Error> {
+    let env = Env::new()?;
+    let (a, b, h) = diverge(&env)?;
+    // The change of the remote must be known locally to be taken into
+    // account by the dry run.
+    env.run(&a, &["pull", "-a", &b.to_string_lossy()])?;
+    let b_ = b.to_string_lossy();
+    check_dry_run(&env, &a, &["push", "-a", "--dry-run", &b_], &b, &h)
+}
+
+#[test]
+fn apply_dry_run() -> Result<(), Error> {
+    let env = Env::new()?;
+    let (a, b, h) = diverge(&env)?;
+    let change = a
+        .join(".pijul/changes")
+        .join(&h[..2])
+        .join(format!("{}.change", &h[2..]));
+    let change = change.to_string_lossy();
+    check_dry_run(&env, &b, &["apply", "--dry-run", &change], &b, &h)
+}
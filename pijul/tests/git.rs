@@ -0,0 +1,152 @@
+#![cfg(feature = "git")]
+
+mod repo;
+
+use anyhow::Error;
+use repo::Env;
+
+/// The messages of the commits of `branch`, most recent first.
+fn git_log(git: &git2::Repository, branch: &str) -> Result<Vec<String>, Error> {
+    let mut walk = git.revwalk()?;
+    walk.push_ref(&format!("refs/heads/{}", branch))?;
+    let mut log = Vec::new();
+    for oid in walk {
+        let commit = git.find_commit(oid?)?;
+        log.push(commit.message().unwrap_or("").to_string());
+    }
+    Ok(log)
+}
+
+#[test]
+fn export() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("repo")?;
+    env.record(&repo, "a")?;
+    let git_path = env.path("git");
+    let git_path = git_path.to_str().unwrap();
+    env.run(&repo, &["git", "export", git_path])?;
+
+    let git = git2::Repository::open(git_path)?;
+    // The root change is exported as an empty commit.
+    assert_eq!(git_log(&git, "main")?, ["a", ""]);
+    let tip = git.refname_to_id("refs/heads/main")?;
+
+    std::fs::write(repo.join("a"), "a\nb\n")?;
+    env.record(&repo, "b")?;
+    env.run(&repo, &["git", "export", git_path])?;
+    assert_eq!(git_log(&git, "main")?, ["b", "a", ""]);
+    let commit = git.find_commit(git.refname_to_id("refs/heads/main")?)?;
+    assert_eq!(commit.parent_id(0)?, tip);
+    let blob = commit.tree()?.get_name("a").unwrap().to_object(&git)?;
+    assert_eq!(blob.as_blob().unwrap().content(), b"a\nb\n");
+
+    // Exporting again gives the same commits.
+    assert!(env
+        .run(&repo, &["git", "export", git_path])?
+        .contains("up to date"));
+    assert_eq!(git.refname_to_id("refs/heads/main")?, commit.id());
+    Ok(())
+}
+
+#[test]
+fn export_foreign_commit() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("repo")?;
+    env.record(&repo, "a")?;
+    let git_path = env.path("git");
+    let git_path = git_path.to_str().unwrap();
+    env.run(&repo, &["git", "export", git_path])?;
+
+    // Commit to the branch from Git.
+    let git = git2::Repository::open(git_path)?;
+    let tip = git.find_commit(git.refname_to_id("refs/heads/main")?)?;
+    let sig = git2::Signature::now("Git", "git@example.com")?;
+    let foreign = git.commit(
+        Some("refs/heads/main"),
+        &sig,
+        &sig,
+        "foreign",
+        &tip.tree()?,
+        &[&tip],
+    )?;
+
+    std::fs::write(repo.join("a"), "a\nb\n")?;
+    env.record(&repo, "b")?;
+    assert!(!env
+        .output(&repo, &["git", "export", git_path])?
+        .status
+        .success());
+    assert_eq!(git.refname_to_id("refs/heads/main")?, foreign);
+
+    env.run(&repo, &["git", "export", "--force", git_path])?;
+    assert_eq!(git_log(&git, "main")?, ["b", "a", ""]);
+    Ok(())
+}
+
+/// Commit `contents` as file `a` to branch `main` of `git`.
+fn git_commit(git: &git2::Repository, contents: &str, message: &str) -> Result<(), Error> {
+    let blob = git.blob(contents.as_bytes())?;
+    let mut tree = git.treebuilder(None)?;
+    tree.insert("a", blob, git2::FileMode::Blob.into())?;
+    let tree = git.find_tree(tree.write()?)?;
+    let parent = git
+        .refname_to_id("refs/heads/main")
+        .ok()
+        .map(|p| git.find_commit(p))
+        .transpose()?;
+    let sig = git2::Signature::now("Git", "git@example.com")?;
+    git.commit(
+        Some("refs/heads/main"),
+        &sig,
+        &sig,
+        message,
+        &tree,
+        &parent.iter().collect::<Vec<_>>(),
+    )?;
+    git.set_head("refs/heads/main")?;
+    Ok(())
+}
+
+/// The id of `channel`, as sent to remotes.
+fn channel_id(env: &Env, repo: &std::path::Path, channel: &str) -> Result<String, Error> {
+    use std::io::Write;
+    let mut child = env
+        .command(repo, &["protocol", "--version", "3"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    writeln!(child.stdin.take().unwrap(), "id {}", channel)?;
+    let out = child.wait_with_output()?;
+    assert!(out.status.success());
+    Ok(String::from_utf8(out.stdout)?.trim().to_string())
+}
+
+#[test]
+fn import() -> Result<(), Error> {
+    let env = Env::new()?;
+    let git_path = env.path("git");
+    let git = git2::Repository::init(&git_path)?;
+    git_commit(&git, "a\n", "a")?;
+    let repo = env.path("repo");
+    std::fs::create_dir(&repo)?;
+    let git_path = git_path.to_str().unwrap();
+    env.run(&repo, &["git", "import", git_path])?;
+    assert_eq!(std::fs::read_to_string(repo.join("a"))?, "a\n");
+    assert_eq!(env.log(&repo)?.len(), 1);
+
+    let id = channel_id(&env, &repo, "main")?;
+    env.run(&repo, &["tag", "create", "-m", "v1"])?;
+    let tags = env.run(&repo, &["tag"])?;
+    assert!(tags.contains("v1"));
+
+    // Importing again updates the channel in place.
+    git_commit(&git, "a\nb\n", "b")?;
+    assert!(env
+        .run(&repo, &["git", "import", git_path])?
+        .contains("Imported branch main into channel main"));
+    assert_eq!(std::fs::read_to_string(repo.join("a"))?, "a\nb\n");
+    assert_eq!(env.log(&repo)?.len(), 2);
+    assert_eq!(channel_id(&env, &repo, "main")?, id);
+    assert_eq!(env.run(&repo, &["tag"])?, tags);
+    Ok(())
+}
@@ -0,0 +1,77 @@
+mod repo;
+
+use anyhow::Error;
+use repo::Env;
+
+#[test]
+fn failing_post_hook() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("repo")?;
+    std::fs::write(
+        repo.join(".pijul/config"),
+        "[hooks]\npost_record = [\"echo post hook failed; exit 1\"]\n",
+    )?;
+    // The change is recorded anyway, and the failure is reported.
+    let output = env.output(
+        &repo,
+        &["record", "-a", "-m", "a", "--identity", repo::IDENTITY],
+    )?;
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("post hook failed"));
+    assert_eq!(env.log(&repo)?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn failing_pre_hook() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("repo")?;
+    env.record(&repo, "a")?;
+    std::fs::write(repo.join("a"), "a\nb\n")?;
+    let h = env.record(&repo, "b")?;
+    std::fs::write(
+        repo.join(".pijul/config"),
+        "[hooks]\npre_unrecord = [\"exit 1\"]\n",
+    )?;
+    assert!(!env.output(&repo, &["unrecord", &h])?.status.success());
+    assert_eq!(env.log(&repo)?[0], h);
+    Ok(())
+}
+
+fn change_files(dir: &std::path::Path) -> Result<Vec<std::path::PathBuf>, Error> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(change_files(&path)?)
+        } else {
+            files.push(path)
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[test]
+fn rejected_push() -> Result<(), Error> {
+    let env = Env::new()?;
+    let server_repo = env.init("server")?;
+    env.record(&server_repo, "a")?;
+    std::fs::write(
+        server_repo.join(".pijul/config"),
+        "[hooks]\npre_apply = [\"exit 1\"]\n",
+    )?;
+    let server = env.serve(&server_repo, &["--allow-push"])?;
+    env.run(&env.path(""), &["clone", &server.url, "client"])?;
+    let client = env.path("client");
+
+    let log = env.log(&server_repo)?;
+    let changes = change_files(&server_repo.join(".pijul/changes"))?;
+    std::fs::write(client.join("a"), "a\nb\n")?;
+    env.record(&client, "b")?;
+    assert!(!env.output(&client, &["push", "-a"])?.status.success());
+    // Neither the channel nor the change store were modified.
+    assert_eq!(env.log(&server_repo)?, log);
+    assert_eq!(change_files(&server_repo.join(".pijul/changes"))?, changes);
+    Ok(())
+}
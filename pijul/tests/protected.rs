@@ -0,0 +1,191 @@
+mod repo;
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Output, Stdio};
+
+use anyhow::Error;
+use libpijul::key::SKey;
+use repo::Env;
+
+/// Protect channel `main` of `repo`, allowing only `key` to push to it.
+fn protect(repo: &Path, key: &str) -> Result<(), Error> {
+    std::fs::write(
+        repo.join(".pijul/config"),
+        format!(
+            "[[protected_channels]]\nchannels = \"ma*\"\npush = [\"{}\"]\n",
+            key
+        ),
+    )?;
+    Ok(())
+}
+
+/// Clone `server` and record a change in the clone, returning the hash
+/// and contents of the change.
+fn new_change(env: &Env, server: &Path) -> Result<(String, Vec<u8>), Error> {
+    env.run(
+        &env.path(""),
+        &["clone", &server.to_string_lossy(), "client"],
+    )?;
+    let client = env.path("client");
+    std::fs::write(client.join("a"), "a\nb\n")?;
+    let h = env.record(&client, "b")?;
+    let mut path = client.join(".pijul/changes");
+    let (a, b) = h.split_at(2);
+    path.push(a);
+    path.push(b);
+    path.set_extension("change");
+    Ok((h, std::fs::read(path)?))
+}
+
+/// Push a change to channel `main` of `server` with the SSH protocol,
+/// proving the identity `key` first if any. If `valid` is false, the
+/// proof is a signature of the wrong challenge.
+fn push_ssh(
+    env: &Env,
+    server: &Path,
+    (h, contents): &(String, Vec<u8>),
+    key: Option<(&SKey, bool)>,
+) -> Result<Output, Error> {
+    let mut child = env
+        .command(
+            server,
+            &[
+                "protocol",
+                "--version",
+                "3",
+                "--repository",
+                &server.to_string_lossy(),
+            ],
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    if let Some((key, valid)) = key {
+        writeln!(
+            stdin,
+            "challenge {}",
+            serde_json::to_string(&key.public_key())?
+        )?;
+        let mut challenge = String::new();
+        stdout.read_line(&mut challenge)?;
+        if !valid {
+            challenge.push('x')
+        }
+        writeln!(
+            stdin,
+            "prove {}",
+            key.sign_raw(challenge.trim().as_bytes())?
+        )?;
+    }
+    // The server stops reading after an invalid proof.
+    match writeln!(stdin, "apply main {} {}", h, contents.len())
+        .and_then(|_| stdin.write_all(contents))
+    {
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+        r => r?,
+    }
+    drop(stdin);
+    Ok(child.wait_with_output()?)
+}
+
+#[test]
+fn ssh_unproven_key() -> Result<(), Error> {
+    let env = Env::new()?;
+    let server = env.init("server")?;
+    env.record(&server, "a")?;
+    let key = SKey::generate(None);
+    protect(&server, &key.public_key().key)?;
+    let change = new_change(&env, &server)?;
+    let log = env.log(&server)?;
+
+    let output = push_ssh(&env, &server, &change, None)?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("must be proven"));
+    assert_eq!(env.log(&server)?, log);
+
+    let output = push_ssh(&env, &server, &change, Some((&key, false)))?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid proof"));
+    assert_eq!(env.log(&server)?, log);
+
+    // A proven key that isn't allowed.
+    let other = SKey::generate(None);
+    let output = push_ssh(&env, &server, &change, Some((&other, true)))?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Not allowed"));
+    assert_eq!(env.log(&server)?, log);
+
+    let output = push_ssh(&env, &server, &change, Some((&key, true)))?;
+    assert!(output.status.success());
+    assert_eq!(env.log(&server)?[0], change.0);
+    Ok(())
+}
+
+#[test]
+fn http_push_protected() -> Result<(), Error> {
+    let env = Env::new()?;
+    let server_repo = env.init("server")?;
+    env.record(&server_repo, "a")?;
+    protect(&server_repo, &env.public_key)?;
+    let server = env.serve(&server_repo, &["--allow-push"])?;
+    env.run(&env.path(""), &["clone", &server.url, "client"])?;
+    let client = env.path("client");
+    let log = env.log(&server_repo)?;
+
+    // HTTP can't prove identities, even the allowed one is refused.
+    std::fs::write(client.join("a"), "a\nb\n")?;
+    env.record(&client, "b")?;
+    let output = env.output(&client, &["push", "-a"])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("protected"));
+    assert_eq!(env.log(&server_repo)?, log);
+    Ok(())
+}
+
+#[test]
+fn local_unrecord_and_tag() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("repo")?;
+    env.record(&repo, "a")?;
+    std::fs::write(repo.join("a"), "a\nb\n")?;
+    let h = env.record(&repo, "b")?;
+    std::fs::write(
+        repo.join(".pijul/config"),
+        format!(
+            "[[protected_channels]]\nchannels = \"main\"\nunrecord = [\"{}\"]\ntag = [\"{}\"]\n",
+            env.public_key,
+            SKey::generate(None).public_key().key
+        ),
+    )?;
+    let output = env.output(&repo, &["tag", "create", "-m", "t"])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("protected"));
+    env.run(&repo, &["unrecord", &h])?;
+    assert_ne!(env.log(&repo)?[0], h);
+    Ok(())
+}
+
+#[test]
+fn local_record() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("repo")?;
+    env.record(&repo, "a")?;
+    protect(&repo, &SKey::generate(None).public_key().key)?;
+    std::fs::write(repo.join("a"), "a\nb\n")?;
+    let output = env.output(
+        &repo,
+        &["record", "-a", "-m", "b", "--identity", repo::IDENTITY],
+    )?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("protected"));
+    assert_eq!(env.log(&repo)?.len(), 2);
+
+    protect(&repo, &env.public_key)?;
+    env.record(&repo, "b")?;
+    assert_eq!(env.log(&repo)?.len(), 3);
+    Ok(())
+}
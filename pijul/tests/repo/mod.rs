@@ -0,0 +1,151 @@
+//! Helpers for the tests running pijul on temporary repositories.
+// Each test file only uses some of these.
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+
+use anyhow::{bail, Error};
+use libpijul::key::SKey;
+use tempfile::TempDir;
+
+pub const IDENTITY: &str = "test";
+
+/// A temporary directory with a global configuration containing a
+/// single identity, in which the `pijul` binary is run.
+pub struct Env {
+    dir: TempDir,
+    pub public_key: String,
+}
+
+impl Env {
+    pub fn new() -> Result<Self, Error> {
+        let dir = tempfile::tempdir()?;
+        let key = SKey::generate(None);
+        let identity = pijul_identity::Complete::new(
+            IDENTITY.to_string(),
+            pijul_identity::Config::from(pijul_config::Author {
+                display_name: "Test".to_string(),
+                ..Default::default()
+            }),
+            key.public_key(),
+            None,
+        );
+        let identity_dir = dir.path().join("config/identities").join(IDENTITY);
+        std::fs::create_dir_all(&identity_dir)?;
+        std::fs::write(
+            identity_dir.join("identity.toml"),
+            toml::to_string_pretty(&identity)?,
+        )?;
+        std::fs::write(
+            identity_dir.join("secret_key.json"),
+            serde_json::to_string_pretty(&key.save(None))?,
+        )?;
+        Ok(Self {
+            public_key: key.public_key().key,
+            dir,
+        })
+    }
+
+    /// Write the global configuration.
+    pub fn set_config(&self, config: &str) -> Result<(), Error> {
+        std::fs::write(self.path("config/config.toml"), config)?;
+        Ok(())
+    }
+
+    pub fn path(&self, p: &str) -> PathBuf {
+        self.dir.path().join(p)
+    }
+
+    pub fn command(&self, cwd: &Path, args: &[&str]) -> Command {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_pijul"));
+        cmd.env("PIJUL_CONFIG_DIR", self.path("config"))
+            .env("HOME", self.dir.path())
+            .current_dir(cwd)
+            .args(args)
+            .stdin(Stdio::null());
+        cmd
+    }
+
+    pub fn output(&self, cwd: &Path, args: &[&str]) -> Result<Output, Error> {
+        println!("Running pijul {}", args.join(" "));
+        Ok(self.command(cwd, args).output()?)
+    }
+
+    /// Run `pijul` with `args` in `cwd`, returning its standard
+    /// output, or an error if it failed.
+    pub fn run(&self, cwd: &Path, args: &[&str]) -> Result<String, Error> {
+        let output = self.output(cwd, args)?;
+        if !output.status.success() {
+            bail!(
+                "pijul {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr)
+            )
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    /// Create a repository called `name`, with a file `a`.
+    pub fn init(&self, name: &str) -> Result<PathBuf, Error> {
+        let repo = self.path(name);
+        std::fs::create_dir_all(&repo)?;
+        self.run(&repo, &["init"])?;
+        std::fs::write(repo.join("a"), "a\n")?;
+        self.run(&repo, &["add", "a"])?;
+        Ok(repo)
+    }
+
+    /// Record all the changes in `repo`, returning the hash.
+    pub fn record(&self, repo: &Path, message: &str) -> Result<String, Error> {
+        let out = self.run(
+            repo,
+            &["record", "-a", "-m", message, "--identity", IDENTITY],
+        )?;
+        if let Some(h) = out.trim().strip_prefix("Hash: ") {
+            Ok(h.to_string())
+        } else {
+            bail!("No hash in record output: {:?}", out)
+        }
+    }
+
+    /// The hashes of the log of `repo`, most recent first.
+    pub fn log(&self, repo: &Path) -> Result<Vec<String>, Error> {
+        Ok(self
+            .run(repo, &["log", "--hash-only"])?
+            .lines()
+            .map(|l| l.to_string())
+            .collect())
+    }
+
+    /// Serve `repo` over HTTP on a free port.
+    pub fn serve(&self, repo: &Path, args: &[&str]) -> Result<Server, Error> {
+        let mut cmd = self.command(repo, &["serve", "--address", "127.0.0.1:0"]);
+        let mut child = cmd.args(args).stderr(Stdio::piped()).spawn()?;
+        let mut line = String::new();
+        BufReader::new(child.stderr.take().unwrap()).read_line(&mut line)?;
+        if let Some(url) = line.trim().strip_prefix("Listening on ") {
+            Ok(Server {
+                url: url.to_string(),
+                child,
+            })
+        } else {
+            child.kill()?;
+            bail!("Unexpected output from pijul serve: {:?}", line)
+        }
+    }
+}
+
+/// A running `pijul serve`, killed when dropped.
+pub struct Server {
+    pub url: String,
+    child: Child,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.child.kill().unwrap_or(());
+        self.child.wait().unwrap_or_default();
+    }
+}
@@ -0,0 +1,83 @@
+mod repo;
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use repo::Env;
+
+/// A repository with files `a` and `b`, and a change modifying both,
+/// returning the hash of that change.
+fn setup(env: &Env) -> Result<(PathBuf, String), Error> {
+    let repo = env.init("repo")?;
+    std::fs::write(repo.join("b"), "b\n")?;
+    env.run(&repo, &["add", "b"])?;
+    env.record(&repo, "a and b")?;
+    std::fs::write(repo.join("a"), "a\nx\n")?;
+    std::fs::write(repo.join("b"), "y\n")?;
+    let h = env.record(&repo, "x and y")?;
+    Ok((repo, h))
+}
+
+fn revert(env: &Env, repo: &Path, args: &[&str]) -> Result<std::process::Output, Error> {
+    let mut a = vec!["revert", "--identity", repo::IDENTITY];
+    a.extend(args);
+    env.output(repo, &a)
+}
+
+#[test]
+fn revert_path() -> Result<(), Error> {
+    let env = Env::new()?;
+    let (repo, h) = setup(&env)?;
+    assert!(revert(&env, &repo, &[&h, "a"])?.status.success());
+    // Only the hunk on `a` is inverted.
+    assert_eq!(std::fs::read_to_string(repo.join("a"))?, "a\n");
+    assert_eq!(std::fs::read_to_string(repo.join("b"))?, "y\n");
+    let log = env.log(&repo)?;
+    assert_eq!(log.len(), 4);
+    assert_eq!(log[1], h);
+    assert!(env.run(&repo, &["diff", "--short"])?.trim().is_empty());
+    Ok(())
+}
+
+#[test]
+fn revert_hooks() -> Result<(), Error> {
+    let env = Env::new()?;
+    let (repo, h) = setup(&env)?;
+    std::fs::write(
+        repo.join(".pijul/config"),
+        "[hooks]\nrecord = [\"exit 1\"]\n",
+    )?;
+    assert!(!revert(&env, &repo, &[&h])?.status.success());
+    assert_eq!(env.log(&repo)?[0], h);
+
+    std::fs::write(
+        repo.join(".pijul/config"),
+        "[hooks]\npost_record = [\"echo $PIJUL_CHANGES > post_record\"]\n",
+    )?;
+    let output = revert(&env, &repo, &[&h])?;
+    assert!(output.status.success());
+    let revert_hash = String::from_utf8(output.stdout)?
+        .trim()
+        .trim_start_matches("Hash: ")
+        .to_string();
+    assert_eq!(
+        std::fs::read_to_string(repo.join("post_record"))?.trim(),
+        revert_hash
+    );
+    Ok(())
+}
+
+#[test]
+fn revert_protected() -> Result<(), Error> {
+    let env = Env::new()?;
+    let (repo, h) = setup(&env)?;
+    std::fs::write(
+        repo.join(".pijul/config"),
+        "[[protected_channels]]\nchannels = \"main\"\npush = [\"someone else\"]\n",
+    )?;
+    let output = revert(&env, &repo, &[&h])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("protected"));
+    assert_eq!(env.log(&repo)?[0], h);
+    Ok(())
+}
@@ -0,0 +1,65 @@
+mod repo;
+
+use std::io::{Read, Write};
+
+use anyhow::Error;
+use repo::Env;
+
+#[test]
+fn clone_and_push() -> Result<(), Error> {
+    let env = Env::new()?;
+    let server_repo = env.init("server")?;
+    let h0 = env.record(&server_repo, "a")?;
+    let server = env.serve(&server_repo, &["--allow-push"])?;
+
+    env.run(&env.path(""), &["clone", &server.url, "client"])?;
+    let client = env.path("client");
+    assert_eq!(std::fs::read_to_string(client.join("a"))?, "a\n");
+    assert!(env.log(&client)?.contains(&h0));
+
+    std::fs::write(client.join("a"), "a\nb\n")?;
+    let h1 = env.record(&client, "b")?;
+    env.run(&client, &["push", "-a"])?;
+    assert_eq!(env.log(&server_repo)?[0], h1);
+    Ok(())
+}
+
+#[test]
+fn unknown_query() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("server")?;
+    env.record(&repo, "a")?;
+    let server = env.serve(&repo, &[])?;
+
+    let status = |query: &str| -> Result<String, Error> {
+        let mut s = std::net::TcpStream::connect(server.url.trim_start_matches("http://"))?;
+        write!(s, "GET /{} HTTP/1.0\r\n\r\n", query)?;
+        let mut resp = Vec::new();
+        s.read_to_end(&mut resp)?;
+        let status = resp.split(|&c| c == b'\r').next().unwrap_or(b"");
+        Ok(String::from_utf8_lossy(status).to_string())
+    };
+    assert!(status("?channel=main")?.contains(" 200 "));
+    assert!(status("")?.contains(" 400 "));
+    assert!(status("?channel=main&unknown=1")?.contains(" 400 "));
+    Ok(())
+}
+
+#[test]
+fn clone_without_main() -> Result<(), Error> {
+    let env = Env::new()?;
+    let server_repo = env.init("server")?;
+    let h0 = env.record(&server_repo, "a")?;
+    env.run(&server_repo, &["channel", "rename", "main", "other"])?;
+    let server = env.serve(&server_repo, &[])?;
+
+    // Changes are downloaded without naming a channel.
+    env.run(
+        &env.path(""),
+        &["clone", "--channel", "other", &server.url, "client"],
+    )?;
+    let client = env.path("client");
+    assert_eq!(std::fs::read_to_string(client.join("a"))?, "a\n");
+    assert!(env.log(&client)?.contains(&h0));
+    Ok(())
+}
@@ -0,0 +1,56 @@
+mod repo;
+
+use anyhow::Error;
+use repo::Env;
+
+/// Clone `repo` in a new environment with `require_signatures` set
+/// to `require`, and whose identities don't include the author.
+fn clone_unknown(require: &str) -> Result<std::process::Output, Error> {
+    let author = Env::new()?;
+    let repo = author.init("repo")?;
+    author.record(&repo, "a")?;
+
+    let env = Env::new()?;
+    env.set_config(&format!("require_signatures = {:?}\n", require))?;
+    env.output(&env.path(""), &["clone", repo.to_str().unwrap(), "clone"])
+}
+
+#[test]
+fn deny_unknown_key() -> Result<(), Error> {
+    let output = clone_unknown("deny")?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown key"));
+    Ok(())
+}
+
+#[test]
+fn warn_unknown_key() -> Result<(), Error> {
+    let output = clone_unknown("warn")?;
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown key"));
+    Ok(())
+}
+
+#[test]
+fn deny_valid() -> Result<(), Error> {
+    let env = Env::new()?;
+    env.set_config("require_signatures = \"deny\"\n")?;
+    let repo = env.init("repo")?;
+    env.record(&repo, "a")?;
+    // The root change isn't signed, and is not reported.
+    let output = env.output(&env.path(""), &["clone", repo.to_str().unwrap(), "clone"])?;
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Change "));
+    Ok(())
+}
+
+#[test]
+fn log_verify() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("repo")?;
+    env.record(&repo, "a")?;
+    let log = env.run(&repo, &["log", "--verify"])?;
+    assert_eq!(log.matches("Signature: valid signature").count(), 1);
+    assert!(!log.contains("unsigned"));
+    Ok(())
+}
@@ -0,0 +1,74 @@
+mod repo;
+
+use anyhow::Error;
+use repo::Env;
+
+#[test]
+fn push_pop() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("repo")?;
+    env.record(&repo, "a")?;
+    let log = env.log(&repo)?;
+
+    std::fs::write(repo.join("a"), "a\nb\n")?;
+    std::fs::write(repo.join("c"), "c\n")?;
+    env.run(&repo, &["add", "c"])?;
+    env.run(&repo, &["stash", "push", "-m", "wip"])?;
+    assert_eq!(std::fs::read_to_string(repo.join("a"))?, "a\n");
+    assert!(!repo.join("c").exists());
+    assert_eq!(env.log(&repo)?, log);
+    let list = env.run(&repo, &["stash", "list"])?;
+    assert_eq!(list.lines().count(), 1);
+    assert!(list.contains("wip"));
+
+    env.run(&repo, &["stash", "pop"])?;
+    assert_eq!(std::fs::read_to_string(repo.join("a"))?, "a\nb\n");
+    assert_eq!(std::fs::read_to_string(repo.join("c"))?, "c\n");
+    assert_eq!(env.log(&repo)?, log);
+    assert!(env.run(&repo, &["stash", "list"])?.is_empty());
+
+    // The changes are unrecorded again.
+    env.record(&repo, "b")?;
+    assert_eq!(env.log(&repo)?.len(), log.len() + 1);
+    Ok(())
+}
+
+#[test]
+fn drop() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("repo")?;
+    env.record(&repo, "a")?;
+
+    std::fs::write(repo.join("a"), "a\nb\n")?;
+    env.run(&repo, &["stash"])?;
+    std::fs::write(repo.join("a"), "a\nc\n")?;
+    env.run(&repo, &["stash"])?;
+    assert_eq!(env.run(&repo, &["stash", "list"])?.lines().count(), 2);
+
+    env.run(&repo, &["stash", "drop"])?;
+    assert_eq!(env.run(&repo, &["stash", "list"])?.lines().count(), 1);
+    env.run(&repo, &["stash", "pop"])?;
+    assert_eq!(std::fs::read_to_string(repo.join("a"))?, "a\nb\n");
+    assert!(!env.output(&repo, &["stash", "drop"])?.status.success());
+    Ok(())
+}
+
+#[test]
+fn pop_conflicts() -> Result<(), Error> {
+    let env = Env::new()?;
+    let repo = env.init("repo")?;
+    env.record(&repo, "a")?;
+
+    std::fs::write(repo.join("a"), "a\nb\n")?;
+    env.run(&repo, &["stash"])?;
+    std::fs::write(repo.join("a"), "a\nc\n")?;
+    env.record(&repo, "c")?;
+
+    let output = env.output(&repo, &["stash", "pop"])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("keeping it in the stash"));
+    assert_eq!(env.run(&repo, &["stash", "list"])?.lines().count(), 1);
+    let a = std::fs::read_to_string(repo.join("a"))?;
+    assert!(a.contains("b\n") && a.contains("c\n"));
+    Ok(())
+}